@@ -0,0 +1,817 @@
+use base64::{engine::general_purpose, Engine};
+use std::time::Duration;
+
+use reqwest::{
+    header::{ACCEPT, CONTENT_TYPE, RETRY_AFTER},
+    multipart, Method, RequestBuilder, Response, StatusCode,
+};
+use serde::de::DeserializeOwned;
+
+pub use cch23::{
+    audit::{AuditEntity, AuditEntry, AuditOperation, AuditPage, AuditParams},
+    auth::{ApiKeyInfo, CreatedApiKey, NewApiKey, Role},
+    day12::UlidsWeekdayResult,
+    day13::{
+        IngestMode, IngestReport, Order, OrderFeedEvent, OrderPopularResponse, OrderTotalResponse,
+        RejectReason, RejectedOrder,
+    },
+    day14::UnsafeRequest,
+    day15::{CheckGameResult, CheckNiceResult, Password},
+    day18::{
+        AnalyticsBucket, AnalyticsParams, RankedGift, Region, RegionAnalytics, RegionNode,
+        RegionParent, RegionTopList, RegionTreeParams, RegionsTotalResponse,
+    },
+    day19::ChatMessage,
+    day4::{ContestResult, MissingStats, Reindeer},
+    day5::Pagination,
+    day6::CountElvesResponse,
+    day7::{BakeResponse, Recipe},
+    error::ProblemDetails,
+    gifts::{CategoryTotal, Gift, GiftMetric, MetricParams, NewGift},
+    health::{CheckResult, HealthStatus, LivenessReport, ReadinessReport},
+    orders::{OrderFields, OrderListParams, OrderPage, OrderPatch, OrderSort, SortDirection},
+    reindeer::{Contest, ContestParams, LeaderboardEntry, LeaderboardParams, NewContest},
+    snapshots::{NewSnapshot, OrderChange, Snapshot, SnapshotDiff},
+    tenant::TENANT_HEADER,
+    transfer::{Format, RegionImportReport},
+};
+
+pub use crate::ws::{ChatRoom, OrderFeed, PingGame};
+
+mod ws;
+
+#[derive(Debug)]
+pub enum ClientError {
+    Http(reqwest::Error),
+    Api {
+        status: StatusCode,
+        problem: Option<ProblemDetails>,
+        body: String,
+        retry_after: Option<Duration>,
+    },
+    WebSocket(tokio_tungstenite::tungstenite::Error),
+    Json(serde_json::Error),
+}
+
+impl std::fmt::Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientError::Http(err) => write!(f, "request failed: {}", err),
+            ClientError::Api {
+                status,
+                problem: Some(problem),
+                ..
+            } => write!(f, "{}: {}", status, problem.detail),
+            ClientError::Api { status, body, .. } => write!(f, "{}: {}", status, body),
+            ClientError::WebSocket(err) => write!(f, "websocket failed: {}", err),
+            ClientError::Json(err) => write!(f, "invalid json: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(err: reqwest::Error) -> Self {
+        Self::Http(err)
+    }
+}
+
+impl From<tokio_tungstenite::tungstenite::Error> for ClientError {
+    fn from(err: tokio_tungstenite::tungstenite::Error) -> Self {
+        Self::WebSocket(err)
+    }
+}
+
+impl From<serde_json::Error> for ClientError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ClientError>;
+
+#[derive(Clone, Debug)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    tenant: Option<String>,
+}
+
+impl Client {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_http_client(reqwest::Client::new(), base_url)
+    }
+
+    pub fn with_http_client(http: reqwest::Client, base_url: impl Into<String>) -> Self {
+        let base_url = base_url.into().trim_end_matches('/').to_string();
+        Self {
+            http,
+            base_url,
+            api_key: None,
+            tenant: None,
+        }
+    }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    // Requests name the tenant in the `X-Tenant` header, which keys bound to a tenant can omit.
+    pub fn with_tenant(mut self, tenant: impl Into<String>) -> Self {
+        self.tenant = Some(tenant.into());
+        self
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let mut request = self.http.request(method, self.url(path));
+        if let Some(tenant) = &self.tenant {
+            request = request.header(TENANT_HEADER, tenant);
+        }
+        match &self.api_key {
+            Some(api_key) => request.bearer_auth(api_key),
+            None => request,
+        }
+    }
+
+    fn ws_url(&self, path: &str) -> String {
+        let url = self.url(path);
+        if let Some(rest) = url.strip_prefix("https://") {
+            format!("wss://{}", rest)
+        } else if let Some(rest) = url.strip_prefix("http://") {
+            format!("ws://{}", rest)
+        } else {
+            url
+        }
+    }
+
+    async fn check(response: Response) -> Result<Response> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok())
+            .map(Duration::from_secs);
+        let body = response.text().await?;
+        let problem = serde_json::from_str(&body).ok();
+        Err(ClientError::Api {
+            status,
+            problem,
+            body,
+            retry_after,
+        })
+    }
+
+    async fn text(response: Response) -> Result<String> {
+        Ok(Self::check(response).await?.text().await?)
+    }
+
+    async fn json<T: DeserializeOwned>(response: Response) -> Result<T> {
+        Ok(Self::check(response).await?.json().await?)
+    }
+
+    async fn empty(response: Response) -> Result<()> {
+        Self::check(response).await?;
+        Ok(())
+    }
+
+    async fn get_text(&self, path: &str) -> Result<String> {
+        Self::text(self.request(Method::GET, path).send().await?).await
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        Self::json(self.request(Method::GET, path).send().await?).await
+    }
+
+    async fn post_body(&self, path: &str, body: impl Into<reqwest::Body>) -> Result<Response> {
+        Ok(self.request(Method::POST, path).body(body).send().await?)
+    }
+
+    async fn post_json(&self, path: &str, body: &impl serde::Serialize) -> Result<Response> {
+        Ok(self.request(Method::POST, path).json(body).send().await?)
+    }
+
+    fn recipe_cookie(recipe: &str) -> String {
+        format!("recipe={}", general_purpose::STANDARD.encode(recipe))
+    }
+
+    pub async fn hello_world(&self) -> Result<String> {
+        self.get_text("/").await
+    }
+
+    pub async fn fake_error(&self) -> Result<()> {
+        Self::empty(self.request(Method::GET, "/-1/error").send().await?).await
+    }
+
+    pub async fn recalibrate_packet_ids(&self, packet_ids: &[i32]) -> Result<String> {
+        let packets = packet_ids
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .join("/");
+        self.get_text(&format!("/1/{}", packets)).await
+    }
+
+    pub async fn strength(&self, reindeers: &[Reindeer]) -> Result<String> {
+        Self::text(self.post_json("/4/strength", &reindeers).await?).await
+    }
+
+    pub async fn contest(&self, reindeers: &[Reindeer]) -> Result<ContestResult> {
+        Self::json(self.post_json("/4/contest", &reindeers).await?).await
+    }
+
+    pub async fn contest_with(
+        &self,
+        reindeers: &[Reindeer],
+        missing: MissingStats,
+    ) -> Result<ContestResult> {
+        Self::json(
+            self.request(Method::POST, "/4/contest")
+                .query(&[("missing", missing)])
+                .json(&reindeers)
+                .send()
+                .await?,
+        )
+        .await
+    }
+
+    pub async fn paginate(&self, pagination: &Pagination, names: &[String]) -> Result<String> {
+        let response = self
+            .request(Method::POST, "/5")
+            .query(pagination)
+            .json(names)
+            .send()
+            .await?;
+        Self::text(response).await
+    }
+
+    pub async fn count_elves(&self, text: impl Into<String>) -> Result<CountElvesResponse> {
+        Self::json(self.post_body("/6", text.into()).await?).await
+    }
+
+    pub async fn decode_recipe(&self, recipe: &str) -> Result<String> {
+        let response = self
+            .request(Method::GET, "/7/decode")
+            .header(reqwest::header::COOKIE, Self::recipe_cookie(recipe))
+            .send()
+            .await?;
+        Self::text(response).await
+    }
+
+    pub async fn bake(&self, recipe: &Recipe) -> Result<BakeResponse> {
+        let recipe = serde_json::to_string(recipe)?;
+        let response = self
+            .request(Method::GET, "/7/bake")
+            .header(reqwest::header::COOKIE, Self::recipe_cookie(&recipe))
+            .send()
+            .await?;
+        Self::json(response).await
+    }
+
+    pub async fn pokemon_weight(&self, pokedex_number: usize) -> Result<String> {
+        self.get_text(&format!("/8/weight/{}", pokedex_number))
+            .await
+    }
+
+    pub async fn pokemon_drop(&self, pokedex_number: usize) -> Result<String> {
+        self.get_text(&format!("/8/drop/{}", pokedex_number)).await
+    }
+
+    pub async fn asset(&self, path: &str) -> Result<Vec<u8>> {
+        let response = self
+            .request(
+                Method::GET,
+                &format!("/11/assets/{}", path.trim_start_matches('/')),
+            )
+            .send()
+            .await?;
+        Ok(Self::check(response).await?.bytes().await?.to_vec())
+    }
+
+    pub async fn red_pixels(&self, file_name: &str, image: Vec<u8>) -> Result<String> {
+        let part = multipart::Part::bytes(image).file_name(file_name.to_string());
+        let form = multipart::Form::new().part("image", part);
+        let response = self
+            .request(Method::POST, "/11/red_pixels")
+            .multipart(form)
+            .send()
+            .await?;
+        Self::text(response).await
+    }
+
+    pub async fn save_packet(&self, packet_id: &str) -> Result<()> {
+        Self::empty(
+            self.post_body(&format!("/12/save/{}", packet_id), "")
+                .await?,
+        )
+        .await
+    }
+
+    pub async fn load_packet(&self, packet_id: &str) -> Result<String> {
+        self.get_text(&format!("/12/load/{}", packet_id)).await
+    }
+
+    pub async fn ulids(&self, ulids: &[String]) -> Result<Vec<String>> {
+        Self::json(self.post_json("/12/ulids", &ulids).await?).await
+    }
+
+    pub async fn ulids_weekday(
+        &self,
+        weekday: usize,
+        ulids: &[String],
+    ) -> Result<UlidsWeekdayResult> {
+        Self::json(
+            self.post_json(&format!("/12/ulids/{}", weekday), &ulids)
+                .await?,
+        )
+        .await
+    }
+
+    pub async fn sql(&self) -> Result<String> {
+        self.get_text("/13/sql").await
+    }
+
+    pub async fn reset_orders(&self) -> Result<()> {
+        Self::empty(self.post_body("/13/reset", "").await?).await
+    }
+
+    // Saves the orders and regions as a snapshot before deleting them.
+    pub async fn reset_orders_into(&self, snapshot: &str) -> Result<()> {
+        Self::empty(
+            self.request(Method::POST, "/13/reset")
+                .query(&[("snapshot", snapshot)])
+                .send()
+                .await?,
+        )
+        .await
+    }
+
+    // A batch rejected as a whole comes back as 422 with the report, so it is not an error here.
+    // Rejected batches answer 422 with a report rather than a problem.
+    async fn ingest_report(response: Response) -> Result<IngestReport> {
+        if response.status() == StatusCode::UNPROCESSABLE_ENTITY {
+            return Ok(response.json().await?);
+        }
+        Self::json(response).await
+    }
+
+    async fn ingest(&self, path: &str, orders: &[Order], mode: IngestMode) -> Result<IngestReport> {
+        let response = self
+            .request(Method::POST, path)
+            .query(&[("mode", mode)])
+            .json(&orders)
+            .send()
+            .await?;
+        Self::ingest_report(response).await
+    }
+
+    pub async fn add_orders(&self, orders: &[Order], mode: IngestMode) -> Result<IngestReport> {
+        self.ingest("/13/orders", orders, mode).await
+    }
+
+    pub async fn orders_total(&self) -> Result<OrderTotalResponse> {
+        self.get_json("/13/orders/total").await
+    }
+
+    pub async fn orders_popular(&self) -> Result<OrderPopularResponse> {
+        self.get_json("/13/orders/popular").await
+    }
+
+    pub async fn orders_total_by(&self, by: GiftMetric) -> Result<OrderTotalResponse> {
+        self.get_by("/13/orders/total", by).await
+    }
+
+    pub async fn orders_popular_by(&self, by: GiftMetric) -> Result<OrderPopularResponse> {
+        self.get_by("/13/orders/popular", by).await
+    }
+
+    async fn get_by<T: DeserializeOwned>(&self, path: &str, by: GiftMetric) -> Result<T> {
+        let params = MetricParams { by };
+        Self::json(
+            self.request(Method::GET, path)
+                .query(&params)
+                .send()
+                .await?,
+        )
+        .await
+    }
+
+    pub async fn order_feed(&self) -> Result<OrderFeed> {
+        OrderFeed::connect(
+            &self.ws_url("/13/orders/feed/ws"),
+            self.api_key.as_deref(),
+            self.tenant.as_deref(),
+        )
+        .await
+    }
+
+    pub async fn list_orders(&self, params: &OrderListParams) -> Result<OrderPage> {
+        Self::json(
+            self.request(Method::GET, "/orders")
+                .query(params)
+                .send()
+                .await?,
+        )
+        .await
+    }
+
+    pub async fn import_orders(
+        &self,
+        format: Format,
+        body: impl Into<reqwest::Body>,
+        mode: IngestMode,
+    ) -> Result<IngestReport> {
+        let response = self
+            .request(Method::POST, "/orders/import")
+            .query(&[("mode", mode)])
+            .header(CONTENT_TYPE, format.content_type())
+            .body(body)
+            .send()
+            .await?;
+        Self::ingest_report(response).await
+    }
+
+    pub async fn export_orders(&self, format: Format) -> Result<String> {
+        self.export("/orders/export", format).await
+    }
+
+    pub async fn import_regions(
+        &self,
+        format: Format,
+        body: impl Into<reqwest::Body>,
+    ) -> Result<RegionImportReport> {
+        Self::json(
+            self.request(Method::POST, "/regions/import")
+                .header(CONTENT_TYPE, format.content_type())
+                .body(body)
+                .send()
+                .await?,
+        )
+        .await
+    }
+
+    pub async fn export_regions(&self, format: Format) -> Result<String> {
+        self.export("/regions/export", format).await
+    }
+
+    async fn export(&self, path: &str, format: Format) -> Result<String> {
+        Self::text(
+            self.request(Method::GET, path)
+                .header(ACCEPT, format.content_type())
+                .send()
+                .await?,
+        )
+        .await
+    }
+
+    pub async fn order(&self, id: i32) -> Result<Order> {
+        self.get_json(&format!("/orders/{}", id)).await
+    }
+
+    pub async fn put_order(&self, id: i32, fields: &OrderFields) -> Result<Order> {
+        let path = format!("/orders/{}", id);
+        Self::json(self.request(Method::PUT, &path).json(fields).send().await?).await
+    }
+
+    pub async fn patch_order(&self, id: i32, patch: &OrderPatch) -> Result<Order> {
+        let path = format!("/orders/{}", id);
+        Self::json(
+            self.request(Method::PATCH, &path)
+                .json(patch)
+                .send()
+                .await?,
+        )
+        .await
+    }
+
+    pub async fn delete_order(&self, id: i32) -> Result<()> {
+        let path = format!("/orders/{}", id);
+        Self::empty(self.request(Method::DELETE, &path).send().await?).await
+    }
+
+    pub async fn create_snapshot(&self, name: &str) -> Result<Snapshot> {
+        let snapshot = NewSnapshot {
+            name: name.to_string(),
+        };
+        Self::json(self.post_json("/snapshots", &snapshot).await?).await
+    }
+
+    pub async fn snapshots(&self) -> Result<Vec<Snapshot>> {
+        self.get_json("/snapshots").await
+    }
+
+    pub async fn snapshot(&self, name: &str) -> Result<Snapshot> {
+        self.get_json(&format!("/snapshots/{}", name)).await
+    }
+
+    pub async fn delete_snapshot(&self, name: &str) -> Result<()> {
+        let path = format!("/snapshots/{}", name);
+        Self::empty(self.request(Method::DELETE, &path).send().await?).await
+    }
+
+    pub async fn restore_snapshot(&self, name: &str) -> Result<Snapshot> {
+        Self::json(
+            self.post_body(&format!("/snapshots/{}/restore", name), "")
+                .await?,
+        )
+        .await
+    }
+
+    // Compares a snapshot with another one, or with the current orders.
+    pub async fn diff_snapshot(&self, name: &str, to: Option<&str>) -> Result<SnapshotDiff> {
+        let path = format!("/snapshots/{}/diff", name);
+        let mut request = self.request(Method::GET, &path);
+        if let Some(to) = to {
+            request = request.query(&[("to", to)]);
+        }
+        Self::json(request.send().await?).await
+    }
+
+    pub async fn gifts(&self) -> Result<Vec<Gift>> {
+        self.get_json("/gifts").await
+    }
+
+    pub async fn gift(&self, id: i32) -> Result<Gift> {
+        self.get_json(&format!("/gifts/{}", id)).await
+    }
+
+    pub async fn create_gift(&self, gift: &NewGift) -> Result<Gift> {
+        Self::json(self.post_json("/gifts", gift).await?).await
+    }
+
+    pub async fn put_gift(&self, id: i32, gift: &NewGift) -> Result<Gift> {
+        let path = format!("/gifts/{}", id);
+        Self::json(self.request(Method::PUT, &path).json(gift).send().await?).await
+    }
+
+    pub async fn delete_gift(&self, id: i32) -> Result<()> {
+        let path = format!("/gifts/{}", id);
+        Self::empty(self.request(Method::DELETE, &path).send().await?).await
+    }
+
+    pub async fn gift_categories(&self) -> Result<Vec<CategoryTotal>> {
+        self.get_json("/gifts/categories").await
+    }
+
+    pub async fn roster(&self) -> Result<Vec<Reindeer>> {
+        self.get_json("/reindeer").await
+    }
+
+    pub async fn reindeer(&self, name: &str) -> Result<Reindeer> {
+        self.get_json(&format!("/reindeer/{}", name)).await
+    }
+
+    // Adds the reindeer to the roster, replacing the stats of the ones already on it.
+    pub async fn register_reindeer(&self, reindeers: &[Reindeer]) -> Result<Vec<Reindeer>> {
+        Self::json(self.post_json("/reindeer", &reindeers).await?).await
+    }
+
+    pub async fn delete_reindeer(&self, name: &str) -> Result<()> {
+        let path = format!("/reindeer/{}", name);
+        Self::empty(self.request(Method::DELETE, &path).send().await?).await
+    }
+
+    pub async fn hold_contest(&self, contest: &NewContest) -> Result<Contest> {
+        Self::json(self.post_json("/contests", contest).await?).await
+    }
+
+    pub async fn contests(&self, params: &ContestParams) -> Result<Vec<Contest>> {
+        Self::json(
+            self.request(Method::GET, "/contests")
+                .query(params)
+                .send()
+                .await?,
+        )
+        .await
+    }
+
+    pub async fn contest_record(&self, id: i32) -> Result<Contest> {
+        self.get_json(&format!("/contests/{}", id)).await
+    }
+
+    pub async fn leaderboard(&self, params: &LeaderboardParams) -> Result<Vec<LeaderboardEntry>> {
+        Self::json(
+            self.request(Method::GET, "/contests/leaderboard")
+                .query(params)
+                .send()
+                .await?,
+        )
+        .await
+    }
+
+    pub async fn render_unsafe(&self, content: impl Into<String>) -> Result<String> {
+        let request = UnsafeRequest {
+            content: content.into(),
+        };
+        Self::text(self.post_json("/14/unsafe", &request).await?).await
+    }
+
+    pub async fn render_safe(&self, content: impl Into<String>) -> Result<String> {
+        let request = UnsafeRequest {
+            content: content.into(),
+        };
+        Self::text(self.post_json("/14/safe", &request).await?).await
+    }
+
+    // The naughty answers come with an error status but still carry a result.
+    pub async fn nice(&self, input: impl Into<String>) -> Result<CheckNiceResult> {
+        let password = Password {
+            input: input.into(),
+        };
+        Ok(self.post_json("/15/nice", &password).await?.json().await?)
+    }
+
+    pub async fn game(&self, input: impl Into<String>) -> Result<(StatusCode, CheckGameResult)> {
+        let password = Password {
+            input: input.into(),
+        };
+        let response = self.post_json("/15/game", &password).await?;
+        Ok((response.status(), response.json().await?))
+    }
+
+    pub async fn reset_regions(&self) -> Result<()> {
+        Self::empty(self.post_body("/18/reset", "").await?).await
+    }
+
+    pub async fn add_region_orders(
+        &self,
+        orders: &[Order],
+        mode: IngestMode,
+    ) -> Result<IngestReport> {
+        self.ingest("/18/orders", orders, mode).await
+    }
+
+    pub async fn add_regions(&self, regions: &[Region]) -> Result<()> {
+        Self::empty(self.post_json("/18/regions", &regions).await?).await
+    }
+
+    pub async fn regions_total(&self) -> Result<Vec<RegionsTotalResponse>> {
+        self.get_json("/18/regions/total").await
+    }
+
+    pub async fn regions_top_list(&self, number: i64) -> Result<Vec<RegionTopList>> {
+        self.get_json(&format!("/18/regions/top_list/{}", number))
+            .await
+    }
+
+    pub async fn regions_total_by(&self, by: GiftMetric) -> Result<Vec<RegionsTotalResponse>> {
+        self.get_by("/18/regions/total", by).await
+    }
+
+    pub async fn regions_top_list_by(
+        &self,
+        number: i64,
+        by: GiftMetric,
+    ) -> Result<Vec<RegionTopList>> {
+        self.get_by(&format!("/18/regions/top_list/{}", number), by)
+            .await
+    }
+
+    pub async fn move_region(&self, id: i64, parent: &RegionParent) -> Result<Region> {
+        let path = format!("/18/regions/{}/parent", id);
+        Self::json(self.request(Method::PUT, &path).json(parent).send().await?).await
+    }
+
+    pub async fn region_tree(&self, params: &RegionTreeParams) -> Result<Vec<RegionNode>> {
+        Self::json(
+            self.request(Method::GET, "/18/regions/tree")
+                .query(params)
+                .send()
+                .await?,
+        )
+        .await
+    }
+
+    pub async fn regions_analytics(
+        &self,
+        params: &AnalyticsParams,
+    ) -> Result<Vec<RegionAnalytics>> {
+        Self::json(
+            self.request(Method::GET, "/18/regions/analytics")
+                .query(params)
+                .send()
+                .await?,
+        )
+        .await
+    }
+
+    pub async fn ping_game(&self) -> Result<PingGame> {
+        PingGame::connect(&self.ws_url("/19/ws/ping")).await
+    }
+
+    pub async fn reset_views(&self) -> Result<()> {
+        Self::empty(self.post_body("/19/reset", "").await?).await
+    }
+
+    pub async fn views(&self) -> Result<String> {
+        self.get_text("/19/views").await
+    }
+
+    pub async fn join_room(&self, room_number: usize, user_name: &str) -> Result<ChatRoom> {
+        ChatRoom::connect(&self.ws_url(&format!("/19/ws/room/{}/user/{}", room_number, user_name)))
+            .await
+    }
+
+    pub async fn archive_files(&self, archive: Vec<u8>) -> Result<String> {
+        Self::text(self.post_body("/20/archive_files", archive).await?).await
+    }
+
+    pub async fn archive_files_size(&self, archive: Vec<u8>) -> Result<String> {
+        Self::text(self.post_body("/20/archive_files_size", archive).await?).await
+    }
+
+    pub async fn cookie(&self, archive: Vec<u8>) -> Result<String> {
+        Self::text(self.post_body("/20/cookie", archive).await?).await
+    }
+
+    pub async fn coords(&self, binary: &str) -> Result<String> {
+        self.get_text(&format!("/21/coords/{}", binary)).await
+    }
+
+    pub async fn country(&self, binary: &str) -> Result<String> {
+        self.get_text(&format!("/21/country/{}", binary)).await
+    }
+
+    pub async fn integers(&self, input: impl Into<String>) -> Result<String> {
+        Self::text(self.post_body("/22/integers", input.into()).await?).await
+    }
+
+    pub async fn rocket(&self, star_map: impl Into<String>) -> Result<String> {
+        Self::text(self.post_body("/22/rocket", star_map.into()).await?).await
+    }
+
+    pub async fn openapi(&self) -> Result<serde_json::Value> {
+        self.get_json("/api-docs/openapi.json").await
+    }
+
+    pub async fn create_api_key(
+        &self,
+        name: impl Into<String>,
+        role: Role,
+    ) -> Result<CreatedApiKey> {
+        let new_key = NewApiKey {
+            name: name.into(),
+            role,
+            tenant: None,
+        };
+        Self::json(self.post_json("/admin/api-keys", &new_key).await?).await
+    }
+
+    // Keys bound to a tenant only reach its data, whatever the requests name.
+    pub async fn create_tenant_api_key(
+        &self,
+        name: impl Into<String>,
+        role: Role,
+        tenant: impl Into<String>,
+    ) -> Result<CreatedApiKey> {
+        let new_key = NewApiKey {
+            name: name.into(),
+            role,
+            tenant: Some(tenant.into()),
+        };
+        Self::json(self.post_json("/admin/api-keys", &new_key).await?).await
+    }
+
+    pub async fn api_keys(&self) -> Result<Vec<ApiKeyInfo>> {
+        self.get_json("/admin/api-keys").await
+    }
+
+    pub async fn revoke_api_key(&self, id: i32) -> Result<()> {
+        let path = format!("/admin/api-keys/{}", id);
+        Self::empty(self.request(Method::DELETE, &path).send().await?).await
+    }
+
+    pub async fn audit(&self, params: &AuditParams) -> Result<AuditPage> {
+        Self::json(
+            self.request(Method::GET, "/audit")
+                .query(params)
+                .send()
+                .await?,
+        )
+        .await
+    }
+
+    pub async fn healthz(&self) -> Result<LivenessReport> {
+        self.get_json("/healthz").await
+    }
+
+    // Unready instances answer 503 with the same breakdown.
+    pub async fn readyz(&self) -> Result<(StatusCode, ReadinessReport)> {
+        let response = self.request(Method::GET, "/readyz").send().await?;
+        Ok((response.status(), response.json().await?))
+    }
+
+    pub async fn metrics(&self) -> Result<String> {
+        self.get_text("/metrics").await
+    }
+}
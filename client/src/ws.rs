@@ -0,0 +1,132 @@
+use futures::{SinkExt, StreamExt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{client::IntoClientRequest, http::HeaderValue, Error, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+
+use crate::{ChatMessage, OrderFeedEvent, Result, TENANT_HEADER};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+pub struct PingGame {
+    socket: Socket,
+}
+
+impl PingGame {
+    pub(crate) async fn connect(url: &str) -> Result<Self> {
+        let (socket, _) = connect_async(url).await?;
+        Ok(Self { socket })
+    }
+
+    pub async fn serve(&mut self) -> Result<()> {
+        self.socket.send(Message::Text("serve".to_string())).await?;
+        Ok(())
+    }
+
+    pub async fn ping(&mut self) -> Result<()> {
+        self.socket.send(Message::Text("ping".to_string())).await?;
+        Ok(())
+    }
+
+    pub async fn next_pong(&mut self) -> Result<Option<String>> {
+        while let Some(msg) = self.socket.next().await {
+            match msg? {
+                Message::Text(text) => return Ok(Some(text)),
+                Message::Close(_) => return Ok(None),
+                _ => continue,
+            }
+        }
+        Ok(None)
+    }
+
+    pub async fn close(mut self) -> Result<()> {
+        self.socket.close(None).await?;
+        Ok(())
+    }
+}
+
+pub struct ChatRoom {
+    socket: Socket,
+}
+
+impl ChatRoom {
+    pub(crate) async fn connect(url: &str) -> Result<Self> {
+        let (socket, _) = connect_async(url).await?;
+        Ok(Self { socket })
+    }
+
+    pub async fn send(&mut self, message: impl Into<String>) -> Result<()> {
+        let message = ChatMessage {
+            user: None,
+            message: message.into(),
+        };
+        self.socket
+            .send(Message::Text(serde_json::to_string(&message)?))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn next_message(&mut self) -> Result<Option<ChatMessage>> {
+        while let Some(msg) = self.socket.next().await {
+            match msg? {
+                Message::Text(text) => match serde_json::from_str::<ChatMessage>(&text) {
+                    Ok(message) if message.user.is_some() => return Ok(Some(message)),
+                    _ => continue,
+                },
+                Message::Close(_) => return Ok(None),
+                _ => continue,
+            }
+        }
+        Ok(None)
+    }
+
+    pub async fn close(mut self) -> Result<()> {
+        self.socket.close(None).await?;
+        Ok(())
+    }
+}
+
+pub struct OrderFeed {
+    socket: Socket,
+}
+
+impl OrderFeed {
+    pub(crate) async fn connect(
+        url: &str,
+        api_key: Option<&str>,
+        tenant: Option<&str>,
+    ) -> Result<Self> {
+        let mut request = url.into_client_request()?;
+        if let Some(api_key) = api_key {
+            let value = HeaderValue::from_str(&format!("Bearer {}", api_key))
+                .map_err(|err| Error::HttpFormat(err.into()))?;
+            request.headers_mut().insert("authorization", value);
+        }
+        if let Some(tenant) = tenant {
+            let value =
+                HeaderValue::from_str(tenant).map_err(|err| Error::HttpFormat(err.into()))?;
+            request.headers_mut().insert(TENANT_HEADER, value);
+        }
+        let (socket, _) = connect_async(request).await?;
+        Ok(Self { socket })
+    }
+
+    // The first event carries the current figures, the following ones each stored batch.
+    pub async fn next_event(&mut self) -> Result<Option<OrderFeedEvent>> {
+        while let Some(msg) = self.socket.next().await {
+            match msg? {
+                Message::Text(text) => return Ok(Some(serde_json::from_str(&text)?)),
+                Message::Close(_) => return Ok(None),
+                _ => continue,
+            }
+        }
+        Ok(None)
+    }
+
+    pub async fn close(mut self) -> Result<()> {
+        self.socket.close(None).await?;
+        Ok(())
+    }
+}
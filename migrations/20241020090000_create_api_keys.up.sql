@@ -0,0 +1,8 @@
+CREATE TABLE api_keys (
+  id SERIAL PRIMARY KEY,
+  name VARCHAR(100) NOT NULL,
+  key_hash CHAR(64) NOT NULL UNIQUE,
+  role VARCHAR(10) NOT NULL CHECK (role IN ('reader', 'writer', 'admin')),
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  revoked_at TIMESTAMPTZ
+);
@@ -0,0 +1,26 @@
+DROP TRIGGER IF EXISTS regions_set_updated_at ON regions;
+DROP TRIGGER IF EXISTS orders_set_updated_at ON orders;
+DROP FUNCTION IF EXISTS set_updated_at();
+
+ALTER TABLE regions
+  DROP COLUMN IF EXISTS updated_at,
+  DROP COLUMN IF EXISTS created_at;
+ALTER TABLE orders
+  DROP COLUMN IF EXISTS updated_at,
+  DROP COLUMN IF EXISTS created_at;
+
+DROP INDEX IF EXISTS orders_gift_name_idx;
+DROP INDEX IF EXISTS orders_region_id_idx;
+
+ALTER TABLE orders
+  DROP CONSTRAINT IF EXISTS orders_region_id_fkey,
+  DROP CONSTRAINT IF EXISTS orders_quantity_non_negative,
+  ALTER COLUMN quantity DROP NOT NULL,
+  ALTER COLUMN gift_name DROP NOT NULL,
+  ALTER COLUMN region_id DROP NOT NULL;
+ALTER TABLE regions ALTER COLUMN name DROP NOT NULL;
+
+INSERT INTO orders (id, region_id, gift_name, quantity)
+SELECT id, region_id, gift_name, quantity FROM orders_quarantine
+ON CONFLICT (id) DO NOTHING;
+DROP TABLE IF EXISTS orders_quarantine;
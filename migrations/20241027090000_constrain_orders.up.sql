@@ -0,0 +1,60 @@
+-- Rows breaking the new constraints are moved here instead of being lost, see the README.
+CREATE TABLE orders_quarantine (
+  id INT NOT NULL,
+  region_id INT,
+  gift_name VARCHAR(50),
+  quantity INT,
+  reason VARCHAR(20) NOT NULL,
+  quarantined_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+UPDATE regions SET name = 'Region ' || id WHERE name IS NULL;
+ALTER TABLE regions ALTER COLUMN name SET NOT NULL;
+
+WITH invalid AS (
+  SELECT o.id,
+    CASE
+      WHEN o.region_id IS NULL THEN 'missing_region'
+      WHEN o.gift_name IS NULL THEN 'missing_gift_name'
+      WHEN o.quantity IS NULL THEN 'missing_quantity'
+      WHEN o.quantity < 0 THEN 'negative_quantity'
+      WHEN NOT EXISTS (SELECT 1 FROM regions r WHERE r.id = o.region_id) THEN 'unknown_region'
+    END AS reason
+  FROM orders o
+), moved AS (
+  DELETE FROM orders o
+  USING invalid i
+  WHERE o.id = i.id AND i.reason IS NOT NULL
+  RETURNING o.id, o.region_id, o.gift_name, o.quantity, i.reason
+)
+INSERT INTO orders_quarantine (id, region_id, gift_name, quantity, reason)
+SELECT id, region_id, gift_name, quantity, reason FROM moved;
+
+ALTER TABLE orders
+  ALTER COLUMN region_id SET NOT NULL,
+  ALTER COLUMN gift_name SET NOT NULL,
+  ALTER COLUMN quantity SET NOT NULL,
+  ADD CONSTRAINT orders_quantity_non_negative CHECK (quantity >= 0),
+  ADD CONSTRAINT orders_region_id_fkey FOREIGN KEY (region_id) REFERENCES regions (id);
+
+CREATE INDEX orders_region_id_idx ON orders (region_id);
+CREATE INDEX orders_gift_name_idx ON orders (gift_name);
+
+ALTER TABLE orders
+  ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+ALTER TABLE regions
+  ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+CREATE FUNCTION set_updated_at() RETURNS TRIGGER AS $$
+BEGIN
+  NEW.updated_at = NOW();
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER orders_set_updated_at BEFORE UPDATE ON orders
+  FOR EACH ROW EXECUTE FUNCTION set_updated_at();
+CREATE TRIGGER regions_set_updated_at BEFORE UPDATE ON regions
+  FOR EACH ROW EXECUTE FUNCTION set_updated_at();
@@ -0,0 +1,5 @@
+DROP INDEX IF EXISTS regions_parent_id_idx;
+
+ALTER TABLE regions
+  DROP CONSTRAINT IF EXISTS regions_parent_id_not_self,
+  DROP COLUMN IF EXISTS parent_id;
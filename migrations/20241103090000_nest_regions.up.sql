@@ -0,0 +1,5 @@
+ALTER TABLE regions
+  ADD COLUMN parent_id INT REFERENCES regions (id),
+  ADD CONSTRAINT regions_parent_id_not_self CHECK (parent_id <> id);
+
+CREATE INDEX regions_parent_id_idx ON regions (parent_id);
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS snapshot_orders;
+DROP TABLE IF EXISTS snapshot_regions;
+DROP TABLE IF EXISTS snapshots;
@@ -0,0 +1,25 @@
+CREATE TABLE snapshots (
+  id SERIAL PRIMARY KEY,
+  name VARCHAR(64) NOT NULL UNIQUE,
+  order_count BIGINT NOT NULL,
+  region_count BIGINT NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Copies of the rows, without constraints on their values: they were checked when stored.
+CREATE TABLE snapshot_regions (
+  snapshot_id INT NOT NULL REFERENCES snapshots (id) ON DELETE CASCADE,
+  id INT NOT NULL,
+  name VARCHAR(50) NOT NULL,
+  parent_id INT,
+  PRIMARY KEY (snapshot_id, id)
+);
+
+CREATE TABLE snapshot_orders (
+  snapshot_id INT NOT NULL REFERENCES snapshots (id) ON DELETE CASCADE,
+  id INT NOT NULL,
+  region_id INT NOT NULL,
+  gift_name VARCHAR(50) NOT NULL,
+  quantity INT NOT NULL,
+  PRIMARY KEY (snapshot_id, id)
+);
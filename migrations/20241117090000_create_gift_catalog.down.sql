@@ -0,0 +1,10 @@
+DROP TRIGGER IF EXISTS orders_resolve_gift ON orders;
+DROP FUNCTION IF EXISTS resolve_order_gift();
+
+DROP INDEX IF EXISTS orders_gift_name_key_idx;
+DROP INDEX IF EXISTS orders_gift_id_idx;
+ALTER TABLE orders DROP COLUMN IF EXISTS gift_id;
+
+DROP TABLE IF EXISTS gift_names;
+DROP TABLE IF EXISTS gifts;
+DROP FUNCTION IF EXISTS gift_name_key(TEXT);
@@ -0,0 +1,65 @@
+-- Lowercase, with whitespace runs collapsed into a single space and trimmed.
+CREATE FUNCTION gift_name_key(name TEXT) RETURNS TEXT AS $$
+  SELECT lower(btrim(regexp_replace(name, '\s+', ' ', 'g')))
+$$ LANGUAGE sql IMMUTABLE;
+
+CREATE TABLE gifts (
+  id SERIAL PRIMARY KEY,
+  name VARCHAR(50) NOT NULL,
+  unit_price_cents BIGINT NOT NULL DEFAULT 0 CONSTRAINT gifts_unit_price_non_negative CHECK (unit_price_cents >= 0),
+  category VARCHAR(50),
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER gifts_set_updated_at BEFORE UPDATE ON gifts
+  FOR EACH ROW EXECUTE FUNCTION set_updated_at();
+
+-- Canonical name and aliases of each gift, a name only ever pointing to one gift.
+CREATE TABLE gift_names (
+  name_key VARCHAR(50) GENERATED ALWAYS AS (gift_name_key(name)) STORED PRIMARY KEY,
+  gift_id INT NOT NULL REFERENCES gifts (id) ON DELETE CASCADE,
+  name VARCHAR(50) NOT NULL
+);
+
+CREATE INDEX gift_names_gift_id_idx ON gift_names (gift_id);
+
+ALTER TABLE orders ADD COLUMN gift_id INT REFERENCES gifts (id) ON DELETE SET NULL;
+CREATE INDEX orders_gift_id_idx ON orders (gift_id);
+CREATE INDEX orders_gift_name_key_idx ON orders (gift_name_key(gift_name));
+
+UPDATE orders SET gift_name = btrim(regexp_replace(gift_name, '\s+', ' ', 'g'));
+
+-- Stores the canonical name of catalog gifts. Other names take the spelling already used by the
+-- orders with the same key, so that they are counted as one gift too.
+CREATE FUNCTION resolve_order_gift() RETURNS TRIGGER AS $$
+DECLARE
+  gift RECORD;
+  spelling VARCHAR(50);
+BEGIN
+  SELECT g.id, g.name INTO gift
+  FROM gift_names n
+  JOIN gifts g ON g.id = n.gift_id
+  WHERE n.name_key = gift_name_key(NEW.gift_name);
+  IF FOUND THEN
+    NEW.gift_id = gift.id;
+    NEW.gift_name = gift.name;
+    RETURN NEW;
+  END IF;
+
+  SELECT o.gift_name INTO spelling
+  FROM orders o
+  WHERE gift_name_key(o.gift_name) = gift_name_key(NEW.gift_name) AND o.id <> NEW.id
+  ORDER BY o.id
+  LIMIT 1;
+  NEW.gift_id = NULL;
+  NEW.gift_name = COALESCE(spelling, btrim(regexp_replace(NEW.gift_name, '\s+', ' ', 'g')));
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER orders_resolve_gift BEFORE INSERT OR UPDATE OF gift_name ON orders
+  FOR EACH ROW EXECUTE FUNCTION resolve_order_gift();
+
+-- Existing orders get the same treatment, merging the spellings already stored.
+UPDATE orders SET gift_name = gift_name;
@@ -0,0 +1,78 @@
+DROP POLICY IF EXISTS tenant_isolation ON snapshot_orders;
+DROP POLICY IF EXISTS tenant_isolation ON snapshot_regions;
+DROP POLICY IF EXISTS tenant_isolation ON snapshots;
+DROP POLICY IF EXISTS tenant_isolation ON gift_names;
+DROP POLICY IF EXISTS tenant_isolation ON gifts;
+DROP POLICY IF EXISTS tenant_isolation ON orders;
+DROP POLICY IF EXISTS tenant_isolation ON regions;
+
+ALTER TABLE snapshot_orders NO FORCE ROW LEVEL SECURITY, DISABLE ROW LEVEL SECURITY;
+ALTER TABLE snapshot_regions NO FORCE ROW LEVEL SECURITY, DISABLE ROW LEVEL SECURITY;
+ALTER TABLE snapshots NO FORCE ROW LEVEL SECURITY, DISABLE ROW LEVEL SECURITY;
+ALTER TABLE gift_names NO FORCE ROW LEVEL SECURITY, DISABLE ROW LEVEL SECURITY;
+ALTER TABLE gifts NO FORCE ROW LEVEL SECURITY, DISABLE ROW LEVEL SECURITY;
+ALTER TABLE orders NO FORCE ROW LEVEL SECURITY, DISABLE ROW LEVEL SECURITY;
+ALTER TABLE regions NO FORCE ROW LEVEL SECURITY, DISABLE ROW LEVEL SECURITY;
+
+-- Only the default tenant fits in a single dataset, the other tenants are dropped.
+DELETE FROM snapshots WHERE tenant <> 'default';
+DELETE FROM gifts WHERE tenant <> 'default';
+DELETE FROM orders WHERE tenant <> 'default';
+DELETE FROM regions WHERE tenant <> 'default';
+DELETE FROM api_keys WHERE tenant IS NOT NULL AND tenant <> 'default';
+
+ALTER TABLE snapshots
+  DROP CONSTRAINT snapshots_name_key,
+  ADD CONSTRAINT snapshots_name_key UNIQUE (name);
+ALTER TABLE gift_names DROP CONSTRAINT gift_names_pkey, ADD PRIMARY KEY (name_key);
+ALTER TABLE orders
+  DROP CONSTRAINT orders_region_id_fkey,
+  DROP CONSTRAINT orders_pkey,
+  ADD PRIMARY KEY (id);
+ALTER TABLE regions DROP CONSTRAINT regions_parent_id_fkey;
+ALTER TABLE regions DROP CONSTRAINT regions_pkey, ADD PRIMARY KEY (id);
+ALTER TABLE regions
+  ADD CONSTRAINT regions_parent_id_fkey FOREIGN KEY (parent_id) REFERENCES regions (id);
+ALTER TABLE orders
+  ADD CONSTRAINT orders_region_id_fkey FOREIGN KEY (region_id) REFERENCES regions (id);
+
+CREATE OR REPLACE FUNCTION resolve_order_gift() RETURNS TRIGGER AS $$
+DECLARE
+  gift RECORD;
+  spelling VARCHAR(50);
+BEGIN
+  SELECT g.id, g.name INTO gift
+  FROM gift_names n
+  JOIN gifts g ON g.id = n.gift_id
+  WHERE n.name_key = gift_name_key(NEW.gift_name);
+  IF FOUND THEN
+    NEW.gift_id = gift.id;
+    NEW.gift_name = gift.name;
+    RETURN NEW;
+  END IF;
+
+  SELECT o.gift_name INTO spelling
+  FROM orders o
+  WHERE gift_name_key(o.gift_name) = gift_name_key(NEW.gift_name) AND o.id <> NEW.id
+  ORDER BY o.id
+  LIMIT 1;
+  NEW.gift_id = NULL;
+  NEW.gift_name = COALESCE(spelling, btrim(regexp_replace(NEW.gift_name, '\s+', ' ', 'g')));
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP INDEX IF EXISTS orders_name_key_idx;
+ALTER TABLE orders DROP COLUMN IF EXISTS name_key;
+CREATE INDEX orders_gift_name_key_idx ON orders (gift_name_key(gift_name));
+
+ALTER TABLE snapshot_orders DROP COLUMN IF EXISTS tenant;
+ALTER TABLE snapshot_regions DROP COLUMN IF EXISTS tenant;
+ALTER TABLE snapshots DROP COLUMN IF EXISTS tenant;
+ALTER TABLE gift_names DROP COLUMN IF EXISTS tenant;
+ALTER TABLE gifts DROP COLUMN IF EXISTS tenant;
+ALTER TABLE orders DROP COLUMN IF EXISTS tenant;
+ALTER TABLE regions DROP COLUMN IF EXISTS tenant;
+ALTER TABLE api_keys DROP COLUMN IF EXISTS tenant;
+
+DROP FUNCTION IF EXISTS current_tenant();
@@ -0,0 +1,90 @@
+-- Tenant set by the transaction with `set_config('app.tenant', ...)`. Unset, it sees no rows and
+-- can't store any.
+CREATE FUNCTION current_tenant() RETURNS TEXT AS $$
+  SELECT NULLIF(current_setting('app.tenant', true), '')
+$$ LANGUAGE sql STABLE;
+
+-- Keys without a tenant reach any tenant through the X-Tenant header.
+ALTER TABLE api_keys ADD COLUMN tenant VARCHAR(63);
+
+-- Existing rows go to the default tenant, new ones to the tenant of the transaction.
+ALTER TABLE regions ADD COLUMN tenant VARCHAR(63) NOT NULL DEFAULT 'default';
+ALTER TABLE orders ADD COLUMN tenant VARCHAR(63) NOT NULL DEFAULT 'default';
+ALTER TABLE gifts ADD COLUMN tenant VARCHAR(63) NOT NULL DEFAULT 'default';
+ALTER TABLE gift_names ADD COLUMN tenant VARCHAR(63) NOT NULL DEFAULT 'default';
+ALTER TABLE snapshots ADD COLUMN tenant VARCHAR(63) NOT NULL DEFAULT 'default';
+ALTER TABLE snapshot_regions ADD COLUMN tenant VARCHAR(63) NOT NULL DEFAULT 'default';
+ALTER TABLE snapshot_orders ADD COLUMN tenant VARCHAR(63) NOT NULL DEFAULT 'default';
+
+ALTER TABLE regions ALTER COLUMN tenant SET DEFAULT current_tenant();
+ALTER TABLE orders ALTER COLUMN tenant SET DEFAULT current_tenant();
+ALTER TABLE gifts ALTER COLUMN tenant SET DEFAULT current_tenant();
+ALTER TABLE gift_names ALTER COLUMN tenant SET DEFAULT current_tenant();
+ALTER TABLE snapshots ALTER COLUMN tenant SET DEFAULT current_tenant();
+ALTER TABLE snapshot_regions ALTER COLUMN tenant SET DEFAULT current_tenant();
+ALTER TABLE snapshot_orders ALTER COLUMN tenant SET DEFAULT current_tenant();
+
+-- Every tenant has its own ids and names, and orders and regions only refer to their own tenant.
+ALTER TABLE orders DROP CONSTRAINT orders_region_id_fkey;
+ALTER TABLE regions DROP CONSTRAINT regions_parent_id_fkey;
+ALTER TABLE regions DROP CONSTRAINT regions_pkey, ADD PRIMARY KEY (tenant, id);
+ALTER TABLE regions
+  ADD CONSTRAINT regions_parent_id_fkey FOREIGN KEY (tenant, parent_id) REFERENCES regions (tenant, id);
+ALTER TABLE orders
+  DROP CONSTRAINT orders_pkey,
+  ADD PRIMARY KEY (tenant, id),
+  ADD CONSTRAINT orders_region_id_fkey FOREIGN KEY (tenant, region_id) REFERENCES regions (tenant, id);
+ALTER TABLE gift_names DROP CONSTRAINT gift_names_pkey, ADD PRIMARY KEY (tenant, name_key);
+ALTER TABLE snapshots
+  DROP CONSTRAINT snapshots_name_key,
+  ADD CONSTRAINT snapshots_name_key UNIQUE (tenant, name);
+
+-- Functions that aren't leakproof can't use an index under the policies, so orders keep their name
+-- key in a column for the resolution trigger to look up.
+DROP INDEX orders_gift_name_key_idx;
+ALTER TABLE orders ADD COLUMN name_key VARCHAR(50) GENERATED ALWAYS AS (gift_name_key(gift_name)) STORED;
+CREATE INDEX orders_name_key_idx ON orders (tenant, name_key);
+
+CREATE OR REPLACE FUNCTION resolve_order_gift() RETURNS TRIGGER AS $$
+DECLARE
+  gift RECORD;
+  spelling VARCHAR(50);
+BEGIN
+  SELECT g.id, g.name INTO gift
+  FROM gift_names n
+  JOIN gifts g ON g.id = n.gift_id
+  WHERE n.name_key = gift_name_key(NEW.gift_name);
+  IF FOUND THEN
+    NEW.gift_id = gift.id;
+    NEW.gift_name = gift.name;
+    RETURN NEW;
+  END IF;
+
+  SELECT o.gift_name INTO spelling
+  FROM orders o
+  WHERE o.name_key = gift_name_key(NEW.gift_name) AND o.id <> NEW.id
+  ORDER BY o.id
+  LIMIT 1;
+  NEW.gift_id = NULL;
+  NEW.gift_name = COALESCE(spelling, btrim(regexp_replace(NEW.gift_name, '\s+', ' ', 'g')));
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Forced, so that the owner of the tables is held to the policies too. Superusers and roles with
+-- BYPASSRLS still see every tenant, see the README.
+ALTER TABLE regions ENABLE ROW LEVEL SECURITY, FORCE ROW LEVEL SECURITY;
+ALTER TABLE orders ENABLE ROW LEVEL SECURITY, FORCE ROW LEVEL SECURITY;
+ALTER TABLE gifts ENABLE ROW LEVEL SECURITY, FORCE ROW LEVEL SECURITY;
+ALTER TABLE gift_names ENABLE ROW LEVEL SECURITY, FORCE ROW LEVEL SECURITY;
+ALTER TABLE snapshots ENABLE ROW LEVEL SECURITY, FORCE ROW LEVEL SECURITY;
+ALTER TABLE snapshot_regions ENABLE ROW LEVEL SECURITY, FORCE ROW LEVEL SECURITY;
+ALTER TABLE snapshot_orders ENABLE ROW LEVEL SECURITY, FORCE ROW LEVEL SECURITY;
+
+CREATE POLICY tenant_isolation ON regions USING (tenant = current_tenant());
+CREATE POLICY tenant_isolation ON orders USING (tenant = current_tenant());
+CREATE POLICY tenant_isolation ON gifts USING (tenant = current_tenant());
+CREATE POLICY tenant_isolation ON gift_names USING (tenant = current_tenant());
+CREATE POLICY tenant_isolation ON snapshots USING (tenant = current_tenant());
+CREATE POLICY tenant_isolation ON snapshot_regions USING (tenant = current_tenant());
+CREATE POLICY tenant_isolation ON snapshot_orders USING (tenant = current_tenant());
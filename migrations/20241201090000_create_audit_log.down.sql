@@ -0,0 +1,5 @@
+DROP TRIGGER IF EXISTS regions_audit ON regions;
+DROP TRIGGER IF EXISTS orders_audit ON orders;
+DROP FUNCTION IF EXISTS audit_change();
+DROP TABLE IF EXISTS audit_log;
+DROP FUNCTION IF EXISTS reject_audit_change();
@@ -0,0 +1,74 @@
+-- Every change to orders and regions, with the key and request it came from. Rows are never
+-- updated or deleted.
+CREATE TABLE audit_log (
+  id BIGSERIAL PRIMARY KEY,
+  tenant VARCHAR(63) NOT NULL,
+  occurred_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  actor TEXT,
+  request_id TEXT,
+  operation TEXT NOT NULL CHECK (operation IN ('insert', 'update', 'delete', 'reset')),
+  entity TEXT NOT NULL CHECK (entity IN ('order', 'region')),
+  entity_id INT NOT NULL,
+  before JSONB,
+  after JSONB
+);
+
+CREATE INDEX audit_log_entity_idx ON audit_log (tenant, entity, entity_id, id);
+CREATE INDEX audit_log_occurred_at_idx ON audit_log (tenant, occurred_at);
+
+ALTER TABLE audit_log ENABLE ROW LEVEL SECURITY, FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON audit_log USING (tenant = current_tenant());
+
+CREATE FUNCTION reject_audit_change() RETURNS TRIGGER AS $$
+BEGIN
+  RAISE EXCEPTION 'The audit log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_log_append_only BEFORE UPDATE OR DELETE ON audit_log
+  FOR EACH ROW EXECUTE FUNCTION reject_audit_change();
+CREATE TRIGGER audit_log_no_truncate BEFORE TRUNCATE ON audit_log
+  FOR EACH STATEMENT EXECUTE FUNCTION reject_audit_change();
+
+-- The actor and request id are set by the transaction with `set_config('app.actor', ...)` and
+-- `set_config('app.request_id', ...)`. Deletions made while `app.audit_operation` is `reset` are
+-- logged as part of a reset.
+CREATE FUNCTION audit_change() RETURNS TRIGGER AS $$
+DECLARE
+  entry audit_log%ROWTYPE;
+BEGIN
+  entry.operation = lower(TG_OP);
+  IF TG_OP = 'DELETE' THEN
+    entry.tenant = OLD.tenant;
+    entry.entity_id = OLD.id;
+    IF current_setting('app.audit_operation', true) = 'reset' THEN
+      entry.operation = 'reset';
+    END IF;
+  ELSE
+    entry.tenant = NEW.tenant;
+    entry.entity_id = NEW.id;
+    entry.after = to_jsonb(NEW) - ARRAY['tenant', 'name_key'];
+  END IF;
+  IF TG_OP <> 'INSERT' THEN
+    entry.before = to_jsonb(OLD) - ARRAY['tenant', 'name_key'];
+  END IF;
+
+  INSERT INTO audit_log (tenant, actor, request_id, operation, entity, entity_id, before, after)
+  VALUES (
+    entry.tenant,
+    NULLIF(current_setting('app.actor', true), ''),
+    NULLIF(current_setting('app.request_id', true), ''),
+    entry.operation,
+    TG_ARGV[0],
+    entry.entity_id,
+    entry.before,
+    entry.after
+  );
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER orders_audit AFTER INSERT OR UPDATE OR DELETE ON orders
+  FOR EACH ROW EXECUTE FUNCTION audit_change('order');
+CREATE TRIGGER regions_audit AFTER INSERT OR UPDATE OR DELETE ON regions
+  FOR EACH ROW EXECUTE FUNCTION audit_change('region');
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS contest_winners;
+DROP TABLE IF EXISTS contests;
+DROP TABLE IF EXISTS reindeer;
@@ -0,0 +1,47 @@
+CREATE TABLE reindeer (
+  tenant VARCHAR(63) NOT NULL DEFAULT current_tenant(),
+  name VARCHAR(50) NOT NULL,
+  strength INT NOT NULL,
+  speed REAL,
+  height INT,
+  antler_width INT,
+  snow_magic_power INT,
+  favorite_food VARCHAR(50),
+  candies_eaten_yesterday INT,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (tenant, name)
+);
+
+CREATE TRIGGER reindeer_set_updated_at BEFORE UPDATE ON reindeer
+  FOR EACH ROW EXECUTE FUNCTION set_updated_at();
+
+-- The roster is copied as it was when the contest was held, so that later changes to the reindeer
+-- don't rewrite history.
+CREATE TABLE contests (
+  id SERIAL PRIMARY KEY,
+  tenant VARCHAR(63) NOT NULL DEFAULT current_tenant(),
+  held_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  roster JSONB NOT NULL,
+  result JSONB NOT NULL
+);
+
+CREATE INDEX contests_held_at_idx ON contests (tenant, held_at);
+
+CREATE TABLE contest_winners (
+  contest_id INT NOT NULL REFERENCES contests (id) ON DELETE CASCADE,
+  tenant VARCHAR(63) NOT NULL DEFAULT current_tenant(),
+  category VARCHAR(50) NOT NULL,
+  reindeer VARCHAR(50) NOT NULL,
+  PRIMARY KEY (contest_id, category)
+);
+
+CREATE INDEX contest_winners_category_idx ON contest_winners (tenant, category, reindeer);
+
+ALTER TABLE reindeer ENABLE ROW LEVEL SECURITY, FORCE ROW LEVEL SECURITY;
+ALTER TABLE contests ENABLE ROW LEVEL SECURITY, FORCE ROW LEVEL SECURITY;
+ALTER TABLE contest_winners ENABLE ROW LEVEL SECURITY, FORCE ROW LEVEL SECURITY;
+
+CREATE POLICY tenant_isolation ON reindeer USING (tenant = current_tenant());
+CREATE POLICY tenant_isolation ON contests USING (tenant = current_tenant());
+CREATE POLICY tenant_isolation ON contest_winners USING (tenant = current_tenant());
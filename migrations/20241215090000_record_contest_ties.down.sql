@@ -0,0 +1,11 @@
+-- Only one winner per category fits, the one first by name is kept.
+DELETE FROM contest_winners w
+USING contest_winners other
+WHERE other.contest_id = w.contest_id
+  AND other.category = w.category
+  AND other.reindeer < w.reindeer;
+
+ALTER TABLE contest_winners DROP CONSTRAINT contest_winners_pkey;
+ALTER TABLE contest_winners ADD PRIMARY KEY (contest_id, category);
+
+ALTER TABLE contests DROP COLUMN missing_stats;
@@ -0,0 +1,5 @@
+ALTER TABLE contests ADD COLUMN missing_stats VARCHAR(10) NOT NULL DEFAULT 'skip';
+
+-- Every reindeer sharing the winning score of a category wins it.
+ALTER TABLE contest_winners DROP CONSTRAINT contest_winners_pkey;
+ALTER TABLE contest_winners ADD PRIMARY KEY (contest_id, category, reindeer);
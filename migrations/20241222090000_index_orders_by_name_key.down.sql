@@ -0,0 +1,2 @@
+DROP INDEX orders_name_key_idx;
+CREATE INDEX orders_name_key_idx ON orders (tenant, name_key);
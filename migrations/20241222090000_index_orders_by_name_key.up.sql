@@ -0,0 +1,4 @@
+-- The resolution trigger takes the first order with a name key, which a plan walking the primary
+-- key in id order could only find by reading every order of the tenant.
+DROP INDEX orders_name_key_idx;
+CREATE INDEX orders_name_key_idx ON orders (tenant, name_key, id);
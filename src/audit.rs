@@ -0,0 +1,233 @@
+use anyhow::anyhow;
+use axum::{
+    extract::{Query, State},
+    middleware,
+    routing::get,
+    Extension, Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder, Transaction};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    auth::{authorize, Role, RoleGuard},
+    config::Config,
+    error::AppError,
+    tenant::{self, Tenant},
+    CommonState,
+};
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 500;
+
+type EntryRow = (
+    i64,
+    DateTime<Utc>,
+    Option<String>,
+    Option<String>,
+    String,
+    String,
+    i32,
+    Option<serde_json::Value>,
+    Option<serde_json::Value>,
+);
+
+// Who a request came from, logged with the changes it makes to orders and regions.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Actor {
+    // Name of the API key, when the request had one.
+    pub key: Option<String>,
+    pub request_id: Option<String>,
+}
+
+// Tags the changes of the transaction with the actor, until the transaction ends.
+pub(crate) async fn enter(conn: &mut PgConnection, actor: &Actor) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT set_config('app.actor', $1, true), set_config('app.request_id', $2, true)")
+        .bind(actor.key.as_deref().unwrap_or_default())
+        .bind(actor.request_id.as_deref().unwrap_or_default())
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+// Every change to orders and regions runs in a transaction started here.
+pub(crate) async fn begin(
+    pool: &PgPool,
+    tenant: &Tenant,
+    actor: &Actor,
+) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
+    let mut tx = tenant::begin(pool, tenant).await?;
+    enter(&mut tx, actor).await?;
+    Ok(tx)
+}
+
+// Deletions made while resetting are logged as a reset rather than one by one.
+pub(crate) async fn resetting(conn: &mut PgConnection, reset: bool) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT set_config('app.audit_operation', $1, true)")
+        .bind(if reset { "reset" } else { "" })
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOperation {
+    Insert,
+    Update,
+    Delete,
+    Reset,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEntity {
+    Order,
+    Region,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub occurred_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    pub operation: AuditOperation,
+    pub entity: AuditEntity,
+    pub entity_id: i32,
+    // The row before and after the change, missing for inserts and deletions respectively.
+    #[schema(value_type = Option<Object>)]
+    pub before: Option<serde_json::Value>,
+    #[schema(value_type = Option<Object>)]
+    pub after: Option<serde_json::Value>,
+}
+
+fn entry(
+    (id, occurred_at, actor, request_id, operation, entity, entity_id, before, after): EntryRow,
+) -> Result<AuditEntry, AppError> {
+    let operation = serde_json::from_value(operation.into()).map_err(AppError::internal)?;
+    let entity = serde_json::from_value(entity.into()).map_err(AppError::internal)?;
+    Ok(AuditEntry {
+        id,
+        occurred_at,
+        actor,
+        request_id,
+        operation,
+        entity,
+        entity_id,
+        before,
+        after,
+    })
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditParams {
+    pub order_id: Option<i32>,
+    pub region_id: Option<i32>,
+    // Changes made at or after this time.
+    pub from: Option<DateTime<Utc>>,
+    // Changes made before this time.
+    pub to: Option<DateTime<Utc>>,
+    // Number of entries per page, 50 by default and at most 500.
+    pub limit: Option<i64>,
+    // `next_cursor` of the previous page.
+    pub cursor: Option<i64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct AuditPage {
+    pub entries: Vec<AuditEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<i64>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/audit",
+    tag = "audit",
+    params(AuditParams),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "A page of changes, oldest first, with the cursor of the next one", body = AuditPage),
+        (status = 400, description = "Invalid limit, or both an order and a region id", body = ProblemDetails),
+        (status = 401, description = "Missing or unknown API key", body = ProblemDetails),
+        (status = 403, description = "Admin role required", body = ProblemDetails)
+    )
+)]
+async fn list_entries(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Query(params): Query<AuditParams>,
+) -> Result<Json<AuditPage>, AppError> {
+    let limit = params.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return Err(AppError::bad_request(anyhow!(
+            "Limit must be between 1 and {}",
+            MAX_PAGE_SIZE
+        )));
+    }
+
+    let mut query = QueryBuilder::<Postgres>::new(
+        "SELECT id, occurred_at, actor, request_id, operation, entity, entity_id, before, after FROM audit_log WHERE TRUE",
+    );
+    match (params.order_id, params.region_id) {
+        (Some(_), Some(_)) => {
+            return Err(AppError::bad_request(anyhow!(
+                "Only one of order_id and region_id can be given"
+            )))
+        }
+        (Some(id), None) => {
+            query
+                .push(" AND entity = 'order' AND entity_id = ")
+                .push_bind(id);
+        }
+        (None, Some(id)) => {
+            query
+                .push(" AND entity = 'region' AND entity_id = ")
+                .push_bind(id);
+        }
+        (None, None) => {}
+    }
+    if let Some(from) = params.from {
+        query.push(" AND occurred_at >= ").push_bind(from);
+    }
+    if let Some(to) = params.to {
+        query.push(" AND occurred_at < ").push_bind(to);
+    }
+    if let Some(cursor) = params.cursor {
+        query.push(" AND id > ").push_bind(cursor);
+    }
+    // One more row than requested tells whether there is a next page.
+    query.push(" ORDER BY id LIMIT ").push_bind(limit + 1);
+
+    let mut tx = tenant::begin(&state.pool, &tenant).await?;
+    let mut entries = query
+        .build_query_as::<EntryRow>()
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(entry)
+        .collect::<Result<Vec<_>, _>>()?;
+    let next_cursor = if entries.len() as i64 > limit {
+        entries.truncate(limit as usize);
+        entries.last().map(|last| last.id)
+    } else {
+        None
+    };
+    Ok(Json(AuditPage {
+        entries,
+        next_cursor,
+    }))
+}
+
+pub fn get_routes(state: CommonState, config: &Config) -> Router {
+    let admin =
+        middleware::from_fn_with_state(RoleGuard::new(&state, config, Role::Admin), authorize);
+    Router::new()
+        .route("/audit", get(list_entries).route_layer(admin))
+        .with_state(state)
+}
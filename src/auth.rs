@@ -0,0 +1,415 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::anyhow;
+use axum::{
+    extract::{Path, Request, State},
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::Response,
+    routing::{delete, get},
+    Extension, Json, Router,
+};
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use tracing::info;
+use utoipa::ToSchema;
+
+use crate::{
+    audit::Actor,
+    config::{Config, StorageBackend},
+    error::AppError,
+    telemetry::RequestId,
+    tenant::Tenant,
+    CommonState,
+};
+
+const KEY_PREFIX: &str = "cch23_";
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Reader,
+    Writer,
+    Admin,
+}
+
+impl Role {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Role::Reader => "reader",
+            Role::Writer => "writer",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Role {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reader" => Ok(Role::Reader),
+            "writer" => Ok(Role::Writer),
+            "admin" => Ok(Role::Admin),
+            _ => Err(anyhow!("Unknown role `{}`", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiKey {
+    pub id: i32,
+    pub name: String,
+    pub role: Role,
+    // Tenant the key is bound to, any tenant being reachable through the header otherwise.
+    pub tenant: Option<Tenant>,
+}
+
+pub fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+fn generate_key() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    format!("{}{}", KEY_PREFIX, hex::encode(bytes))
+}
+
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+}
+
+async fn find_key(pool: &PgPool, token: &str) -> Result<Option<ApiKey>, AppError> {
+    let row = sqlx::query_as::<_, (i32, String, String, Option<String>)>(
+        "SELECT id, name, role, tenant FROM api_keys WHERE key_hash = $1 AND revoked_at IS NULL",
+    )
+    .bind(hash_key(token))
+    .fetch_optional(pool)
+    .await?;
+    row.map(|(id, name, role, tenant)| {
+        Ok(ApiKey {
+            id,
+            name,
+            role: role.parse().map_err(AppError::internal)?,
+            tenant: tenant
+                .as_deref()
+                .map(Tenant::parse)
+                .transpose()
+                .map_err(|_| AppError::internal(anyhow!("Key {} has an invalid tenant", id)))?,
+        })
+    })
+    .transpose()
+}
+
+// Makes sure `key` is a valid admin key, so a fresh deployment can create the other keys.
+pub async fn bootstrap_admin(pool: &PgPool, key: &str) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO api_keys (name, key_hash, role) VALUES ('bootstrap', $1, 'admin') ON CONFLICT (key_hash) DO NOTHING",
+    )
+    .bind(hash_key(key))
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[derive(Clone)]
+enum KeySource {
+    Database(PgPool),
+    // Without a database to hold the keys, the bootstrap admin key (hashed) is the only one.
+    Bootstrap(Option<String>),
+}
+
+#[derive(Clone)]
+pub struct RoleGuard {
+    keys: KeySource,
+    role: Role,
+    public: bool,
+}
+
+impl RoleGuard {
+    pub fn new(state: &CommonState, config: &Config, role: Role) -> Self {
+        let keys = match state.backend {
+            StorageBackend::Postgres => KeySource::Database(state.pool.clone()),
+            StorageBackend::Memory => {
+                KeySource::Bootstrap(config.auth.bootstrap_admin_key.as_deref().map(hash_key))
+            }
+        };
+        Self {
+            keys,
+            role,
+            public: role == Role::Reader && config.auth.public_reads,
+        }
+    }
+
+    pub(crate) async fn find_key(&self, token: &str) -> Result<Option<ApiKey>, AppError> {
+        match &self.keys {
+            KeySource::Database(pool) => find_key(pool, token).await,
+            KeySource::Bootstrap(key_hash) => Ok(key_hash
+                .as_ref()
+                .filter(|key_hash| **key_hash == hash_key(token))
+                .map(|_| ApiKey {
+                    id: 0,
+                    name: "bootstrap".to_string(),
+                    role: Role::Admin,
+                    tenant: None,
+                })),
+        }
+    }
+}
+
+// The tenant a key is bound to, else the one named by the header, else the default one.
+fn resolve_tenant(key: Option<&ApiKey>, headers: &HeaderMap) -> Result<Tenant, AppError> {
+    let requested = Tenant::from_headers(headers)?;
+    match (key.and_then(|key| key.tenant.as_ref()), requested) {
+        (Some(bound), Some(requested)) if *bound != requested => Err(AppError::forbidden(anyhow!(
+            "Key `{}` is bound to tenant {}",
+            key.map_or("", |key| key.name.as_str()),
+            bound
+        ))),
+        (Some(bound), _) => Ok(bound.clone()),
+        (None, requested) => Ok(requested.unwrap_or_default()),
+    }
+}
+
+pub async fn authorize(
+    State(guard): State<RoleGuard>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let token = bearer_token(request.headers());
+    let key = if guard.public {
+        // Public reads don't need a key, a valid one still picks its tenant.
+        match token {
+            Some(token) => guard.find_key(token).await?,
+            None => None,
+        }
+    } else {
+        let token = token.ok_or_else(|| AppError::unauthorized(anyhow!("Missing bearer token")))?;
+        let key = guard
+            .find_key(token)
+            .await?
+            .ok_or_else(|| AppError::unauthorized(anyhow!("Unknown or revoked API key")))?;
+        if key.role < guard.role {
+            return Err(AppError::forbidden(anyhow!(
+                "Role {} is required, key `{}` is {}",
+                guard.role,
+                key.name,
+                key.role
+            )));
+        }
+        Some(key)
+    };
+    let tenant = resolve_tenant(key.as_ref(), request.headers())?;
+    let actor = Actor {
+        key: key.as_ref().map(|key| key.name.clone()),
+        request_id: request
+            .extensions()
+            .get::<RequestId>()
+            .map(|RequestId(id)| id.clone()),
+    };
+    request.extensions_mut().insert(tenant);
+    request.extensions_mut().insert(actor);
+    if let Some(key) = key {
+        request.extensions_mut().insert(key);
+    }
+    Ok(next.run(request).await)
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct NewApiKey {
+    pub name: String,
+    pub role: Role,
+    // Binds the key to a tenant. Keys created with a bound admin key are bound to its tenant.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct ApiKeyInfo {
+    pub id: i32,
+    pub name: String,
+    pub role: Role,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub info: ApiKeyInfo,
+    pub key: String,
+}
+
+type ApiKeyRow = (
+    i32,
+    String,
+    String,
+    Option<String>,
+    DateTime<Utc>,
+    Option<DateTime<Utc>>,
+);
+
+fn key_info(
+    (id, name, role, tenant, created_at, revoked_at): ApiKeyRow,
+) -> Result<ApiKeyInfo, AppError> {
+    Ok(ApiKeyInfo {
+        id,
+        name,
+        role: role.parse().map_err(AppError::internal)?,
+        tenant,
+        created_at,
+        revoked_at,
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/api-keys",
+    tag = "admin",
+    request_body = NewApiKey,
+    security(("bearer" = [])),
+    responses(
+        (status = 201, description = "Key created, the secret is only returned once", body = CreatedApiKey),
+        (status = 400, description = "Invalid name or tenant", body = ProblemDetails),
+        (status = 401, description = "Missing or unknown API key", body = ProblemDetails),
+        (status = 403, description = "API key is not an admin, or is bound to another tenant", body = ProblemDetails)
+    )
+)]
+async fn create_key(
+    State(state): State<CommonState>,
+    Extension(admin): Extension<ApiKey>,
+    Json(new_key): Json<NewApiKey>,
+) -> Result<(StatusCode, Json<CreatedApiKey>), AppError> {
+    let name = new_key.name.trim();
+    if name.is_empty() || name.len() > 100 {
+        return Err(AppError::bad_request(anyhow!(
+            "Key name must be between 1 and 100 characters"
+        )));
+    }
+    let tenant = new_key.tenant.as_deref().map(Tenant::parse).transpose()?;
+    let tenant = match (&admin.tenant, tenant) {
+        (Some(bound), Some(tenant)) if *bound != tenant => {
+            return Err(AppError::forbidden(anyhow!(
+                "Key `{}` can only create keys for tenant {}",
+                admin.name,
+                bound
+            )));
+        }
+        (Some(bound), _) => Some(bound.clone()),
+        (None, tenant) => tenant,
+    };
+    let key = generate_key();
+    let row = sqlx::query_as::<_, ApiKeyRow>(
+        "INSERT INTO api_keys (name, key_hash, role, tenant) VALUES ($1, $2, $3, $4) RETURNING id, name, role, tenant, created_at, revoked_at",
+    )
+    .bind(name)
+    .bind(hash_key(&key))
+    .bind(new_key.role.as_str())
+    .bind(tenant.as_ref().map(Tenant::as_str))
+    .fetch_one(&state.pool)
+    .await?;
+    let info = key_info(row)?;
+    info!(id = info.id, role = %info.role, tenant = ?info.tenant, created_by = %admin.name, "API key created");
+    Ok((StatusCode::CREATED, Json(CreatedApiKey { info, key })))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/api-keys",
+    tag = "admin",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Every key, including revoked ones, only the ones of its tenant for a bound admin key", body = Vec<ApiKeyInfo>),
+        (status = 401, description = "Missing or unknown API key", body = ProblemDetails),
+        (status = 403, description = "API key is not an admin", body = ProblemDetails)
+    )
+)]
+async fn list_keys(
+    State(state): State<CommonState>,
+    Extension(admin): Extension<ApiKey>,
+) -> Result<Json<Vec<ApiKeyInfo>>, AppError> {
+    let rows = sqlx::query_as::<_, ApiKeyRow>(
+        r#"
+        SELECT id, name, role, tenant, created_at, revoked_at FROM api_keys
+        WHERE $1::TEXT IS NULL OR tenant = $1
+        ORDER BY id
+        "#,
+    )
+    .bind(admin.tenant.as_ref().map(Tenant::as_str))
+    .fetch_all(&state.pool)
+    .await?;
+    let keys = rows
+        .into_iter()
+        .map(key_info)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Json(keys))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/api-keys/{id}",
+    tag = "admin",
+    params(("id" = i32, Path, description = "Key to revoke")),
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Key revoked"),
+        (status = 401, description = "Missing or unknown API key", body = ProblemDetails),
+        (status = 403, description = "API key is not an admin", body = ProblemDetails),
+        (status = 404, description = "No active key with this id, or not one of its tenant for a bound admin key", body = ProblemDetails)
+    )
+)]
+async fn revoke_key(
+    State(state): State<CommonState>,
+    Extension(admin): Extension<ApiKey>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    let revoked = sqlx::query(
+        r#"
+        UPDATE api_keys SET revoked_at = NOW()
+        WHERE id = $1 AND revoked_at IS NULL AND ($2::TEXT IS NULL OR tenant = $2)
+        "#,
+    )
+    .bind(id)
+    .bind(admin.tenant.as_ref().map(Tenant::as_str))
+    .execute(&state.pool)
+    .await?;
+    if revoked.rows_affected() == 0 {
+        return Err(AppError::not_found(anyhow!("No active API key {}", id)));
+    }
+    info!(id, revoked_by = %admin.name, "API key revoked");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub fn get_routes(state: CommonState, config: &Config) -> Router {
+    let admin = RoleGuard::new(&state, config, Role::Admin);
+    Router::new()
+        .route(
+            "/admin/api-keys",
+            get(list_keys)
+                .post(create_key)
+                .route_layer(middleware::from_fn_with_state(admin.clone(), authorize)),
+        )
+        .route(
+            "/admin/api-keys/:id",
+            delete(revoke_key).route_layer(middleware::from_fn_with_state(admin, authorize)),
+        )
+        .with_state(state)
+}
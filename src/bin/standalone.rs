@@ -0,0 +1,67 @@
+use std::{env, path::PathBuf};
+
+use anyhow::Context;
+use cch23::{
+    config::{Config, StorageBackend},
+    shutdown::Shutdown,
+    CommonState,
+};
+use sqlx::postgres::PgPoolOptions;
+use tokio::net::TcpListener;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let config_path = env::args()
+        .nth(1)
+        .or_else(|| env::var("CCH23_CONFIG").ok())
+        .map(PathBuf::from);
+    let config = Config::load(config_path.as_deref())?;
+    cch23::telemetry::init_tracing(&config.logging)?;
+
+    let backend = config.database.backend;
+    let pool = match backend {
+        StorageBackend::Postgres => {
+            let pool = PgPoolOptions::new()
+                .max_connections(config.database.pool_size)
+                .connect(&config.database.url)
+                .await
+                .context("Unable to connect to the database")?;
+            cch23::tenant::ensure_isolated(&pool).await?;
+            cch23::migrate(&pool)
+                .await
+                .context("Migration should run")?;
+            if let Some(key) = &config.auth.bootstrap_admin_key {
+                cch23::auth::bootstrap_admin(&pool, key)
+                    .await
+                    .context("Unable to register the bootstrap admin key")?;
+            }
+            pool
+        }
+        // Only `/13/sql` and the readiness check reach for the database, connecting on demand.
+        StorageBackend::Memory => PgPoolOptions::new()
+            .max_connections(config.database.pool_size)
+            .connect_lazy(&config.database.url)
+            .context("Invalid database URL")?,
+    };
+
+    let shutdown = Shutdown::new();
+    let router = cch23::router(
+        CommonState::with_backend(pool.clone(), backend),
+        &config,
+        &shutdown,
+    );
+
+    let listener = TcpListener::bind(config.server.bind_address)
+        .await
+        .with_context(|| format!("Unable to bind {}", config.server.bind_address))?;
+    cch23::shutdown::serve(
+        listener,
+        router,
+        shutdown,
+        pool,
+        config.server.shutdown_timeout(),
+    )
+    .await?;
+
+    Ok(())
+}
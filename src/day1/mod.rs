@@ -1,6 +1,19 @@
+use anyhow::anyhow;
 use axum::{extract::Path, routing::get, Router};
 
-async fn recalibrate_packet_ids(Path(packets): Path<String>) -> String {
+use crate::error::AppError;
+
+#[utoipa::path(
+    get,
+    path = "/1/{packets}",
+    tag = "day1",
+    params(("packets" = String, Path, description = "Slash separated packet ids")),
+    responses(
+        (status = 200, description = "Cube of the XOR of all packet ids", body = String),
+        (status = 400, description = "The cube does not fit in 32 bits", body = ProblemDetails)
+    )
+)]
+async fn recalibrate_packet_ids(Path(packets): Path<String>) -> Result<String, AppError> {
     let splits = packets.split('/');
     let mut ids = Vec::<i32>::new();
     for split in splits {
@@ -8,8 +21,12 @@ async fn recalibrate_packet_ids(Path(packets): Path<String>) -> String {
             ids.push(v);
         }
     }
-    let result = ids.iter().fold(0, |acc, v| acc ^ v).pow(3);
-    result.to_string()
+    let result = ids
+        .iter()
+        .fold(0, |acc, v| acc ^ v)
+        .checked_pow(3)
+        .ok_or_else(|| AppError::bad_request(anyhow!("The cube of the packet ids overflows")))?;
+    Ok(result.to_string())
 }
 
 pub fn get_routes() -> Router {
@@ -1,20 +1,32 @@
 use std::io::Cursor;
 
+use anyhow::anyhow;
 use axum::{
     async_trait,
     body::Bytes,
-    extract::{FromRequest, Multipart, Request},
-    http::header::CONTENT_TYPE,
+    extract::{multipart::MultipartError, FromRequest, Multipart, Request, State},
+    http::{header::CONTENT_TYPE, StatusCode},
     routing::post,
     Router,
 };
-use image::{GenericImageView, ImageReader};
+use image::{error::ImageError, GenericImageView, ImageReader, Limits};
 use tower_http::services::ServeDir;
 
-use crate::error::AppError;
+use crate::{
+    config::{Config, DecodeLimits},
+    error::AppError,
+};
 
 struct PngImage(Bytes);
 
+fn multipart_error(err: MultipartError) -> AppError {
+    if err.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        AppError::payload_too_large(err)
+    } else {
+        AppError::bad_request(err)
+    }
+}
+
 #[async_trait]
 impl<S> FromRequest<S> for PngImage
 where
@@ -24,33 +36,59 @@ where
     type Rejection = AppError;
     async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
         let Some(content_type) = req.headers().get(CONTENT_TYPE) else {
-            return Err(AppError(anyhow::anyhow!("Missing content-type header")));
+            return Err(AppError::bad_request(anyhow!(
+                "Missing content-type header"
+            )));
         };
 
-        let body = if content_type.to_str()?.starts_with("multipart/form-data") {
-            let mut multipart = Multipart::from_request(req, state)
-                .await
-                .map_err(|err| AppError(anyhow::Error::new(err)))?;
+        let content_type = content_type.to_str().map_err(AppError::bad_request)?;
+        if !content_type.starts_with("multipart/form-data") {
+            return Err(AppError::bad_request(anyhow!(
+                "Expected a multipart/form-data body"
+            )));
+        }
 
-            let Ok(Some(field)) = multipart.next_field().await else {
-                return Err(AppError(anyhow::anyhow!("Missing multipart field")));
-            };
+        let mut multipart = Multipart::from_request(req, state)
+            .await
+            .map_err(AppError::bad_request)?;
 
-            field
-                .bytes()
-                .await
-                .map_err(|err| AppError(anyhow::Error::new(err)))?
-        } else {
-            return Err(AppError(anyhow::anyhow!("Bad request")));
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => return Err(AppError::bad_request(anyhow!("Missing multipart field"))),
+            Err(err) => return Err(multipart_error(err)),
         };
+
+        let body = field.bytes().await.map_err(multipart_error)?;
         Ok(Self(body))
     }
 }
 
-async fn red_pixels(PngImage(image): PngImage) -> Result<String, AppError> {
-    let img = ImageReader::new(Cursor::new(image))
-        .with_guessed_format()?
-        .decode()?;
+#[utoipa::path(
+    post,
+    path = "/11/red_pixels",
+    tag = "day11",
+    request_body(content = Vec<u8>, content_type = "multipart/form-data", description = "Image to analyze"),
+    responses(
+        (status = 200, description = "Number of magical red pixels", body = String),
+        (status = 400, description = "Missing or undecodable image", body = ProblemDetails),
+        (status = 413, description = "Image or decoded image too large", body = ProblemDetails)
+    )
+)]
+async fn red_pixels(
+    State(limits): State<DecodeLimits>,
+    PngImage(image): PngImage,
+) -> Result<String, AppError> {
+    let mut decode_limits = Limits::default();
+    decode_limits.max_image_width = Some(limits.image_max_dimension);
+    decode_limits.max_image_height = Some(limits.image_max_dimension);
+    decode_limits.max_alloc = Some(limits.image_max_alloc_bytes);
+
+    let mut reader = ImageReader::new(Cursor::new(image)).with_guessed_format()?;
+    reader.limits(decode_limits);
+    let img = reader.decode().map_err(|err| match err {
+        ImageError::Limits(_) => AppError::payload_too_large(err),
+        _ => AppError::bad_request(err),
+    })?;
     let mut count = 0;
     for (_, _, rgba) in img.pixels() {
         let colors = rgba.0;
@@ -64,8 +102,9 @@ async fn red_pixels(PngImage(image): PngImage) -> Result<String, AppError> {
     Ok(count.to_string())
 }
 
-pub fn get_routes() -> Router {
+pub fn get_routes(config: &Config) -> Router {
     Router::new()
-        .nest_service("/11/assets", ServeDir::new("resources"))
+        .nest_service("/11/assets", ServeDir::new(&config.server.assets_dir))
         .route("/11/red_pixels", post(red_pixels))
+        .with_state(config.limits.decode.clone())
 }
@@ -1,18 +1,20 @@
 use std::{
     collections::HashMap,
     str::FromStr,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, MutexGuard},
     time::{Duration, SystemTime},
 };
 
+use anyhow::anyhow;
 use axum::{
     extract::{Path, State},
     routing::{get, post},
     Json, Router,
 };
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use ulid::Ulid;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use crate::error::AppError;
@@ -25,13 +27,24 @@ struct AppState {
 type UlidRequest = Vec<String>;
 type SharedState = Arc<Mutex<AppState>>;
 
+fn lock_state(state: &SharedState) -> Result<MutexGuard<'_, AppState>, AppError> {
+    state
+        .lock()
+        .map_err(|_| AppError::internal(anyhow!("Stopwatch state is poisoned")))
+}
+
+#[utoipa::path(
+    post,
+    path = "/12/save/{packet_id}",
+    tag = "day12",
+    params(("packet_id" = String, Path, description = "Packet to start timing")),
+    responses((status = 200, description = "Timer started"))
+)]
 async fn save_packet(
     State(state): State<SharedState>,
     Path(packet_id): Path<String>,
 ) -> Result<(), AppError> {
-    state
-        .lock()
-        .unwrap()
+    lock_state(&state)?
         .multi_stopwatch
         .entry(packet_id)
         .and_modify(|e| *e = SystemTime::now())
@@ -39,12 +52,18 @@ async fn save_packet(
     Ok(())
 }
 
+#[utoipa::path(
+    get,
+    path = "/12/load/{packet_id}",
+    tag = "day12",
+    params(("packet_id" = String, Path, description = "Packet to read the timer of")),
+    responses((status = 200, description = "Seconds elapsed since the packet was saved", body = String))
+)]
 async fn load_packet(
     State(state): State<SharedState>,
     Path(packet_id): Path<String>,
 ) -> Result<String, AppError> {
-    let time_diff = if let Some(&timestamp) = state.lock().unwrap().multi_stopwatch.get(&packet_id)
-    {
+    let time_diff = if let Some(&timestamp) = lock_state(&state)?.multi_stopwatch.get(&packet_id) {
         SystemTime::now().duration_since(timestamp)?
     } else {
         Duration::ZERO
@@ -52,6 +71,13 @@ async fn load_packet(
     Ok(time_diff.as_secs().to_string())
 }
 
+#[utoipa::path(
+    post,
+    path = "/12/ulids",
+    tag = "day12",
+    request_body = Vec<String>,
+    responses((status = 200, description = "ULIDs converted to UUIDs, in reverse order", body = Vec<String>))
+)]
 async fn ulids(Json(ulids): Json<UlidRequest>) -> Result<Json<Vec<String>>, AppError> {
     let ulids = ulids
         .iter()
@@ -66,17 +92,28 @@ async fn ulids(Json(ulids): Json<UlidRequest>) -> Result<Json<Vec<String>>, AppE
     Ok(Json(uuids))
 }
 
-#[derive(Serialize)]
-struct UlidsWeekdayResult {
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct UlidsWeekdayResult {
     #[serde(rename = "christmas eve")]
-    christmas_eve: usize,
-    weekday: usize,
+    pub christmas_eve: usize,
+    pub weekday: usize,
     #[serde(rename = "in the future")]
-    in_the_future: usize,
+    pub in_the_future: usize,
     #[serde(rename = "LSB is 1")]
-    lsb_is_1: usize,
+    pub lsb_is_1: usize,
 }
 
+#[utoipa::path(
+    post,
+    path = "/12/ulids/{weekday}",
+    tag = "day12",
+    params(("weekday" = usize, Path, description = "Weekday to count, 0 being Monday")),
+    request_body = Vec<String>,
+    responses(
+        (status = 200, description = "Statistics about the ULIDs", body = UlidsWeekdayResult),
+        (status = 400, description = "Invalid weekday", body = ProblemDetails)
+    )
+)]
 async fn ulids_weekday(
     Path(weekday_req): Path<String>,
     Json(ulids): Json<UlidRequest>,
@@ -85,6 +122,13 @@ async fn ulids_weekday(
         .iter()
         .flat_map(|s| Ulid::from_str(s))
         .collect::<Vec<_>>();
+    let mut weekday_req = weekday_req
+        .parse::<usize>()
+        .map_err(AppError::bad_request)?;
+    weekday_req += 1;
+    if weekday_req == 7 {
+        weekday_req = 0;
+    }
     let mut result = UlidsWeekdayResult {
         christmas_eve: 0,
         weekday: 0,
@@ -96,14 +140,9 @@ async fn ulids_weekday(
         let day = datetime.format("%d").to_string();
         let month = datetime.format("%m").to_string();
         let weekday = datetime.format("%w").to_string();
-        if day == "24".to_string() && month == "12".to_string() {
+        if day == "24" && month == "12" {
             result.christmas_eve += 1;
         }
-        let mut weekday_req = weekday_req.parse::<usize>().unwrap();
-        weekday_req += 1;
-        if weekday_req == 7 {
-            weekday_req = 0;
-        }
         if weekday == weekday_req.to_string() {
             result.weekday += 1;
         }
@@ -0,0 +1,280 @@
+use std::{
+    borrow::Cow,
+    sync::{Arc, Once},
+    time::Duration,
+};
+
+use axum::{
+    extract::ws::{close_code, CloseFrame, Message, WebSocket},
+    response::sse::Event,
+};
+use futures::{stream, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use sqlx::{postgres::PgListener, PgConnection, PgPool};
+use tokio::{
+    sync::{broadcast, broadcast::error::RecvError, watch},
+    time::{sleep, timeout},
+};
+use tracing::{error, warn};
+use utoipa::ToSchema;
+
+use super::Order;
+use crate::{
+    error::AppError,
+    gifts::GiftMetric,
+    orders::{order, OrderRow},
+    repository::OrderRepository,
+    shutdown::Shutdown,
+    tenant::{self, Tenant},
+};
+
+// Notifications are per schema, so that datasets sharing a database don't see each other's orders.
+// Within a schema, each one names the tenant of its orders.
+const CHANNEL_PREFIX: &str = "orders_feed_";
+// Keeps each payload well under the 8000 bytes Postgres allows.
+const NOTIFY_IDS: usize = 500;
+const EVENT_BUFFER: usize = 64;
+const LISTEN_TIMEOUT: Duration = Duration::from_secs(5);
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct OrderFeedEvent {
+    // Orders stored since the previous event, empty in the first event of a subscription.
+    pub orders: Vec<Order>,
+    pub total: i64,
+    pub popular: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Notification {
+    tenant: String,
+    ids: Vec<i32>,
+}
+
+// Announces stored orders to every instance listening. Postgres only delivers the notifications
+// once the transaction commits, so orders that get rolled back are never announced.
+pub(crate) async fn notify(
+    conn: &mut PgConnection,
+    tenant: &Tenant,
+    ids: &[i32],
+) -> Result<(), AppError> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+    let payloads = ids
+        .chunks(NOTIFY_IDS)
+        .map(|ids| {
+            serde_json::to_string(&Notification {
+                tenant: tenant.to_string(),
+                ids: ids.to_vec(),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(AppError::internal)?;
+    sqlx::query(
+        "SELECT pg_notify($1 || current_schema(), payload) FROM UNNEST($2::TEXT[]) AS payload",
+    )
+    .bind(CHANNEL_PREFIX)
+    .bind(payloads)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+// The figures come from the repository, so they match /13/orders/total and /13/orders/popular.
+async fn event(
+    pool: &PgPool,
+    repository: &dyn OrderRepository,
+    tenant: &Tenant,
+    ids: &[i32],
+) -> Result<OrderFeedEvent, AppError> {
+    let mut tx = tenant::begin(pool, tenant).await?;
+    let orders = sqlx::query_as::<_, OrderRow>(
+        "SELECT id, region_id, gift_name, quantity FROM orders WHERE id = ANY($1) ORDER BY id",
+    )
+    .bind(ids)
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .map(order)
+    .collect();
+    Ok(OrderFeedEvent {
+        orders,
+        total: repository.total(tenant, GiftMetric::Quantity).await?,
+        popular: repository.popular(tenant, GiftMetric::Quantity).await?,
+    })
+}
+
+// The listener is started by the first subscriber, so that instances nobody watches don't hold a
+// connection.
+#[derive(Clone)]
+pub(crate) struct Feed {
+    pool: PgPool,
+    orders: Arc<dyn OrderRepository>,
+    shutdown: Shutdown,
+    events: broadcast::Sender<(Tenant, OrderFeedEvent)>,
+    listening: Arc<watch::Sender<bool>>,
+    started: Arc<Once>,
+}
+
+impl Feed {
+    pub(crate) fn new(pool: PgPool, orders: Arc<dyn OrderRepository>, shutdown: &Shutdown) -> Self {
+        Self {
+            pool,
+            orders,
+            shutdown: shutdown.clone(),
+            events: broadcast::channel(EVENT_BUFFER).0,
+            listening: Arc::new(watch::channel(false).0),
+            started: Arc::new(Once::new()),
+        }
+    }
+
+    pub(super) fn shutdown(&self) -> &Shutdown {
+        &self.shutdown
+    }
+
+    // Subscribes to the following events of the tenant before reading its current figures, so
+    // that no order falls in between.
+    pub(super) async fn subscribe(&self, tenant: Tenant) -> Result<Subscription, AppError> {
+        self.started.call_once(|| {
+            tokio::spawn(self.shutdown.track(self.clone().listen()));
+        });
+        let mut listening = self.listening.subscribe();
+        timeout(LISTEN_TIMEOUT, listening.wait_for(|listening| *listening))
+            .await
+            .map_err(|_| AppError::internal(anyhow::anyhow!("The order feed is not listening")))?
+            .map_err(AppError::internal)?;
+        let receiver = self.events.subscribe();
+        Ok(Subscription {
+            current: event(&self.pool, self.orders.as_ref(), &tenant, &[]).await?,
+            tenant,
+            receiver,
+            shutdown: self.shutdown.clone(),
+        })
+    }
+
+    async fn listen(self) {
+        loop {
+            tokio::select! {
+                result = self.relay() => {
+                    if let Err(err) = result {
+                        error!(error = ?err, "order feed listener failed");
+                    }
+                }
+                _ = self.shutdown.triggered() => return,
+            }
+            self.listening.send_replace(false);
+            tokio::select! {
+                _ = sleep(RECONNECT_DELAY) => {}
+                _ = self.shutdown.triggered() => return,
+            }
+        }
+    }
+
+    async fn relay(&self) -> Result<(), AppError> {
+        let mut listener = PgListener::connect_with(&self.pool).await?;
+        let schema = sqlx::query_scalar::<_, String>("SELECT current_schema()")
+            .fetch_one(&mut listener)
+            .await?;
+        listener
+            .listen(&format!("{}{}", CHANNEL_PREFIX, schema))
+            .await?;
+        self.listening.send_replace(true);
+        loop {
+            // Lost connections are reopened by the listener, missing what was sent meanwhile.
+            let notification = listener.recv().await?;
+            if self.events.receiver_count() == 0 {
+                continue;
+            }
+            let payload = serde_json::from_str::<Notification>(notification.payload());
+            let Some((tenant, ids)) = payload
+                .ok()
+                .and_then(|payload| Some((Tenant::parse(&payload.tenant).ok()?, payload.ids)))
+            else {
+                warn!(
+                    payload = notification.payload(),
+                    "invalid order feed payload"
+                );
+                continue;
+            };
+            let event = event(&self.pool, self.orders.as_ref(), &tenant, &ids).await?;
+            let _ = self.events.send((tenant, event));
+        }
+    }
+}
+
+pub(super) struct Subscription {
+    tenant: Tenant,
+    current: OrderFeedEvent,
+    receiver: broadcast::Receiver<(Tenant, OrderFeedEvent)>,
+    shutdown: Shutdown,
+}
+
+// The next event of the tenant, `None` once the feed is gone.
+async fn next_event(
+    receiver: &mut broadcast::Receiver<(Tenant, OrderFeedEvent)>,
+    tenant: &Tenant,
+) -> Option<OrderFeedEvent> {
+    loop {
+        match receiver.recv().await {
+            Ok((event_tenant, event)) if event_tenant == *tenant => return Some(event),
+            Ok(_) => {}
+            Err(RecvError::Lagged(skipped)) => {
+                warn!(skipped, "order feed subscriber is lagging behind");
+            }
+            Err(RecvError::Closed) => return None,
+        }
+    }
+}
+
+impl Subscription {
+    // The current figures, then every following event until the server shuts down.
+    pub(super) fn events(self) -> impl Stream<Item = Result<Event, axum::Error>> {
+        let shutdown = self.shutdown;
+        let following = stream::unfold(
+            (self.receiver, self.tenant),
+            |(mut receiver, tenant)| async move {
+                let event = next_event(&mut receiver, &tenant).await?;
+                Some((event, (receiver, tenant)))
+            },
+        );
+        stream::once(async { self.current })
+            .chain(following)
+            .map(|event| Event::default().event("orders").json_data(event))
+            .take_until(async move { shutdown.triggered().await })
+    }
+
+    pub(super) async fn forward(mut self, mut socket: WebSocket) {
+        let mut next = Some(self.current);
+        loop {
+            if let Some(event) = next.take() {
+                let Ok(text) = serde_json::to_string(&event) else {
+                    break;
+                };
+                if socket.send(Message::Text(text)).await.is_err() {
+                    break;
+                }
+            }
+            tokio::select! {
+                event = next_event(&mut self.receiver, &self.tenant) => match event {
+                    Some(event) => next = Some(event),
+                    None => break,
+                },
+                // Anything sent by the client is ignored, until it goes away.
+                msg = socket.recv() => match msg {
+                    Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                },
+                _ = self.shutdown.triggered() => {
+                    let _ = socket
+                        .send(Message::Close(Some(CloseFrame {
+                            code: close_code::AWAY,
+                            reason: Cow::from("Server is shutting down"),
+                        })))
+                        .await;
+                    break;
+                }
+            }
+        }
+    }
+}
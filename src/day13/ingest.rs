@@ -0,0 +1,282 @@
+use std::{collections::HashSet, fmt};
+
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres, Transaction};
+use utoipa::{IntoParams, ToSchema};
+
+use super::{feed, Order};
+use crate::{
+    audit::{self, Actor},
+    error::AppError,
+    tenant::Tenant,
+};
+
+const GIFT_NAME_MAX_CHARS: usize = 50;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum IngestMode {
+    // Nothing is stored when a single order is rejected.
+    #[default]
+    AllOrNothing,
+    // Valid orders are stored, the others are reported.
+    SkipInvalid,
+    // Orders whose id already exists replace the stored one.
+    Upsert,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct IngestParams {
+    #[serde(default)]
+    pub mode: IngestMode,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectReason {
+    DuplicateId,
+    DuplicateInBatch,
+    UnknownRegion,
+    NegativeQuantity,
+    GiftNameTooLong,
+}
+
+impl fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RejectReason::DuplicateId => write!(f, "An order with the same id already exists"),
+            RejectReason::DuplicateInBatch => write!(f, "The id appears earlier in the batch"),
+            RejectReason::UnknownRegion => write!(f, "The region does not exist"),
+            RejectReason::NegativeQuantity => write!(f, "The quantity is negative"),
+            RejectReason::GiftNameTooLong => write!(
+                f,
+                "The gift name is longer than {} characters",
+                GIFT_NAME_MAX_CHARS
+            ),
+        }
+    }
+}
+
+// Checks the fields that can be validated without the rest of the batch or the database.
+pub(crate) fn check_fields(gift_name: &str, quantity: i32) -> Option<RejectReason> {
+    if quantity < 0 {
+        Some(RejectReason::NegativeQuantity)
+    } else if gift_name.chars().count() > GIFT_NAME_MAX_CHARS {
+        Some(RejectReason::GiftNameTooLong)
+    } else {
+        None
+    }
+}
+
+// Checks an order against the ones seen earlier in the batch and the regions that exist, before
+// it is stored. Ids already stored are only noticed while storing it.
+pub(crate) fn check_order(
+    order: &Order,
+    seen: &mut HashSet<i32>,
+    regions: &HashSet<i32>,
+) -> Option<RejectReason> {
+    if !seen.insert(order.id) {
+        Some(RejectReason::DuplicateInBatch)
+    } else if let Some(reason) = check_fields(&order.gift_name, order.quantity) {
+        Some(reason)
+    } else if !regions.contains(&order.region_id) {
+        Some(RejectReason::UnknownRegion)
+    } else {
+        None
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct RejectedOrder {
+    // Position of the order in the submitted batch.
+    pub index: usize,
+    pub id: i32,
+    pub reason: RejectReason,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct IngestReport {
+    pub mode: IngestMode,
+    pub committed: bool,
+    pub inserted: usize,
+    pub updated: usize,
+    pub rejected: Vec<RejectedOrder>,
+}
+
+impl IngestReport {
+    // Batches that were rolled back answer 422, with the report explaining why.
+    pub fn status(&self) -> StatusCode {
+        if self.committed {
+            StatusCode::OK
+        } else {
+            StatusCode::UNPROCESSABLE_ENTITY
+        }
+    }
+}
+
+pub(crate) fn reject(
+    rejected: &mut Vec<RejectedOrder>,
+    index: usize,
+    order: &Order,
+    reason: RejectReason,
+) {
+    rejected.push(RejectedOrder {
+        index,
+        id: order.id,
+        reason,
+    });
+}
+
+async fn known_regions(
+    tx: &mut Transaction<'_, Postgres>,
+    orders: &[Order],
+) -> Result<HashSet<i32>, AppError> {
+    let region_ids = orders
+        .iter()
+        .map(|order| order.region_id)
+        .collect::<Vec<_>>();
+    let known = sqlx::query_scalar::<_, i32>("SELECT id FROM regions WHERE id = ANY($1)")
+        .bind(region_ids)
+        .fetch_all(&mut **tx)
+        .await?;
+    Ok(known.into_iter().collect())
+}
+
+// Inserts the whole batch with one statement, returning each stored id and whether it was new.
+async fn insert_batch(
+    tx: &mut Transaction<'_, Postgres>,
+    orders: &[(usize, &Order)],
+    mode: IngestMode,
+) -> Result<Vec<(i32, bool)>, AppError> {
+    let on_conflict = match mode {
+        IngestMode::Upsert => {
+            "DO UPDATE SET region_id = EXCLUDED.region_id, gift_name = EXCLUDED.gift_name, quantity = EXCLUDED.quantity"
+        }
+        IngestMode::AllOrNothing | IngestMode::SkipInvalid => "DO NOTHING",
+    };
+    let query = format!(
+        r#"
+        INSERT INTO orders (id, region_id, gift_name, quantity)
+        SELECT * FROM UNNEST($1::INT[], $2::INT[], $3::VARCHAR[], $4::INT[])
+        ON CONFLICT (tenant, id) {}
+        RETURNING id, xmax = 0 AS inserted
+        "#,
+        on_conflict
+    );
+    let stored = sqlx::query_as::<_, (i32, bool)>(&query)
+        .bind(orders.iter().map(|(_, o)| o.id).collect::<Vec<_>>())
+        .bind(orders.iter().map(|(_, o)| o.region_id).collect::<Vec<_>>())
+        .bind(
+            orders
+                .iter()
+                .map(|(_, o)| o.gift_name.clone())
+                .collect::<Vec<_>>(),
+        )
+        .bind(orders.iter().map(|(_, o)| o.quantity).collect::<Vec<_>>())
+        .fetch_all(&mut **tx)
+        .await?;
+    Ok(stored)
+}
+
+// Stores orders arriving in successive chunks within one transaction, reported as a single batch.
+pub(crate) struct OrderIngest {
+    tx: Transaction<'static, Postgres>,
+    tenant: Tenant,
+    mode: IngestMode,
+    seen: HashSet<i32>,
+    received: usize,
+    inserted: usize,
+    updated: usize,
+    rejected: Vec<RejectedOrder>,
+}
+
+impl OrderIngest {
+    pub(crate) async fn begin(
+        pool: &PgPool,
+        tenant: &Tenant,
+        actor: &Actor,
+        mode: IngestMode,
+    ) -> Result<Self, AppError> {
+        Ok(Self {
+            tx: audit::begin(pool, tenant, actor).await?,
+            tenant: tenant.clone(),
+            mode,
+            seen: HashSet::new(),
+            received: 0,
+            inserted: 0,
+            updated: 0,
+            rejected: Vec::new(),
+        })
+    }
+
+    pub(crate) async fn add(&mut self, orders: &[Order]) -> Result<(), AppError> {
+        let regions = known_regions(&mut self.tx, orders).await?;
+
+        let mut valid = Vec::new();
+        for (position, order) in orders.iter().enumerate() {
+            let index = self.received + position;
+            match check_order(order, &mut self.seen, &regions) {
+                Some(reason) => reject(&mut self.rejected, index, order, reason),
+                None => valid.push((index, order)),
+            }
+        }
+        self.received += orders.len();
+
+        // Once an order is rejected, an all-or-nothing batch won't be committed anyway.
+        let insert = !valid.is_empty()
+            && (self.mode != IngestMode::AllOrNothing || self.rejected.is_empty());
+        if !insert {
+            return Ok(());
+        }
+        let stored = insert_batch(&mut self.tx, &valid, self.mode).await?;
+
+        // Without an upsert, the orders missing from the returned rows hit an existing id.
+        let stored_ids = stored.iter().map(|(id, _)| *id).collect::<HashSet<_>>();
+        if self.mode != IngestMode::Upsert {
+            for (index, order) in valid.iter().filter(|(_, o)| !stored_ids.contains(&o.id)) {
+                reject(&mut self.rejected, *index, order, RejectReason::DuplicateId);
+            }
+        }
+        feed::notify(
+            &mut self.tx,
+            &self.tenant,
+            &stored.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+        )
+        .await?;
+        let inserted = stored.iter().filter(|(_, inserted)| *inserted).count();
+        self.inserted += inserted;
+        self.updated += stored.len() - inserted;
+        Ok(())
+    }
+
+    pub(crate) async fn finish(mut self) -> Result<IngestReport, AppError> {
+        self.rejected.sort_by_key(|rejected| rejected.index);
+        let committed = self.mode != IngestMode::AllOrNothing || self.rejected.is_empty();
+        if committed {
+            self.tx.commit().await?;
+        } else {
+            self.tx.rollback().await?;
+        }
+        Ok(IngestReport {
+            mode: self.mode,
+            committed,
+            inserted: if committed { self.inserted } else { 0 },
+            updated: if committed { self.updated } else { 0 },
+            rejected: self.rejected,
+        })
+    }
+}
+
+pub async fn ingest_orders(
+    pool: &PgPool,
+    tenant: &Tenant,
+    actor: &Actor,
+    orders: &[Order],
+    mode: IngestMode,
+) -> Result<IngestReport, AppError> {
+    let mut ingest = OrderIngest::begin(pool, tenant, actor, mode).await?;
+    ingest.add(orders).await?;
+    ingest.finish().await
+}
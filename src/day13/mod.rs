@@ -1,127 +1,251 @@
 use axum::{
-    extract::State,
+    extract::{Query, State, WebSocketUpgrade},
     http::StatusCode,
+    middleware,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
     routing::{get, post},
-    Json, Router,
+    Extension, Json, Router,
 };
+use futures::Stream;
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use tracing::{Instrument, Span};
+use utoipa::{IntoParams, ToSchema};
 
-use crate::CommonState;
+use crate::{
+    audit::Actor,
+    auth::{authorize, Role, RoleGuard},
+    config::{Config, StorageBackend},
+    error::AppError,
+    gifts::MetricParams,
+    shutdown::Shutdown,
+    tenant::Tenant,
+    CommonState,
+};
 
-pub async fn reset(State(state): State<CommonState>) -> StatusCode {
-    sqlx::query!(
-        r#"
-TRUNCATE TABLE orders
-"#
-    )
-    .execute(&state.pool)
-    .await
-    .unwrap();
-    sqlx::query!(
-        r#"
-TRUNCATE TABLE regions
-"#
+mod feed;
+mod ingest;
+
+use self::feed::Feed;
+pub use self::feed::OrderFeedEvent;
+
+pub(crate) use self::ingest::{check_fields, check_order, ingest_orders, reject, OrderIngest};
+pub use self::ingest::{IngestMode, IngestParams, IngestReport, RejectReason, RejectedOrder};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ResetParams {
+    // Name of a snapshot saving the orders and regions before they are deleted.
+    pub snapshot: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/13/reset",
+    tag = "day13",
+    params(ResetParams),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Orders and regions deleted"),
+        (status = 401, description = "Missing or unknown API key", body = ProblemDetails),
+        (status = 403, description = "Admin role required", body = ProblemDetails),
+        (status = 409, description = "A snapshot with the same name already exists", body = ProblemDetails),
+        (status = 422, description = "Invalid snapshot name", body = ProblemDetails)
     )
-    .execute(&state.pool)
-    .await
-    .unwrap();
-    StatusCode::OK
+)]
+pub async fn reset(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Extension(actor): Extension<Actor>,
+    Query(params): Query<ResetParams>,
+) -> Result<StatusCode, AppError> {
+    state
+        .orders
+        .reset(&tenant, &actor, params.snapshot.as_deref())
+        .await?;
+    Ok(StatusCode::OK)
 }
 
-async fn sql(State(state): State<CommonState>) -> String {
+#[utoipa::path(
+    get,
+    path = "/13/sql",
+    tag = "day13",
+    security((), ("bearer" = [])),
+    responses(
+        (status = 200, description = "Result of a test query", body = String),
+        (status = 401, description = "Missing or unknown API key when reads are not public", body = ProblemDetails)
+    )
+)]
+async fn sql(State(state): State<CommonState>) -> Result<String, AppError> {
     let record = sqlx::query!(
         r#"
 SELECT 20231213 as "id!"
 "#
     )
     .fetch_one(&state.pool)
-    .await
-    .unwrap();
+    .await?;
 
-    record.id.to_string()
+    Ok(record.id.to_string())
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
 pub struct Order {
-    id: i32,
-    region_id: i32,
-    gift_name: String,
-    quantity: i32,
+    pub id: i32,
+    pub region_id: i32,
+    pub gift_name: String,
+    pub quantity: i32,
 }
 
-async fn add_order(order: Order, pool: &PgPool) {
-    sqlx::query!(
-        "INSERT INTO orders (id, region_id, gift_name, quantity) VALUES ($1, $2, $3, $4)",
-        order.id,
-        order.region_id,
-        order.gift_name,
-        order.quantity
+#[utoipa::path(
+    post,
+    path = "/13/orders",
+    tag = "day13",
+    params(IngestParams),
+    request_body = Vec<Order>,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Orders stored, with the ones that were rejected", body = IngestReport),
+        (status = 401, description = "Missing or unknown API key", body = ProblemDetails),
+        (status = 403, description = "Writer role required", body = ProblemDetails),
+        (status = 422, description = "Nothing stored because an order was rejected", body = IngestReport)
     )
-    .execute(pool)
-    .await
-    .unwrap();
-}
-
+)]
 pub async fn orders(
     State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Extension(actor): Extension<Actor>,
+    Query(params): Query<IngestParams>,
     Json(orders): Json<Vec<Order>>,
-) -> StatusCode {
-    for order in orders {
-        add_order(order, &state.pool).await;
-    }
-    StatusCode::OK
+) -> Result<(StatusCode, Json<IngestReport>), AppError> {
+    let report = state
+        .orders
+        .ingest(&tenant, &actor, &orders, params.mode)
+        .await?;
+    Ok((report.status(), Json(report)))
 }
 
-#[derive(Serialize)]
-struct OrderTotalResponse {
-    total: i64,
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct OrderTotalResponse {
+    pub total: i64,
 }
 
-async fn orders_total(State(state): State<CommonState>) -> Json<OrderTotalResponse> {
-    if let Ok(result) = sqlx::query!(r#"SELECT SUM(quantity) as "total!" FROM orders"#)
-        .fetch_one(&state.pool)
-        .await
-    {
-        Json(OrderTotalResponse {
-            total: result.total,
-        })
-    } else {
-        Json(OrderTotalResponse { total: 0 })
-    }
+#[utoipa::path(
+    get,
+    path = "/13/orders/total",
+    tag = "day13",
+    params(MetricParams),
+    security((), ("bearer" = [])),
+    responses(
+        (status = 200, description = "Total quantity ordered, or revenue in cents", body = OrderTotalResponse),
+        (status = 401, description = "Missing or unknown API key when reads are not public", body = ProblemDetails)
+    )
+)]
+async fn orders_total(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Query(params): Query<MetricParams>,
+) -> Result<Json<OrderTotalResponse>, AppError> {
+    Ok(Json(OrderTotalResponse {
+        total: state.orders.total(&tenant, params.by).await?,
+    }))
 }
 
-#[derive(Serialize)]
-struct OrderPopularResponse {
-    popular: Option<String>,
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct OrderPopularResponse {
+    pub popular: Option<String>,
 }
 
-async fn orders_popular(State(state): State<CommonState>) -> Json<OrderPopularResponse> {
-    if let Ok(result) = sqlx::query!(
-        r#"
-        SELECT gift_name, SUM(quantity) as total
-        FROM orders
-        GROUP BY gift_name
-        ORDER BY total DESC
-        "#
+#[utoipa::path(
+    get,
+    path = "/13/orders/popular",
+    tag = "day13",
+    params(MetricParams),
+    security((), ("bearer" = [])),
+    responses(
+        (status = 200, description = "Gift with the largest quantity or revenue", body = OrderPopularResponse),
+        (status = 401, description = "Missing or unknown API key when reads are not public", body = ProblemDetails)
     )
-    .fetch_one(&state.pool)
-    .await
-    {
-        Json(OrderPopularResponse {
-            popular: result.gift_name,
-        })
-    } else {
-        Json(OrderPopularResponse { popular: None })
-    }
+)]
+async fn orders_popular(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Query(params): Query<MetricParams>,
+) -> Result<Json<OrderPopularResponse>, AppError> {
+    Ok(Json(OrderPopularResponse {
+        popular: state.orders.popular(&tenant, params.by).await?,
+    }))
 }
 
-pub fn get_routes(state: CommonState) -> Router {
-    Router::new()
-        .route("/13/sql", get(sql))
-        .route("/13/reset", post(reset))
-        .route("/13/orders", post(orders))
-        .route("/13/orders/total", get(orders_total))
-        .route("/13/orders/popular", get(orders_popular))
-        .with_state(state)
+#[utoipa::path(
+    get,
+    path = "/13/orders/feed",
+    tag = "day13",
+    security((), ("bearer" = [])),
+    responses(
+        (status = 200, description = "Server-sent `orders` events, starting with the current figures", content_type = "text/event-stream", body = OrderFeedEvent),
+        (status = 401, description = "Missing or unknown API key when reads are not public", body = ProblemDetails)
+    )
+)]
+async fn orders_feed(
+    State(feed): State<Feed>,
+    Extension(tenant): Extension<Tenant>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, AppError> {
+    let subscription = feed.subscribe(tenant).await?;
+    Ok(Sse::new(subscription.events()).keep_alive(KeepAlive::default()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/13/orders/feed/ws",
+    tag = "day13",
+    security((), ("bearer" = [])),
+    responses(
+        (status = 101, description = "WebSocket sending an `OrderFeedEvent` frame with the current figures, then one per stored batch"),
+        (status = 401, description = "Missing or unknown API key when reads are not public", body = ProblemDetails)
+    )
+)]
+async fn orders_feed_ws(
+    ws: WebSocketUpgrade,
+    State(feed): State<Feed>,
+    Extension(tenant): Extension<Tenant>,
+) -> Result<impl IntoResponse, AppError> {
+    let subscription = feed.subscribe(tenant).await?;
+    let shutdown = feed.shutdown().clone();
+    let span = Span::current();
+    Ok(ws.on_upgrade(move |socket| shutdown.track(subscription.forward(socket).instrument(span))))
+}
+
+pub fn get_routes(state: CommonState, config: &Config, shutdown: &Shutdown) -> Router {
+    let reader =
+        middleware::from_fn_with_state(RoleGuard::new(&state, config, Role::Reader), authorize);
+    let writer =
+        middleware::from_fn_with_state(RoleGuard::new(&state, config, Role::Writer), authorize);
+    let admin =
+        middleware::from_fn_with_state(RoleGuard::new(&state, config, Role::Admin), authorize);
+    let router = Router::new()
+        .route("/13/sql", get(sql).route_layer(reader.clone()))
+        .route("/13/reset", post(reset).route_layer(admin))
+        .route("/13/orders", post(orders).route_layer(writer))
+        .route(
+            "/13/orders/total",
+            get(orders_total).route_layer(reader.clone()),
+        )
+        .route(
+            "/13/orders/popular",
+            get(orders_popular).route_layer(reader.clone()),
+        )
+        .with_state(state.clone());
+    // The feed relies on Postgres notifications, which orders kept in memory never send.
+    if state.backend == StorageBackend::Memory {
+        return router;
+    }
+    router.merge(
+        Router::new()
+            .route("/13/orders/feed", get(orders_feed))
+            .route("/13/orders/feed/ws", get(orders_feed_ws))
+            .route_layer(reader)
+            .with_state(Feed::new(state.pool, state.orders, shutdown)),
+    )
 }
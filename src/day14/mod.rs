@@ -1,7 +1,8 @@
 use askama::Template;
 use askama_axum::IntoResponse;
 use axum::{routing::post, Json, Router};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 #[derive(Template)]
 #[template(path = "index.html", escape = "none")]
@@ -15,16 +16,30 @@ struct SafeTemplate {
     content: String,
 }
 
-#[derive(Deserialize)]
-struct UnsafeRequest {
-    content: String,
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct UnsafeRequest {
+    pub content: String,
 }
 
+#[utoipa::path(
+    post,
+    path = "/14/unsafe",
+    tag = "day14",
+    request_body = UnsafeRequest,
+    responses((status = 200, description = "Page with the content rendered unescaped", body = String, content_type = "text/html"))
+)]
 async fn unsafe_route(Json(req): Json<UnsafeRequest>) -> impl IntoResponse {
     let content = req.content;
     UnsafeTemplate { content }
 }
 
+#[utoipa::path(
+    post,
+    path = "/14/safe",
+    tag = "day14",
+    request_body = UnsafeRequest,
+    responses((status = 200, description = "Page with the content rendered escaped", body = String, content_type = "text/html"))
+)]
 async fn safe_route(Json(req): Json<UnsafeRequest>) -> impl IntoResponse {
     let content = req.content;
     SafeTemplate { content }
@@ -1,31 +1,37 @@
+use std::sync::LazyLock;
+
 use axum::{http::StatusCode, routing::post, Json, Router};
 use fancy_regex::Regex;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+
+use crate::error::AppError;
+
+static SANDWICH: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"([a-zA-Z])\w\1").unwrap());
+static OUT_OF_RANGE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[\u{2980}-\u{2BFF}]").unwrap());
 
-#[derive(Deserialize)]
-struct Password {
-    input: String,
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct Password {
+    pub input: String,
 }
 
-#[derive(Serialize)]
-struct CheckNiceResult {
-    result: String,
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct CheckNiceResult {
+    pub result: String,
 }
 
-#[derive(Serialize)]
-struct CheckGameResult {
-    result: String,
-    reason: String,
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct CheckGameResult {
+    pub result: String,
+    pub reason: String,
 }
 
 fn contains_three_vowels(input: &str) -> bool {
     let vowels = input
         .chars()
-        .filter(|c| match c {
-            'a' | 'e' | 'i' | 'o' | 'u' => true,
-            _ => false,
-        })
+        .filter(|c| matches!(c, 'a' | 'e' | 'i' | 'o' | 'u'))
         .count();
     vowels >= 3
 }
@@ -67,6 +73,16 @@ fn is_nice(input: &str) -> Result<(), String> {
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/15/nice",
+    tag = "day15",
+    request_body = Password,
+    responses(
+        (status = 200, description = "Nice password", body = CheckNiceResult),
+        (status = 400, description = "Naughty password", body = CheckNiceResult)
+    )
+)]
 async fn nice(Json(password): Json<Password>) -> (StatusCode, Json<CheckNiceResult>) {
     match is_nice(&password.input) {
         Ok(()) => (
@@ -89,9 +105,9 @@ fn check_rule_1(input: &str) -> bool {
 }
 
 fn check_rule_2(input: &str) -> bool {
-    let contains_lowercase = input.chars().any(|c| matches!(c, 'a'..='z'));
-    let contains_uppercase = input.chars().any(|c| matches!(c, 'A'..='Z'));
-    let contains_digits = input.chars().any(|c| matches!(c, '0'..='9'));
+    let contains_lowercase = input.chars().any(|c| c.is_ascii_lowercase());
+    let contains_uppercase = input.chars().any(|c| c.is_ascii_uppercase());
+    let contains_digits = input.chars().any(|c| c.is_ascii_digit());
     contains_lowercase && contains_uppercase && contains_digits
 }
 
@@ -107,8 +123,11 @@ fn check_rule_4(input: &str) -> bool {
     for c in input.chars() {
         if c.is_numeric() {
             current_int.push(c);
-        } else if current_int.len() > 0 {
-            ints.push(current_int.parse().unwrap());
+        } else if !current_int.is_empty() {
+            let Ok(int) = current_int.parse() else {
+                return false;
+            };
+            ints.push(int);
             current_int = String::new();
         }
     }
@@ -119,26 +138,26 @@ fn check_rule_4(input: &str) -> bool {
 fn check_rule_5(input: &str) -> bool {
     let joy = input
         .chars()
-        .filter(|c| match c {
-            'j' | 'o' | 'y' => true,
-            _ => false,
-        })
+        .filter(|c| matches!(c, 'j' | 'o' | 'y'))
         .collect::<String>();
-    joy == "joy".to_string()
+    joy == "joy"
 }
 
-fn check_rule_6(input: &str) -> bool {
-    let rule = Regex::new(r"([a-zA-Z])\w\1").unwrap();
-    rule.is_match(input).unwrap()
+// The backreference needs backtracking, which fancy_regex gives up on for pathological inputs.
+fn check_rule_6(input: &str) -> Result<bool, AppError> {
+    SANDWICH
+        .is_match(input)
+        .map_err(AppError::unprocessable_entity)
 }
 
-fn check_rule_7(input: &str) -> bool {
-    let rule = Regex::new(r"[\u{2980}-\u{2BFF}]").unwrap();
-    rule.is_match(input).unwrap()
+fn check_rule_7(input: &str) -> Result<bool, AppError> {
+    OUT_OF_RANGE
+        .is_match(input)
+        .map_err(AppError::unprocessable_entity)
 }
 
 fn check_rule_8(input: &str) -> bool {
-    emojito::find_emoji(input).len() > 0
+    !emojito::find_emoji(input).is_empty()
 }
 
 fn check_rule_9(input: &str) -> bool {
@@ -148,8 +167,8 @@ fn check_rule_9(input: &str) -> bool {
     hex::encode(hash).ends_with('a')
 }
 
-fn is_valid(input: &str) -> Result<(), (usize, String)> {
-    if !check_rule_1(input) {
+fn is_valid(input: &str) -> Result<Result<(), (usize, String)>, AppError> {
+    Ok(if !check_rule_1(input) {
         Err((1, "8 chars".to_string()))
     } else if !check_rule_2(input) {
         Err((2, "more types of chars".to_string()))
@@ -159,9 +178,9 @@ fn is_valid(input: &str) -> Result<(), (usize, String)> {
         Err((4, "math is hard".to_string()))
     } else if !check_rule_5(input) {
         Err((5, "not joyful enough".to_string()))
-    } else if !check_rule_6(input) {
+    } else if !check_rule_6(input)? {
         Err((6, "illegal: no sandwich".to_string()))
-    } else if !check_rule_7(input) {
+    } else if !check_rule_7(input)? {
         Err((7, "outranged".to_string()))
     } else if !check_rule_8(input) {
         Err((8, "😳".to_string()))
@@ -169,11 +188,29 @@ fn is_valid(input: &str) -> Result<(), (usize, String)> {
         Err((9, "not a coffee brewer".to_string()))
     } else {
         Ok(())
-    }
+    })
 }
 
-async fn game(Json(password): Json<Password>) -> (StatusCode, Json<CheckGameResult>) {
-    match is_valid(&password.input) {
+#[utoipa::path(
+    post,
+    path = "/15/game",
+    tag = "day15",
+    request_body = Password,
+    responses(
+        (status = 200, description = "Nice password", body = CheckGameResult),
+        (status = 400, description = "Rules 1 to 4 failed", body = CheckGameResult),
+        (status = 406, description = "Rule 5 failed", body = CheckGameResult),
+        (status = 451, description = "Rule 6 failed", body = CheckGameResult),
+        (status = 416, description = "Rule 7 failed", body = CheckGameResult),
+        (status = 426, description = "Rule 8 failed", body = CheckGameResult),
+        (status = 418, description = "Rule 9 failed", body = CheckGameResult),
+        (status = 422, description = "Password too complex to check", body = ProblemDetails)
+    )
+)]
+async fn game(
+    Json(password): Json<Password>,
+) -> Result<(StatusCode, Json<CheckGameResult>), AppError> {
+    Ok(match is_valid(&password.input)? {
         Ok(()) => (
             StatusCode::OK,
             Json(CheckGameResult {
@@ -181,7 +218,7 @@ async fn game(Json(password): Json<Password>) -> (StatusCode, Json<CheckGameResu
                 reason: "that's a nice password".to_string(),
             }),
         ),
-        Err((1 | 2 | 3 | 4, s)) => (
+        Err((1..=4, s)) => (
             StatusCode::BAD_REQUEST,
             Json(CheckGameResult {
                 result: "naughty".to_string(),
@@ -230,7 +267,7 @@ async fn game(Json(password): Json<Password>) -> (StatusCode, Json<CheckGameResu
                 reason: "uncaught error".to_string(),
             }),
         ),
-    }
+    })
 }
 
 pub fn get_routes() -> Router {
@@ -0,0 +1,74 @@
+use anyhow::anyhow;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::error::AppError;
+
+const DEFAULT_TOP_GIFTS: i64 = 3;
+const MAX_TOP_GIFTS: i64 = 100;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AnalyticsBucket {
+    Day,
+    Week,
+}
+
+impl AnalyticsBucket {
+    // Field name understood by `date_trunc`.
+    pub(crate) fn field(&self) -> &'static str {
+        match self {
+            AnalyticsBucket::Day => "day",
+            AnalyticsBucket::Week => "week",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AnalyticsParams {
+    // Splits the figures by day or by ISO week (starting on Monday, in UTC) of the orders.
+    #[param(inline)]
+    pub bucket: Option<AnalyticsBucket>,
+    // Only orders created at or after this instant.
+    pub from: Option<DateTime<Utc>>,
+    // Only orders created before this instant.
+    pub to: Option<DateTime<Utc>>,
+    // Number of gifts ranked per region, 3 by default and at most 100.
+    pub top: Option<i64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct RankedGift {
+    pub gift_name: String,
+    pub total: i64,
+    // Gifts with the same total share a rank.
+    pub rank: i64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct RegionAnalytics {
+    pub region: String,
+    // First day of the bucket, absent when the figures are not bucketed.
+    pub bucket: Option<NaiveDate>,
+    pub orders: i64,
+    pub total: i64,
+    // Part of the total quantity of the bucket ordered from this region, between 0 and 1.
+    pub share: f64,
+    pub distinct_gifts: i64,
+    pub median_quantity: f64,
+    pub p90_quantity: f64,
+    pub top_gifts: Vec<RankedGift>,
+}
+
+pub(super) fn top_gifts(top: Option<i64>) -> Result<i64, AppError> {
+    let top = top.unwrap_or(DEFAULT_TOP_GIFTS);
+    if !(0..=MAX_TOP_GIFTS).contains(&top) {
+        return Err(AppError::bad_request(anyhow!(
+            "top must be between 0 and {}",
+            MAX_TOP_GIFTS
+        )));
+    }
+    Ok(top)
+}
@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::error::AppError;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, ToSchema)]
+pub struct RegionParent {
+    // Absent to make the region a root.
+    pub parent_id: Option<i64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RegionTreeParams {
+    // Number of gifts listed per region, 3 by default and at most 100.
+    pub top: Option<i64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct RegionNode {
+    pub id: i64,
+    pub name: String,
+    // Quantity ordered from the region itself.
+    pub own_total: i64,
+    // Quantity ordered from the region and all the regions below it.
+    pub total: i64,
+    // Most ordered gifts of the region and all the regions below it.
+    pub top_gifts: Vec<String>,
+    pub children: Vec<RegionNode>,
+}
+
+// Region ids are stored as INT, larger ones are rejected instead of being wrapped around.
+pub(crate) fn region_id(id: i64) -> Result<i32, AppError> {
+    i32::try_from(id)
+        .map_err(|_| AppError::unprocessable_entity(anyhow!("Region id {} is out of range", id)))
+}
+
+// Region id, parent id, name, own total, total and top gifts.
+pub(crate) type NodeRow = (i32, Option<i32>, String, i64, i64, Vec<String>);
+
+// Builds the nodes below `parent_id` out of the rows of every region, sorted by name.
+pub(crate) fn nest(
+    children: &mut HashMap<Option<i32>, Vec<NodeRow>>,
+    parent_id: Option<i32>,
+) -> Vec<RegionNode> {
+    children
+        .remove(&parent_id)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(id, _, name, own_total, total, top_gifts)| RegionNode {
+            id: id.into(),
+            name,
+            own_total,
+            total,
+            top_gifts,
+            children: nest(children, Some(id)),
+        })
+        .collect()
+}
@@ -1,127 +1,256 @@
 use axum::{
-    extract::{Path, State},
-    routing::{get, post},
-    Json, Router,
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    middleware,
+    response::Response,
+    routing::{get, post, put},
+    Extension, Json, Router,
 };
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use utoipa::ToSchema;
 
 use crate::{
+    audit::Actor,
+    auth::{authorize, Role, RoleGuard},
+    config::Config,
     day13::{orders, reset},
+    error::AppError,
+    gifts::MetricParams,
+    tenant::Tenant,
+    transfer::{encode, Format},
     CommonState,
 };
 
-#[derive(Deserialize)]
-struct Region {
-    id: i64,
-    name: String,
-}
+mod analytics;
+mod hierarchy;
 
-async fn add_region(region: Region, pool: &PgPool) {
-    sqlx::query!(
-        "INSERT INTO regions (id, name) VALUES ($1, $2)",
-        region.id as i32,
-        region.name,
-    )
-    .execute(pool)
-    .await
-    .unwrap();
+use self::analytics::top_gifts;
+pub(crate) use self::hierarchy::{nest, region_id, NodeRow};
+pub use self::{
+    analytics::{AnalyticsBucket, AnalyticsParams, RankedGift, RegionAnalytics},
+    hierarchy::{RegionNode, RegionParent, RegionTreeParams},
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct Region {
+    pub id: i64,
+    pub name: String,
+    // Region this one belongs to, absent for top-level regions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<i64>,
 }
 
-#[derive(Serialize, Debug)]
-struct RegionsTotalResponse {
-    region: String,
-    total: i64,
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct RegionsTotalResponse {
+    pub region: String,
+    pub total: i64,
 }
 
-async fn regions_total(State(state): State<CommonState>) -> Json<Vec<RegionsTotalResponse>> {
-    let regions: Vec<RegionsTotalResponse> = sqlx::query_as!(
-        RegionsTotalResponse,
-        r#"
-        SELECT r.name as "region!", SUM(o.quantity) as "total!" 
-        FROM orders o 
-        JOIN regions r ON o.region_id = r.id
-        GROUP BY r.name
-        ORDER BY r.name ASC
-        "#,
+#[utoipa::path(
+    get,
+    path = "/18/regions/total",
+    tag = "day18",
+    params(MetricParams),
+    security((), ("bearer" = [])),
+    responses(
+        (status = 200, description = "Total quantity ordered per region, or revenue in cents", content(
+            ("application/json" = Vec<RegionsTotalResponse>),
+            ("application/x-ndjson" = RegionsTotalResponse),
+            ("text/csv" = String)
+        )),
+        (status = 401, description = "Missing or unknown API key when reads are not public", body = ProblemDetails),
+        (status = 406, description = "None of the accepted formats can be returned", body = ProblemDetails)
     )
-    .fetch_all(&state.pool)
-    .await
-    .unwrap();
+)]
+async fn regions_total(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Query(params): Query<MetricParams>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let format = Format::negotiate(&headers)?;
+    let regions = state.regions.totals(&tenant, params.by).await?;
+    encode(format, &regions)
+}
 
-    Json(regions)
+#[utoipa::path(
+    post,
+    path = "/18/regions",
+    tag = "day18",
+    request_body = Vec<Region>,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Regions stored"),
+        (status = 401, description = "Missing or unknown API key", body = ProblemDetails),
+        (status = 403, description = "Writer role required", body = ProblemDetails),
+        (status = 409, description = "A region with the same id already exists", body = ProblemDetails),
+        (status = 422, description = "A parent region does not exist or an id is out of range", body = ProblemDetails)
+    )
+)]
+async fn regions(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Extension(actor): Extension<Actor>,
+    Json(regions): Json<Vec<Region>>,
+) -> Result<(), AppError> {
+    state.regions.add(&tenant, &actor, &regions).await
 }
 
-async fn regions(State(state): State<CommonState>, Json(regions): Json<Vec<Region>>) {
-    for region in regions {
-        add_region(region, &state.pool).await;
-    }
+#[utoipa::path(
+    put,
+    path = "/18/regions/{id}/parent",
+    tag = "day18",
+    params(("id" = i64, Path, description = "Region id")),
+    request_body = RegionParent,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Region moved with everything below it", body = Region),
+        (status = 401, description = "Missing or unknown API key", body = ProblemDetails),
+        (status = 403, description = "Writer role required", body = ProblemDetails),
+        (status = 404, description = "No region with this id", body = ProblemDetails),
+        (status = 422, description = "Unknown parent, parent below the region, or id out of range", body = ProblemDetails)
+    )
+)]
+async fn region_parent(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Extension(actor): Extension<Actor>,
+    Path(id): Path<i64>,
+    Json(parent): Json<RegionParent>,
+) -> Result<Json<Region>, AppError> {
+    Ok(Json(
+        state
+            .regions
+            .move_region(&tenant, &actor, id, parent.parent_id)
+            .await?,
+    ))
 }
 
-async fn get_regions(pool: &PgPool) -> Vec<Region> {
-    let regions: Vec<Region> = sqlx::query_as!(
-        Region,
-        r#"
-        SELECT id as "id!", name as "name!"
-        FROM regions
-        ORDER BY name ASC
-        "#,
+#[utoipa::path(
+    get,
+    path = "/18/regions/tree",
+    tag = "day18",
+    params(RegionTreeParams),
+    security((), ("bearer" = [])),
+    responses(
+        (status = 200, description = "Regions nested under their parent, with totals rolled up", body = Vec<RegionNode>),
+        (status = 400, description = "Invalid number of gifts", body = ProblemDetails),
+        (status = 401, description = "Missing or unknown API key when reads are not public", body = ProblemDetails)
     )
-    .fetch_all(pool)
-    .await
-    .unwrap();
+)]
+async fn tree(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Query(params): Query<RegionTreeParams>,
+) -> Result<Json<Vec<RegionNode>>, AppError> {
+    let top = top_gifts(params.top)?;
+    Ok(Json(state.regions.tree(&tenant, top).await?))
+}
 
-    regions
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct RegionTopList {
+    pub region: String,
+    pub top_gifts: Vec<String>,
 }
 
+// CSV can't hold the list of gifts, each gift gets a row of its own there.
 #[derive(Serialize)]
-struct RegionTopList {
-    region: String,
-    top_gifts: Vec<String>,
+struct TopGiftRecord<'a> {
+    region: &'a str,
+    position: usize,
+    gift_name: &'a str,
 }
 
-async fn get_top_list_for_region(region_name: String, limit: i64, pool: &PgPool) -> Vec<String> {
-    let top_list = sqlx::query_scalar::<_, String>(
-        r#"
-        SELECT o.gift_name as "gift_name!"
-        FROM orders o
-        JOIN regions r ON o.region_id = r.id
-        WHERE r.name = $1
-        GROUP BY r.name, o.gift_name
-        ORDER BY SUM(o.quantity) DESC, o.gift_name ASC
-        LIMIT $2
-        "#,
+#[utoipa::path(
+    get,
+    path = "/18/regions/top_list/{number}",
+    tag = "day18",
+    params(("number" = i64, Path, description = "Number of gifts to list per region"), MetricParams),
+    security((), ("bearer" = [])),
+    responses(
+        (status = 200, description = "Gifts with the largest quantity or revenue in each region, as one `region,position,gift_name` row per gift in CSV", content(
+            ("application/json" = Vec<RegionTopList>),
+            ("application/x-ndjson" = RegionTopList),
+            ("text/csv" = String)
+        )),
+        (status = 401, description = "Missing or unknown API key when reads are not public", body = ProblemDetails),
+        (status = 406, description = "None of the accepted formats can be returned", body = ProblemDetails)
     )
-    .bind(region_name)
-    .bind(limit)
-    .fetch_all(pool)
-    .await
-    .unwrap();
-
-    top_list
-}
-
+)]
 async fn top_list(
     State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
     Path(number): Path<i64>,
-) -> Json<Vec<RegionTopList>> {
-    let regions = get_regions(&state.pool).await;
-    let mut top_lists = Vec::<RegionTopList>::new();
-    for region in regions {
-        top_lists.push(RegionTopList {
-            region: region.name.clone(),
-            top_gifts: get_top_list_for_region(region.name, number, &state.pool).await,
-        })
+    Query(params): Query<MetricParams>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let format = Format::negotiate(&headers)?;
+    let top_lists = state.regions.top_lists(&tenant, number, params.by).await?;
+    if format != Format::Csv {
+        return encode(format, &top_lists);
     }
-    Json(top_lists)
+    let records: Vec<TopGiftRecord> = top_lists
+        .iter()
+        .flat_map(|top_list| {
+            top_list
+                .top_gifts
+                .iter()
+                .enumerate()
+                .map(|(index, gift_name)| TopGiftRecord {
+                    region: &top_list.region,
+                    position: index + 1,
+                    gift_name,
+                })
+        })
+        .collect();
+    encode(format, &records)
+}
+
+#[utoipa::path(
+    get,
+    path = "/18/regions/analytics",
+    tag = "day18",
+    params(AnalyticsParams),
+    security((), ("bearer" = [])),
+    responses(
+        (status = 200, description = "Order figures of each region, optionally per day or week", body = Vec<RegionAnalytics>),
+        (status = 400, description = "Invalid bucket, time window or number of gifts", body = ProblemDetails),
+        (status = 401, description = "Missing or unknown API key when reads are not public", body = ProblemDetails)
+    )
+)]
+async fn analytics(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Query(params): Query<AnalyticsParams>,
+) -> Result<Json<Vec<RegionAnalytics>>, AppError> {
+    let top = top_gifts(params.top)?;
+    Ok(Json(state.regions.analytics(&tenant, &params, top).await?))
 }
 
-pub fn get_routes(state: CommonState) -> Router {
+pub fn get_routes(state: CommonState, config: &Config) -> Router {
+    let reader =
+        middleware::from_fn_with_state(RoleGuard::new(&state, config, Role::Reader), authorize);
+    let writer =
+        middleware::from_fn_with_state(RoleGuard::new(&state, config, Role::Writer), authorize);
+    let admin =
+        middleware::from_fn_with_state(RoleGuard::new(&state, config, Role::Admin), authorize);
     Router::new()
-        .route("/18/reset", post(reset))
-        .route("/18/orders", post(orders))
-        .route("/18/regions", post(regions))
-        .route("/18/regions/total", get(regions_total))
-        .route("/18/regions/top_list/:number", get(top_list))
+        .route("/18/reset", post(reset).route_layer(admin))
+        .route("/18/orders", post(orders).route_layer(writer.clone()))
+        .route("/18/regions", post(regions).route_layer(writer.clone()))
+        .route(
+            "/18/regions/:id/parent",
+            put(region_parent).route_layer(writer),
+        )
+        .route("/18/regions/tree", get(tree).route_layer(reader.clone()))
+        .route(
+            "/18/regions/total",
+            get(regions_total).route_layer(reader.clone()),
+        )
+        .route(
+            "/18/regions/top_list/:number",
+            get(top_list).route_layer(reader.clone()),
+        )
+        .route("/18/regions/analytics", get(analytics).route_layer(reader))
         .with_state(state)
 }
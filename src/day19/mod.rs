@@ -1,15 +1,17 @@
 use askama_axum::IntoResponse;
 use axum::{
     extract::{
-        ws::{Message, WebSocket},
+        ws::{close_code, CloseFrame, Message, WebSocket},
         Path, State, WebSocketUpgrade,
     },
     routing::{get, post},
     Router,
 };
 use futures::{stream::StreamExt, SinkExt};
-use serde_json::Value;
+use metrics::gauge;
+use serde::{Deserialize, Serialize};
 use std::{
+    borrow::Cow,
     collections::HashMap,
     ops::ControlFlow,
     sync::{
@@ -18,17 +20,52 @@ use std::{
     },
 };
 use tokio::sync::{watch, RwLock};
-use tracing::info;
+use tracing::{debug, info, warn, Instrument, Span};
 
+use crate::{
+    shutdown::Shutdown,
+    telemetry::{CHAT_ACTIVE_ROOMS, CHAT_CONNECTED_SOCKETS},
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ChatMessage {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    pub message: String,
+}
+
+fn going_away() -> Message {
+    Message::Close(Some(CloseFrame {
+        code: close_code::AWAY,
+        reason: Cow::from("Server is shutting down"),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/19/ws/ping",
+    tag = "day19",
+    responses((status = 101, description = "WebSocket answering `ping` with `pong` once `serve` was sent"))
+)]
 async fn ping(ws: WebSocketUpgrade, State(state): State<SharedState>) -> impl IntoResponse {
     info!("Entered ping route");
-    ws.on_upgrade(|socket| handle_socket(socket, state))
+    let shutdown = state.shutdown.clone();
+    let span = Span::current();
+    ws.on_upgrade(move |socket| shutdown.track(handle_socket(socket, state).instrument(span)))
 }
 
 async fn handle_socket(mut socket: WebSocket, state: SharedState) {
     info!("Entered handle_socket");
-    while let Some(msg) = socket.recv().await {
-        if let Ok(msg) = msg {
+    gauge!(CHAT_CONNECTED_SOCKETS).increment(1.0);
+    loop {
+        let msg = tokio::select! {
+            msg = socket.recv() => msg,
+            _ = state.shutdown.triggered() => {
+                let _ = socket.send(going_away()).await;
+                break;
+            }
+        };
+        if let Some(Ok(msg)) = msg {
             match handle_ping_message(msg, &state, &mut socket).await {
                 ControlFlow::Continue(_) => continue,
                 ControlFlow::Break(_) => break,
@@ -40,6 +77,7 @@ async fn handle_socket(mut socket: WebSocket, state: SharedState) {
     state
         .game_started
         .store(false, std::sync::atomic::Ordering::Relaxed);
+    gauge!(CHAT_CONNECTED_SOCKETS).decrement(1.0);
 }
 
 async fn handle_ping_message(
@@ -50,7 +88,7 @@ async fn handle_ping_message(
     info!("Entered handle_ping_message");
     match msg {
         Message::Text(t) => {
-            println!(">>> received {}", t);
+            debug!(message = %t, "received text frame");
             match t.as_str() {
                 "serve" => {
                     state
@@ -63,11 +101,11 @@ async fn handle_ping_message(
                         .game_started
                         .load(std::sync::atomic::Ordering::Relaxed)
                     {
-                        println!("<<< sending pong");
+                        debug!("sending pong");
                         let _ = socket.send(Message::Text("pong".to_string())).await;
                         ControlFlow::Continue(())
                     } else {
-                        println!("XXX game not started");
+                        debug!("ping received before serve");
                         ControlFlow::Continue(())
                     }
                 }
@@ -75,19 +113,19 @@ async fn handle_ping_message(
             }
         }
         Message::Ping(_) => {
-            println!(">>> received Ping message");
+            debug!("received ping frame");
             ControlFlow::Continue(())
         }
         Message::Binary(_) => {
-            println!(">>> received Binary message");
+            debug!("received binary frame");
             ControlFlow::Continue(())
         }
         Message::Pong(_) => {
-            println!(">>> received Pong message");
+            debug!("received pong frame");
             ControlFlow::Continue(())
         }
         Message::Close(_) => {
-            println!(">>> received Close message");
+            debug!("received close frame");
             ControlFlow::Break(())
         }
     }
@@ -98,13 +136,27 @@ struct SharedState {
     game_started: Arc<AtomicBool>,
     room_channel: Arc<RwLock<HashMap<usize, watch::Sender<Message>>>>,
     count: Arc<AtomicUsize>,
+    shutdown: Shutdown,
 }
 
+#[utoipa::path(
+    post,
+    path = "/19/reset",
+    tag = "day19",
+    operation_id = "reset_views",
+    responses((status = 200, description = "View counter reset"))
+)]
 async fn reset(State(state): State<SharedState>) {
     info!("Entered reset route");
     state.count.store(0, std::sync::atomic::Ordering::Relaxed);
 }
 
+#[utoipa::path(
+    get,
+    path = "/19/views",
+    tag = "day19",
+    responses((status = 200, description = "Number of chat messages delivered", body = String))
+)]
 async fn views(State(state): State<SharedState>) -> String {
     info!("Entered views route");
     state
@@ -113,6 +165,16 @@ async fn views(State(state): State<SharedState>) -> String {
         .to_string()
 }
 
+#[utoipa::path(
+    get,
+    path = "/19/ws/room/{room_number}/user/{user_name}",
+    tag = "day19",
+    params(
+        ("room_number" = usize, Path, description = "Room to join"),
+        ("user_name" = String, Path, description = "Name shown to the other members")
+    ),
+    responses((status = 101, description = "WebSocket broadcasting `{\"message\": ...}` frames to the room"))
+)]
 async fn room(
     ws: WebSocketUpgrade,
     State(state): State<SharedState>,
@@ -123,7 +185,11 @@ async fn room(
         let (tx, _rx) = watch::channel(Message::Text("{}".to_string()));
         state.room_channel.write().await.insert(room_number, tx);
     }
-    ws.on_upgrade(move |socket| handle_chat_socket(socket, room_number, user_name, state))
+    let shutdown = state.shutdown.clone();
+    let span = Span::current();
+    ws.on_upgrade(move |socket| {
+        shutdown.track(handle_chat_socket(socket, room_number, user_name, state).instrument(span))
+    })
 }
 
 async fn handle_chat_socket(
@@ -135,44 +201,85 @@ async fn handle_chat_socket(
     info!("Entered handle_chat_socket");
     let (mut sender, mut receiver) = socket.split();
 
-    let mut rx = state
+    let Some(mut rx) = state
         .room_channel
         .read()
         .await
         .get(&room_number)
-        .unwrap()
-        .subscribe();
-
-    let mut send_task = tokio::spawn(async move {
-        while let Ok(()) = rx.changed().await {
-            let msg = rx.borrow().clone();
+        .map(|tx| tx.subscribe())
+    else {
+        return;
+    };
+    gauge!(CHAT_CONNECTED_SOCKETS).increment(1.0);
+    record_active_rooms(&state).await;
 
-            if sender.send(msg).await.is_ok() {
-            } else {
-                break;
+    let shutdown = state.shutdown.clone();
+    let mut send_task = tokio::spawn(
+        async move {
+            loop {
+                tokio::select! {
+                    changed = rx.changed() => {
+                        if changed.is_err() {
+                            break;
+                        }
+                        let msg = rx.borrow().clone();
+                        if sender.send(msg).await.is_err() {
+                            break;
+                        }
+                    }
+                    _ = shutdown.triggered() => {
+                        let _ = sender.send(going_away()).await;
+                        break;
+                    }
+                }
             }
         }
-    });
+        .in_current_span(),
+    );
 
     let recv_user = user_name.clone();
-    let mut recv_task = tokio::spawn(async move {
-        while let Some(msg) = receiver.next().await {
-            if let Ok(msg) = msg {
-                if process_chat_message(msg, room_number, recv_user.clone(), state.clone())
-                    .await
-                    .is_continue()
-                {
-                    continue;
+    let recv_state = state.clone();
+    let mut recv_task = tokio::spawn(
+        async move {
+            while let Some(msg) = receiver.next().await {
+                if let Ok(msg) = msg {
+                    if process_chat_message(msg, room_number, recv_user.clone(), recv_state.clone())
+                        .await
+                        .is_continue()
+                    {
+                        continue;
+                    }
                 }
+                break;
             }
-            break;
         }
-    });
+        .in_current_span(),
+    );
 
+    // Wait for the aborted task too, so its receiver is gone before the rooms are counted.
     tokio::select! {
-        _ = (&mut send_task) => recv_task.abort(),
-        _ = (&mut recv_task) => send_task.abort(),
+        _ = (&mut send_task) => {
+            recv_task.abort();
+            let _ = recv_task.await;
+        }
+        _ = (&mut recv_task) => {
+            send_task.abort();
+            let _ = send_task.await;
+        }
     };
+    gauge!(CHAT_CONNECTED_SOCKETS).decrement(1.0);
+    record_active_rooms(&state).await;
+}
+
+async fn record_active_rooms(state: &SharedState) {
+    let active = state
+        .room_channel
+        .read()
+        .await
+        .values()
+        .filter(|tx| tx.receiver_count() > 0)
+        .count();
+    gauge!(CHAT_ACTIVE_ROOMS).set(active as f64);
 }
 
 async fn process_chat_message(
@@ -184,63 +291,55 @@ async fn process_chat_message(
     info!("Entered process_chat_message");
     match msg {
         Message::Text(text) => {
-            let msg = serde_json::from_str::<Value>(&text).unwrap();
-            let message = msg.get("message").unwrap().as_str().unwrap();
-            if msg.get("user").is_none() {
-                if message.len() > 128 {
-                    println!("message too long");
+            let Ok(msg) = serde_json::from_str::<ChatMessage>(&text) else {
+                warn!(%user, "message is not a valid chat message");
+                return ControlFlow::Continue(());
+            };
+            if msg.user.is_none() {
+                if msg.message.len() > 128 {
+                    warn!(%user, length = msg.message.len(), "message too long");
                     return ControlFlow::Continue(());
                 }
 
-                let broadcast_msg = serde_json::json!({
-                    "user": user,
-                    "message": message,
-                });
-
-                if state
-                    .room_channel
-                    .write()
-                    .await
-                    .get(&room_number)
-                    .unwrap()
-                    .send(Message::Text(broadcast_msg.to_string()))
-                    .is_ok()
-                {
-                    let count = state
-                        .room_channel
-                        .write()
-                        .await
-                        .get(&room_number)
-                        .unwrap()
-                        .receiver_count();
+                let broadcast_msg = ChatMessage {
+                    user: Some(user.clone()),
+                    message: msg.message,
+                };
+                let Ok(broadcast_msg) = serde_json::to_string(&broadcast_msg) else {
+                    return ControlFlow::Continue(());
+                };
+
+                let room_channel = state.room_channel.read().await;
+                let Some(tx) = room_channel.get(&room_number) else {
+                    return ControlFlow::Break(());
+                };
+                if tx.send(Message::Text(broadcast_msg)).is_ok() {
+                    let count = tx.receiver_count();
                     state
                         .count
                         .fetch_add(count, std::sync::atomic::Ordering::Relaxed);
                 } else {
-                    println!("client disconnected");
+                    debug!(%user, "client disconnected");
                     return ControlFlow::Break(());
                 }
             }
         }
         Message::Close(c) => {
             if let Some(cf) = c {
-                println!(
-                    ">>> client sent close with code {} and reason `{}`",
-                    cf.code, cf.reason
-                );
+                debug!(%user, code = cf.code, reason = %cf.reason, "client sent close frame");
             } else {
-                println!(">>> {} somehow sent close message without CloseFrame", user);
+                debug!(%user, "client sent close without a close frame");
             }
             return ControlFlow::Break(());
         }
         _ => {
-            println!(">>> client sent something else: {:?} ", msg);
+            debug!(%user, ?msg, "ignoring non-text frame");
         }
     }
     ControlFlow::Continue(())
 }
 
-pub fn get_routes() -> Router {
+pub fn get_routes(shutdown: &Shutdown) -> Router {
     Router::new()
         .route("/19/ws/ping", get(ping))
         .route("/19/reset", post(reset))
@@ -250,5 +349,6 @@ pub fn get_routes() -> Router {
             game_started: Arc::new(AtomicBool::new(false)),
             room_channel: Arc::new(RwLock::new(HashMap::new())),
             count: Arc::new(AtomicUsize::new(0)),
+            shutdown: shutdown.clone(),
         })
 }
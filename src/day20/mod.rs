@@ -2,75 +2,150 @@ use core::str;
 
 use bytes::Buf;
 
-use askama_axum::IntoResponse;
-use axum::{body::Bytes, routing::post, Router};
+use anyhow::anyhow;
+use axum::{body::Bytes, extract::State, routing::post, Router};
 use git2::{Repository, TreeWalkMode, TreeWalkResult};
 use tar::Archive;
 
-async fn archive_files(file: Bytes) -> impl IntoResponse {
+use crate::{
+    config::{Config, DecodeLimits},
+    error::AppError,
+    shutdown::Shutdown,
+};
+
+#[derive(Clone)]
+struct ArchiveState {
+    shutdown: Shutdown,
+    limits: DecodeLimits,
+}
+
+#[utoipa::path(
+    post,
+    path = "/20/archive_files",
+    tag = "day20",
+    request_body(content = Vec<u8>, content_type = "application/x-tar"),
+    responses(
+        (status = 200, description = "Number of files in the archive", body = String),
+        (status = 400, description = "Invalid archive", body = ProblemDetails)
+    )
+)]
+async fn archive_files(file: Bytes) -> Result<String, AppError> {
     let mut archive = Archive::new(file.reader());
-    archive.entries().unwrap().count().to_string()
+    let entries = archive.entries().map_err(AppError::bad_request)?;
+    Ok(entries.count().to_string())
 }
 
-async fn archive_files_size(file: Bytes) -> impl IntoResponse {
+#[utoipa::path(
+    post,
+    path = "/20/archive_files_size",
+    tag = "day20",
+    request_body(content = Vec<u8>, content_type = "application/x-tar"),
+    responses(
+        (status = 200, description = "Total size of the files in the archive", body = String),
+        (status = 400, description = "Invalid archive", body = ProblemDetails)
+    )
+)]
+async fn archive_files_size(file: Bytes) -> Result<String, AppError> {
     let mut archive = Archive::new(file.reader());
-    archive
-        .entries()
-        .unwrap()
-        .map(|f| f.unwrap().size())
-        .sum::<u64>()
-        .to_string()
+    let mut size = 0;
+    for entry in archive.entries().map_err(AppError::bad_request)? {
+        size += entry.map_err(AppError::bad_request)?.size();
+    }
+    Ok(size.to_string())
+}
+
+#[utoipa::path(
+    post,
+    path = "/20/cookie",
+    tag = "day20",
+    request_body(content = Vec<u8>, content_type = "application/x-tar", description = "Archive of a git repository"),
+    responses(
+        (status = 200, description = "Author and id of the commit that hid the cookie", body = String),
+        (status = 400, description = "Invalid archive or repository", body = ProblemDetails),
+        (status = 404, description = "No christmas branch", body = ProblemDetails),
+        (status = 413, description = "Archive has too many entries or unpacks too large", body = ProblemDetails)
+    )
+)]
+async fn cookie(State(state): State<ArchiveState>, file: Bytes) -> Result<String, AppError> {
+    let limits = state.limits;
+    state
+        .shutdown
+        .spawn_blocking(move || find_cookie(file, &limits))
+        .await?
 }
 
-async fn cookie(file: Bytes) -> impl IntoResponse {
-    let repo_dir = tempfile::tempdir().unwrap();
+fn unpack(file: Bytes, dir: &std::path::Path, limits: &DecodeLimits) -> Result<(), AppError> {
     let mut archive = Archive::new(file.reader());
-    if archive.unpack(repo_dir.as_ref()).is_ok() {
-        let repo = Repository::open(repo_dir.path()).unwrap();
-        let branch = repo
-            .find_branch("christmas", git2::BranchType::Local)
-            .unwrap();
-        let mut commit = branch.get().peel_to_commit().unwrap();
-        while commit.parent_count() > 0 {
-            let mut found = false;
-            commit
-                .tree()
-                .unwrap()
-                .walk(TreeWalkMode::PreOrder, |_, entry| {
-                    if let Some(name) = entry.name() {
-                        if name == "santa.txt" {
-                            let obj = entry.to_object(&repo).unwrap();
-                            let blob = obj.as_blob().unwrap();
-                            let content = blob.content();
-                            let text = str::from_utf8(content).unwrap();
-                            if text.contains("COOKIE") {
-                                found = true;
-                                TreeWalkResult::Abort
-                            } else {
-                                TreeWalkResult::Ok
-                            }
-                        } else {
-                            TreeWalkResult::Ok
-                        }
-                    } else {
-                        TreeWalkResult::Ok
-                    }
-                })
-                .unwrap();
-            if found {
-                break;
+    let mut unpacked = 0u64;
+    for (count, entry) in archive
+        .entries()
+        .map_err(AppError::bad_request)?
+        .enumerate()
+    {
+        if count >= limits.archive_max_entries {
+            return Err(AppError::payload_too_large(anyhow!(
+                "Archive has more than {} entries",
+                limits.archive_max_entries
+            )));
+        }
+        let mut entry = entry.map_err(AppError::bad_request)?;
+        unpacked = unpacked.saturating_add(entry.size());
+        if unpacked > limits.archive_max_unpacked_bytes {
+            return Err(AppError::payload_too_large(anyhow!(
+                "Archive unpacks to more than {} bytes",
+                limits.archive_max_unpacked_bytes
+            )));
+        }
+        entry.unpack_in(dir).map_err(AppError::bad_request)?;
+    }
+    Ok(())
+}
+
+fn find_cookie(file: Bytes, limits: &DecodeLimits) -> Result<String, AppError> {
+    let repo_dir = tempfile::tempdir()?;
+    unpack(file, repo_dir.path(), limits)?;
+    let repo = Repository::open(repo_dir.path()).map_err(AppError::bad_request)?;
+    let branch = repo
+        .find_branch("christmas", git2::BranchType::Local)
+        .map_err(AppError::not_found)?;
+    let mut commit = branch.get().peel_to_commit()?;
+    while commit.parent_count() > 0 {
+        let mut found = false;
+        commit.tree()?.walk(TreeWalkMode::PreOrder, |_, entry| {
+            if entry.name() != Some("santa.txt") {
+                return TreeWalkResult::Ok;
+            }
+            let contains_cookie = entry.to_object(&repo).is_ok_and(|obj| {
+                obj.as_blob()
+                    .and_then(|blob| str::from_utf8(blob.content()).ok())
+                    .is_some_and(|text| text.contains("COOKIE"))
+            });
+            if contains_cookie {
+                found = true;
+                TreeWalkResult::Abort
+            } else {
+                TreeWalkResult::Ok
             }
-            commit = commit.parent(0).unwrap();
+        })?;
+        if found {
+            break;
         }
-        format!("{} {}", commit.author().name().unwrap(), commit.id())
-    } else {
-        axum::http::StatusCode::INTERNAL_SERVER_ERROR.to_string()
+        commit = commit.parent(0)?;
     }
+    let author = commit.author();
+    let author = author
+        .name()
+        .ok_or_else(|| AppError::bad_request(anyhow!("Commit author is not valid UTF-8")))?;
+    Ok(format!("{} {}", author, commit.id()))
 }
 
-pub fn get_routes() -> Router {
+pub fn get_routes(shutdown: &Shutdown, config: &Config) -> Router {
     Router::new()
         .route("/20/archive_files", post(archive_files))
         .route("/20/archive_files_size", post(archive_files_size))
         .route("/20/cookie", post(cookie))
+        .with_state(ArchiveState {
+            shutdown: shutdown.clone(),
+            limits: config.limits.decode.clone(),
+        })
 }
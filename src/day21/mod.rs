@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-use anyhow::Result;
+use anyhow::anyhow;
 use axum::{extract::Path, routing::get, Router};
 use country_boundaries::{CountryBoundaries, LatLon, BOUNDARIES_ODBL_360X180};
 use dms_coordinates::DMS;
@@ -30,12 +30,13 @@ impl From<DdegCoords> for DmsCoords {
 }
 
 fn format_dms(dms: DMS) -> String {
+    let cardinal = dms
+        .cardinal
+        .map(|cardinal| cardinal.to_string())
+        .unwrap_or_default();
     format!(
         "{}°{}'{:.3}''{}",
-        dms.degrees,
-        dms.minutes,
-        dms.seconds,
-        dms.cardinal.unwrap()
+        dms.degrees, dms.minutes, dms.seconds, cardinal
     )
 }
 
@@ -50,9 +51,15 @@ impl Display for DmsCoords {
     }
 }
 
-fn get_coordinates(binary: &str) -> Result<DdegCoords> {
-    let n = u64::from_str_radix(binary, 2)?;
+fn get_coordinates(binary: &str) -> Result<DdegCoords, AppError> {
+    let n = u64::from_str_radix(binary, 2).map_err(AppError::bad_request)?;
     let cell_id = CellID(n);
+    if !cell_id.is_valid() {
+        return Err(AppError::bad_request(anyhow!(
+            "{} is not a valid S2 cell id",
+            binary
+        )));
+    }
     let point = cell::Cell::from(cell_id).center();
     let latitude = point.latitude().deg();
     let longitude = point.longitude().deg();
@@ -62,20 +69,49 @@ fn get_coordinates(binary: &str) -> Result<DdegCoords> {
     })
 }
 
+#[utoipa::path(
+    get,
+    path = "/21/coords/{binary}",
+    tag = "day21",
+    params(("binary" = String, Path, description = "S2 cell id in binary")),
+    responses(
+        (status = 200, description = "Center of the cell in DMS", body = String),
+        (status = 400, description = "Invalid cell id", body = ProblemDetails)
+    )
+)]
 async fn coords(Path(binary): Path<String>) -> Result<String, AppError> {
     let ddeg_coords = get_coordinates(&binary)?;
     let dms_coords = DmsCoords::from(ddeg_coords);
     Ok(format!("{}", dms_coords))
 }
 
+#[utoipa::path(
+    get,
+    path = "/21/country/{binary}",
+    tag = "day21",
+    params(("binary" = String, Path, description = "S2 cell id in binary")),
+    responses(
+        (status = 200, description = "Country the cell is in", body = String),
+        (status = 400, description = "Invalid cell id", body = ProblemDetails),
+        (status = 404, description = "The cell is not in any country", body = ProblemDetails)
+    )
+)]
 async fn country(Path(binary): Path<String>) -> Result<String, AppError> {
     let ddeg_coords = get_coordinates(&binary)?;
-    let lat_lon = LatLon::new(ddeg_coords.ddeg_latitude, ddeg_coords.ddeg_longitude)?;
+    let lat_lon = LatLon::new(ddeg_coords.ddeg_latitude, ddeg_coords.ddeg_longitude)
+        .map_err(AppError::bad_request)?;
     let boundaries = CountryBoundaries::from_reader(BOUNDARIES_ODBL_360X180)?;
-    let country_name = rust_iso3166::from_alpha2(boundaries.ids(lat_lon).last().unwrap())
-        .unwrap()
+    let country_name = boundaries
+        .ids(lat_lon)
+        .last()
+        .and_then(|id| rust_iso3166::from_alpha2(id))
+        .ok_or_else(|| AppError::not_found(anyhow!("No country found at {}", binary)))?
         .name;
-    Ok(country_name.split(' ').next().unwrap().to_string())
+    Ok(country_name
+        .split(' ')
+        .next()
+        .unwrap_or(country_name)
+        .to_string())
 }
 
 pub fn get_routes() -> Router {
@@ -1,8 +1,23 @@
-use axum::{routing::post, Router};
-
-use crate::error::AppError;
-
-async fn integers(input: String) -> Result<String, AppError> {
+use anyhow::anyhow;
+use axum::{extract::State, routing::post, Router};
+
+use crate::{
+    config::{Config, DecodeLimits},
+    error::AppError,
+};
+
+#[utoipa::path(
+    post,
+    path = "/22/integers",
+    tag = "day22",
+    request_body(content = String, content_type = "text/plain", description = "One integer per line"),
+    responses(
+        (status = 200, description = "One present per unpaired integer", body = String),
+        (status = 400, description = "No unpaired integer", body = ProblemDetails),
+        (status = 413, description = "Too many presents to wrap", body = ProblemDetails)
+    )
+)]
+async fn integers(State(limits): State<DecodeLimits>, input: String) -> Result<String, AppError> {
     let mut nums: Vec<u64> = input.lines().flat_map(|n| n.parse::<u64>()).collect();
     nums.sort();
     let v: i64 = nums
@@ -16,8 +31,18 @@ async fn integers(input: String) -> Result<String, AppError> {
             }
         })
         .sum();
-    let packages = String::from("🎁");
-    Ok(packages.repeat(v as usize))
+    let package = "🎁";
+    let count = usize::try_from(v).map_err(AppError::bad_request)?;
+    count
+        .checked_mul(package.len())
+        .filter(|size| *size <= limits.presents_max_bytes)
+        .ok_or_else(|| {
+            AppError::payload_too_large(anyhow!(
+                "More than {} bytes of presents to wrap",
+                limits.presents_max_bytes
+            ))
+        })?;
+    Ok(package.repeat(count))
 }
 
 #[derive(Debug)]
@@ -36,13 +61,25 @@ impl Star {
     }
 }
 
-impl From<&str> for Star {
-    fn from(s: &str) -> Self {
+fn next_number<T: std::str::FromStr>(
+    parts: &mut std::str::Split<'_, char>,
+    line: &str,
+) -> Result<T, AppError> {
+    parts
+        .next()
+        .and_then(|part| part.parse::<T>().ok())
+        .ok_or_else(|| AppError::bad_request(anyhow!("Malformed line `{}`", line)))
+}
+
+impl TryFrom<&str> for Star {
+    type Error = AppError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
         let mut coords = s.split(' ');
-        let x = coords.next().unwrap().parse::<i32>().unwrap();
-        let y = coords.next().unwrap().parse::<i32>().unwrap();
-        let z = coords.next().unwrap().parse::<i32>().unwrap();
-        Self { x, y, z }
+        let x = next_number(&mut coords, s)?;
+        let y = next_number(&mut coords, s)?;
+        let z = next_number(&mut coords, s)?;
+        Ok(Self { x, y, z })
     }
 }
 
@@ -52,15 +89,17 @@ struct Portal {
     destination: usize,
 }
 
-impl From<&str> for Portal {
-    fn from(s: &str) -> Self {
+impl TryFrom<&str> for Portal {
+    type Error = AppError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
         let mut path = s.split(' ');
-        let source = path.next().unwrap().parse::<usize>().unwrap();
-        let destination = path.next().unwrap().parse::<usize>().unwrap();
-        Self {
+        let source = next_number(&mut path, s)?;
+        let destination = next_number(&mut path, s)?;
+        Ok(Self {
             source,
             destination,
-        }
+        })
     }
 }
 
@@ -70,88 +109,143 @@ struct StarMap {
     portals: Vec<Portal>,
 }
 
-impl From<String> for StarMap {
-    fn from(s: String) -> Self {
+fn next_line<'a>(lines: &mut std::str::Lines<'a>) -> Result<&'a str, AppError> {
+    lines
+        .next()
+        .ok_or_else(|| AppError::bad_request(anyhow!("Unexpected end of star map")))
+}
+
+impl StarMap {
+    fn parse(s: &str, limits: &DecodeLimits) -> Result<Self, AppError> {
         let mut lines = s.lines();
-        let number_of_stars = lines.next().unwrap().parse::<usize>().unwrap();
+        let number_of_stars = next_line(&mut lines)?
+            .parse::<usize>()
+            .map_err(AppError::bad_request)?;
+        if number_of_stars == 0 {
+            return Err(AppError::bad_request(anyhow!("Star map has no stars")));
+        }
+        if number_of_stars > limits.star_map_max_stars {
+            return Err(AppError::payload_too_large(anyhow!(
+                "Star map has more than {} stars",
+                limits.star_map_max_stars
+            )));
+        }
         let mut stars = Vec::new();
         for _i in 0..number_of_stars {
-            stars.push(Star::from(lines.next().unwrap()));
+            stars.push(Star::try_from(next_line(&mut lines)?)?);
+        }
+        let number_of_portals = next_line(&mut lines)?
+            .parse::<usize>()
+            .map_err(AppError::bad_request)?;
+        if number_of_portals > limits.star_map_max_portals {
+            return Err(AppError::payload_too_large(anyhow!(
+                "Star map has more than {} portals",
+                limits.star_map_max_portals
+            )));
         }
-        let number_of_portals = lines.next().unwrap().parse::<usize>().unwrap();
         let mut portals = Vec::new();
         for _i in 0..number_of_portals {
-            portals.push(Portal::from(lines.next().unwrap()));
+            let portal = Portal::try_from(next_line(&mut lines)?)?;
+            if portal.source >= number_of_stars || portal.destination >= number_of_stars {
+                return Err(AppError::bad_request(anyhow!(
+                    "Portal {} -> {} leads to an unknown star",
+                    portal.source,
+                    portal.destination
+                )));
+            }
+            portals.push(portal);
         }
-        Self { stars, portals }
+        Ok(Self { stars, portals })
     }
-}
 
-impl StarMap {
     fn last_star_id(&self) -> usize {
         self.stars.len() - 1
     }
 
     fn get_star(&self, id: usize) -> Option<&Star> {
-        self.stars.iter().nth(id)
+        self.stars.get(id)
     }
 
     fn portals_from_source(&self, source: usize) -> Vec<Portal> {
         self.portals
             .iter()
-            .cloned()
             .filter(|p| p.source == source)
+            .cloned()
             .collect()
     }
 
-    fn find_all_paths(&self) -> Vec<Vec<usize>> {
+    // The search is exponential in the number of portals, `budget` bounds the number of steps taken.
+    fn find_all_paths(&self, mut budget: usize) -> Result<Vec<Vec<usize>>, AppError> {
         let mut result: Vec<Vec<usize>> = Vec::new();
         for portal in self.portals_from_source(0) {
             let mut path: Vec<usize> = Vec::new();
             path.push(portal.source);
             path.push(portal.destination);
-            self.find_path(&mut result, portal.destination, &mut path);
+            self.find_path(&mut result, portal.destination, &mut path, &mut budget)?;
         }
-        result
+        Ok(result)
     }
 
-    fn find_path(&self, paths: &mut Vec<Vec<usize>>, start_star_id: usize, path: &mut Vec<usize>) {
+    fn find_path(
+        &self,
+        paths: &mut Vec<Vec<usize>>,
+        start_star_id: usize,
+        path: &mut Vec<usize>,
+        budget: &mut usize,
+    ) -> Result<(), AppError> {
+        *budget = budget.checked_sub(1).ok_or_else(|| {
+            AppError::payload_too_large(anyhow!("Star map is too complex to explore"))
+        })?;
         if start_star_id == self.last_star_id() {
             paths.push(path.clone());
         } else {
             for portal in self.portals_from_source(start_star_id) {
                 if !path.contains(&portal.destination) {
                     path.push(portal.destination);
-                    self.find_path(paths, portal.destination, path);
+                    self.find_path(paths, portal.destination, path, budget)?;
                 }
                 path.pop();
             }
         }
+        Ok(())
     }
 }
 
-async fn rocket(input: String) -> String {
-    let star_map = StarMap::from(input);
-    let result = star_map.find_all_paths();
+#[utoipa::path(
+    post,
+    path = "/22/rocket",
+    tag = "day22",
+    request_body(content = String, content_type = "text/plain", description = "Stars followed by portals"),
+    responses(
+        (status = 200, description = "Portals used and distance travelled on the shortest path", body = String),
+        (status = 400, description = "Malformed star map or no path", body = ProblemDetails),
+        (status = 413, description = "Star map too large to explore", body = ProblemDetails)
+    )
+)]
+async fn rocket(State(limits): State<DecodeLimits>, input: String) -> Result<String, AppError> {
+    let star_map = StarMap::parse(&input, &limits)?;
+    let result = star_map.find_all_paths(limits.star_map_max_search_steps)?;
     let min_path = result
         .iter()
         .min_by(|v1, v2| v1.len().cmp(&v2.len()))
-        .unwrap();
+        .ok_or_else(|| AppError::bad_request(anyhow!("No path leads to the last star")))?;
     let mut distance: f32 = 0.0;
     let mut path_iter = min_path.iter().peekable();
     while let Some(star) = path_iter.next() {
         if let Some(other) = path_iter.peek() {
-            let star = star_map.get_star(*star).unwrap();
-            let other = star_map.get_star(**other).unwrap();
-            let curr_distance = star.distance(other);
-            distance += curr_distance;
+            if let (Some(star), Some(other)) =
+                (star_map.get_star(*star), star_map.get_star(**other))
+            {
+                distance += star.distance(other);
+            }
         }
     }
-    format!("{} {:.3}", min_path.len() - 1, distance)
+    Ok(format!("{} {:.3}", min_path.len() - 1, distance))
 }
 
-pub fn get_routes() -> Router {
+pub fn get_routes(config: &Config) -> Router {
     Router::new()
         .route("/22/integers", post(integers))
         .route("/22/rocket", post(rocket))
+        .with_state(config.limits.decode.clone())
 }
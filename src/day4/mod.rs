@@ -1,92 +1,57 @@
-use axum::{routing::post, Json, Router};
-use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
-#[derive(Deserialize)]
-struct Reindeer {
-    name: String,
-    strength: i32,
-    speed: Option<f32>,
-    height: Option<i32>,
-    antler_width: Option<i32>,
-    snow_magic_power: Option<i32>,
-    favorite_food: Option<String>,
-    #[serde(rename = "cAnD13s_3ATeN-yesT3rdAy")]
-    candies_eaten_yesterday: Option<i32>,
-}
+use axum::{
+    extract::{Query, State},
+    routing::post,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Serialize)]
-struct ContestResult {
-    fastest: String,
-    tallest: String,
-    magician: String,
-    consumer: String,
-}
+use crate::{config::Config, error::AppError};
 
-impl ContestResult {
-    pub fn get_contest_results(reindeers: &Vec<Reindeer>) -> Self {
-        ContestResult {
-            fastest: Self::get_fastest(reindeers),
-            tallest: Self::get_tallest(reindeers),
-            magician: Self::get_magician(reindeers),
-            consumer: Self::get_consumer(reindeers),
-        }
-    }
-    fn get_fastest(reindeers: &Vec<Reindeer>) -> String {
-        let mut fastest_reindeer = reindeers.iter().next().unwrap();
-        for reindeer in reindeers.iter().skip(1) {
-            if reindeer.speed > fastest_reindeer.speed {
-                fastest_reindeer = reindeer;
-            }
-        }
-        format!(
-            "Speeding past the finish line with a strength of {} is {}",
-            fastest_reindeer.strength, fastest_reindeer.name
-        )
-    }
+mod scoring;
 
-    fn get_tallest(reindeers: &Vec<Reindeer>) -> String {
-        let mut tallest_reindeer = reindeers.iter().next().unwrap();
-        for reindeer in reindeers.iter().skip(1) {
-            if reindeer.height > tallest_reindeer.height {
-                tallest_reindeer = reindeer;
-            }
-        }
-        format!(
-            "{} is standing tall with his {} cm wide antlers",
-            tallest_reindeer.name,
-            tallest_reindeer.antler_width.unwrap()
-        )
-    }
+pub use self::scoring::{
+    CategoryDefinition, ContestOptions, ContestRules, Direction, MissingStats, Score, Stat,
+    TieBreak,
+};
 
-    fn get_magician(reindeers: &Vec<Reindeer>) -> String {
-        let mut magician_reindeer = reindeers.iter().next().unwrap();
-        for reindeer in reindeers.iter().skip(1) {
-            if reindeer.snow_magic_power > magician_reindeer.snow_magic_power {
-                magician_reindeer = reindeer;
-            }
-        }
-        format!(
-            "{} could blast you away with a snow magic power of {}",
-            magician_reindeer.name,
-            magician_reindeer.snow_magic_power.unwrap()
-        )
-    }
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct Reindeer {
+    pub name: String,
+    pub strength: i32,
+    pub speed: Option<f32>,
+    pub height: Option<i32>,
+    pub antler_width: Option<i32>,
+    pub snow_magic_power: Option<i32>,
+    pub favorite_food: Option<String>,
+    #[serde(rename = "cAnD13s_3ATeN-yesT3rdAy")]
+    pub candies_eaten_yesterday: Option<i32>,
+}
 
-    fn get_consumer(reindeers: &Vec<Reindeer>) -> String {
-        let mut consumer_reindeer = reindeers.iter().next().unwrap();
-        for reindeer in reindeers.iter().skip(1) {
-            if reindeer.candies_eaten_yesterday > consumer_reindeer.candies_eaten_yesterday {
-                consumer_reindeer = reindeer;
-            }
-        }
-        format!(
-            "{} ate lots of candies, but also some {}",
-            consumer_reindeer.name,
-            consumer_reindeer.favorite_food.clone().unwrap()
-        )
-    }
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct ContestResult {
+    pub fastest: String,
+    pub tallest: String,
+    pub magician: String,
+    pub consumer: String,
+    // Every reindeer sharing the winning score of a category, for the categories with a tie. The
+    // announcement is about the first one.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub ties: BTreeMap<String, Vec<String>>,
+    // Announcements of the configured categories, by category name.
+    #[serde(flatten)]
+    pub others: BTreeMap<String, String>,
 }
 
+#[utoipa::path(
+    post,
+    path = "/4/strength",
+    tag = "day4",
+    request_body = Vec<Reindeer>,
+    responses((status = 200, description = "Combined strength of the reindeer", body = String))
+)]
 async fn calculate_strength(Json(reindeers): Json<Vec<Reindeer>>) -> String {
     let mut total_strength = 0;
     for reindeer in reindeers {
@@ -95,12 +60,28 @@ async fn calculate_strength(Json(reindeers): Json<Vec<Reindeer>>) -> String {
     total_strength.to_string()
 }
 
-async fn get_contest_results(Json(reindeers): Json<Vec<Reindeer>>) -> Json<ContestResult> {
-    Json(ContestResult::get_contest_results(&reindeers))
+#[utoipa::path(
+    post,
+    path = "/4/contest",
+    tag = "day4",
+    request_body = Vec<Reindeer>,
+    params(ContestOptions),
+    responses(
+        (status = 200, description = "Winner of each category", body = ContestResult),
+        (status = 422, description = "No reindeer, or some lacking stats the contest needs", body = ProblemDetails)
+    )
+)]
+async fn get_contest_results(
+    State(rules): State<ContestRules>,
+    Query(options): Query<ContestOptions>,
+    Json(reindeers): Json<Vec<Reindeer>>,
+) -> Result<Json<ContestResult>, AppError> {
+    Ok(Json(rules.run(&reindeers, options.missing)?.0))
 }
 
-pub fn get_routes() -> Router {
+pub fn get_routes(config: &Config) -> Router {
     Router::new()
         .route("/4/strength", post(calculate_strength))
         .route("/4/contest", post(get_contest_results))
+        .with_state(ContestRules::new(&config.contest))
 }
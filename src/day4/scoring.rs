@@ -0,0 +1,453 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use anyhow::{anyhow, bail, ensure};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use super::{ContestResult, Reindeer};
+use crate::{
+    config::ContestConfig,
+    error::{AppError, InvalidItem, InvalidItems},
+};
+
+const NAME_MAX_CHARS: usize = 50;
+// Keys of the contest result that configured categories can't use.
+const RESERVED: [&str; 5] = ["fastest", "tallest", "magician", "consumer", "ties"];
+const PLACEHOLDERS: [&str; 9] = [
+    "name",
+    "strength",
+    "speed",
+    "height",
+    "antler_width",
+    "snow_magic_power",
+    "favorite_food",
+    "candies_eaten_yesterday",
+    "score",
+];
+
+// Stats a category can rank the reindeer by.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Stat {
+    Strength,
+    Speed,
+    Height,
+    AntlerWidth,
+    SnowMagicPower,
+    CandiesEatenYesterday,
+}
+
+impl Stat {
+    fn name(&self) -> &'static str {
+        match self {
+            Stat::Strength => "strength",
+            Stat::Speed => "speed",
+            Stat::Height => "height",
+            Stat::AntlerWidth => "antler_width",
+            Stat::SnowMagicPower => "snow_magic_power",
+            Stat::CandiesEatenYesterday => "candies_eaten_yesterday",
+        }
+    }
+
+    fn value(&self, reindeer: &Reindeer) -> Option<f64> {
+        match self {
+            Stat::Strength => Some(f64::from(reindeer.strength)),
+            Stat::Speed => reindeer.speed.map(f64::from),
+            Stat::Height => reindeer.height.map(f64::from),
+            Stat::AntlerWidth => reindeer.antler_width.map(f64::from),
+            Stat::SnowMagicPower => reindeer.snow_magic_power.map(f64::from),
+            Stat::CandiesEatenYesterday => reindeer.candies_eaten_yesterday.map(f64::from),
+        }
+    }
+}
+
+// A single stat, or the sum of several ones multiplied by their weight.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Score {
+    Stat(Stat),
+    Weighted(BTreeMap<Stat, f64>),
+}
+
+impl Score {
+    fn stats(&self) -> Vec<Stat> {
+        match self {
+            Score::Stat(stat) => vec![*stat],
+            Score::Weighted(weights) => weights.keys().copied().collect(),
+        }
+    }
+
+    // Missing when the reindeer lacks one of the stats, unless they count as zero.
+    fn of(&self, reindeer: &Reindeer, missing: MissingStats) -> Option<f64> {
+        let value = |stat: &Stat| match missing {
+            MissingStats::Zero => Some(stat.value(reindeer).unwrap_or_default()),
+            MissingStats::Skip | MissingStats::Reject => stat.value(reindeer),
+        };
+        match self {
+            Score::Stat(stat) => value(stat),
+            Score::Weighted(weights) => weights
+                .iter()
+                .map(|(stat, weight)| value(stat).map(|value| value * weight))
+                .sum(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    // The largest score wins.
+    #[default]
+    Max,
+    // The smallest score wins.
+    Min,
+}
+
+// Order of the reindeer sharing the best score, the first one being announced.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TieBreak {
+    // The first one in the line-up.
+    #[default]
+    First,
+    // The last one in the line-up.
+    Last,
+    // The first one by name.
+    Name,
+}
+
+// What a contest does with reindeer lacking a field a category needs, for its score or its message.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MissingStats {
+    // They don't compete in the category.
+    #[default]
+    Skip,
+    // Missing stats count as zero, and a missing favorite food as nothing.
+    Zero,
+    // The contest is rejected, listing every reindeer lacking a field.
+    Reject,
+}
+
+impl MissingStats {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MissingStats::Skip => "skip",
+            MissingStats::Zero => "zero",
+            MissingStats::Reject => "reject",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ContestOptions {
+    #[serde(default)]
+    #[param(inline)]
+    pub missing: MissingStats,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct CategoryDefinition {
+    // Key of the announcement in the result.
+    pub name: String,
+    pub score: Score,
+    #[serde(default)]
+    pub direction: Direction,
+    #[serde(default)]
+    pub ties: TieBreak,
+    // Announcement of the winner, where `{name}`, `{favorite_food}`, `{score}` and the names of
+    // the stats are replaced by the ones of the winner.
+    pub message: String,
+}
+
+// Copies the template, replacing each `{field}` with its value.
+fn fill<'t>(template: &'t str, mut value: impl FnMut(&'t str) -> String) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        filled.push_str(&rest[..start]);
+        filled.push_str(&value(&rest[start + 1..start + end]));
+        rest = &rest[start + end + 1..];
+    }
+    filled.push_str(rest);
+    filled
+}
+
+// Value of a message field for the reindeer, missing when it doesn't have it.
+fn field(reindeer: &Reindeer, field: &str) -> Option<String> {
+    match field {
+        "name" => Some(reindeer.name.clone()),
+        "strength" => Some(reindeer.strength.to_string()),
+        "speed" => reindeer.speed.map(|speed| speed.to_string()),
+        "height" => reindeer.height.map(|height| height.to_string()),
+        "antler_width" => reindeer.antler_width.map(|width| width.to_string()),
+        "snow_magic_power" => reindeer.snow_magic_power.map(|power| power.to_string()),
+        "favorite_food" => reindeer.favorite_food.clone(),
+        "candies_eaten_yesterday" => reindeer
+            .candies_eaten_yesterday
+            .map(|candies| candies.to_string()),
+        _ => None,
+    }
+}
+
+impl CategoryDefinition {
+    fn built_in(name: &str, stat: Stat, message: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            score: Score::Stat(stat),
+            direction: Direction::Max,
+            ties: TieBreak::First,
+            message: message.to_string(),
+        }
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        ensure!(
+            !self.name.is_empty() && self.name.chars().count() <= NAME_MAX_CHARS,
+            "Contest category names must have between 1 and {} characters",
+            NAME_MAX_CHARS
+        );
+        if let Score::Weighted(weights) = &self.score {
+            ensure!(
+                !weights.is_empty() && weights.values().all(|weight| weight.is_finite()),
+                "The weights of the {} category must be finite numbers, at least one",
+                self.name
+            );
+        }
+        let mut unknown = None;
+        fill(&self.message, |field| {
+            if !PLACEHOLDERS.contains(&field) {
+                unknown.get_or_insert(field);
+            }
+            String::new()
+        });
+        if let Some(field) = unknown {
+            bail!(
+                "The message of the {} category has an unknown field {{{}}}",
+                self.name,
+                field
+            );
+        }
+        Ok(())
+    }
+
+    // Fields of the reindeer the score and the message use.
+    fn needs(&self) -> Vec<&str> {
+        let mut needs = self
+            .score
+            .stats()
+            .iter()
+            .map(Stat::name)
+            .collect::<Vec<_>>();
+        fill(&self.message, |field| {
+            if !matches!(field, "name" | "score") && !needs.contains(&field) {
+                needs.push(field);
+            }
+            String::new()
+        });
+        needs
+    }
+
+    fn lacks(&self, reindeer: &Reindeer) -> Vec<&str> {
+        self.needs()
+            .into_iter()
+            .filter(|name| field(reindeer, name).is_none())
+            .collect()
+    }
+
+    // Every reindeer sharing the best score, in the order of the tie-break rule.
+    fn winners<'a>(
+        &self,
+        reindeers: &'a [Reindeer],
+        missing: MissingStats,
+    ) -> Result<Vec<&'a Reindeer>, AppError> {
+        let scored = reindeers
+            .iter()
+            .filter(|reindeer| missing != MissingStats::Skip || self.lacks(reindeer).is_empty())
+            .filter_map(|reindeer| Some((reindeer, self.score.of(reindeer, missing)?)))
+            .collect::<Vec<_>>();
+        let best = scored
+            .iter()
+            .map(|(_, score)| *score)
+            .reduce(|best, score| match self.direction {
+                Direction::Max => best.max(score),
+                Direction::Min => best.min(score),
+            })
+            .ok_or_else(|| {
+                AppError::unprocessable_entity(anyhow!(
+                    "No reindeer has the {} the {} category needs",
+                    self.needs().join(", "),
+                    self.name
+                ))
+            })?;
+        let mut winners = scored
+            .into_iter()
+            .filter(|(_, score)| *score == best)
+            .map(|(reindeer, _)| reindeer)
+            .collect::<Vec<_>>();
+        match self.ties {
+            TieBreak::First => {}
+            TieBreak::Last => winners.reverse(),
+            TieBreak::Name => winners.sort_by(|a, b| a.name.cmp(&b.name)),
+        }
+        Ok(winners)
+    }
+
+    // Winners only lack fields when missing stats count as zero.
+    fn announce(&self, winner: &Reindeer, missing: MissingStats) -> String {
+        fill(&self.message, |name| {
+            let value = match name {
+                "score" => self
+                    .score
+                    .of(winner, missing)
+                    .map(|score| score.to_string()),
+                _ => field(winner, name),
+            };
+            value.unwrap_or_else(|| match name {
+                "favorite_food" => String::new(),
+                _ => "0".to_string(),
+            })
+        })
+    }
+}
+
+impl ContestConfig {
+    pub fn check(&self) -> anyhow::Result<()> {
+        let mut names = RESERVED.to_vec();
+        for category in &self.categories {
+            ensure!(
+                !names.contains(&category.name.as_str()),
+                "There already is a {} contest category",
+                category.name
+            );
+            category.check()?;
+            names.push(&category.name);
+        }
+        Ok(())
+    }
+}
+
+// The categories contests are held in: the built-in ones, then the configured ones.
+#[derive(Debug, Clone)]
+pub struct ContestRules {
+    categories: Arc<Vec<CategoryDefinition>>,
+}
+
+impl ContestRules {
+    pub fn new(config: &ContestConfig) -> Self {
+        let mut categories = vec![
+            CategoryDefinition::built_in(
+                "fastest",
+                Stat::Speed,
+                "Speeding past the finish line with a strength of {strength} is {name}",
+            ),
+            CategoryDefinition::built_in(
+                "tallest",
+                Stat::Height,
+                "{name} is standing tall with his {antler_width} cm wide antlers",
+            ),
+            CategoryDefinition::built_in(
+                "magician",
+                Stat::SnowMagicPower,
+                "{name} could blast you away with a snow magic power of {snow_magic_power}",
+            ),
+            CategoryDefinition::built_in(
+                "consumer",
+                Stat::CandiesEatenYesterday,
+                "{name} ate lots of candies, but also some {favorite_food}",
+            ),
+        ];
+        categories.extend(config.categories.iter().cloned());
+        Self {
+            categories: Arc::new(categories),
+        }
+    }
+
+    // Lists every reindeer lacking a field one of the categories needs.
+    fn check_stats(&self, reindeers: &[Reindeer]) -> Result<(), AppError> {
+        let items = reindeers
+            .iter()
+            .enumerate()
+            .filter_map(|(index, reindeer)| {
+                let mut lacks = Vec::new();
+                for field in self.categories.iter().flat_map(|c| c.lacks(reindeer)) {
+                    if !lacks.contains(&field) {
+                        lacks.push(field);
+                    }
+                }
+                (!lacks.is_empty()).then(|| InvalidItem {
+                    index,
+                    name: reindeer.name.clone(),
+                    reason: format!("Missing {}", lacks.join(", ")),
+                })
+            })
+            .collect::<Vec<_>>();
+        if items.is_empty() {
+            return Ok(());
+        }
+        Err(AppError::unprocessable_entity(InvalidItems {
+            message: format!("{} reindeer lack stats the contest needs", items.len()),
+            items,
+        }))
+    }
+
+    // Announces the winner of every category, also returning the names of every reindeer sharing
+    // a win to keep track of them.
+    pub(crate) fn run(
+        &self,
+        reindeers: &[Reindeer],
+        missing: MissingStats,
+    ) -> Result<(ContestResult, Vec<(String, String)>), AppError> {
+        if reindeers.is_empty() {
+            return Err(AppError::unprocessable_entity(anyhow!(
+                "At least one reindeer is needed"
+            )));
+        }
+        if missing == MissingStats::Reject {
+            self.check_stats(reindeers)?;
+        }
+        let mut announcements = BTreeMap::new();
+        let mut ties = BTreeMap::new();
+        let mut winners = Vec::with_capacity(self.categories.len());
+        for category in self.categories.iter() {
+            let tied = category.winners(reindeers, missing)?;
+            announcements.insert(category.name.clone(), category.announce(tied[0], missing));
+            if tied.len() > 1 {
+                ties.insert(
+                    category.name.clone(),
+                    tied.iter().map(|reindeer| reindeer.name.clone()).collect(),
+                );
+            }
+            winners.extend(
+                tied.iter()
+                    .map(|reindeer| (category.name.clone(), reindeer.name.clone())),
+            );
+        }
+        let mut built_in = |name: &str| announcements.remove(name).unwrap_or_default();
+        let (fastest, tallest, magician, consumer) = (
+            built_in("fastest"),
+            built_in("tallest"),
+            built_in("magician"),
+            built_in("consumer"),
+        );
+        let result = ContestResult {
+            fastest,
+            tallest,
+            magician,
+            consumer,
+            ties,
+            others: announcements,
+        };
+        Ok((result, winners))
+    }
+}
+
+impl Default for ContestRules {
+    fn default() -> Self {
+        Self::new(&ContestConfig::default())
+    }
+}
@@ -1,18 +1,37 @@
+use anyhow::anyhow;
 use axum::{extract::Query, routing::post, Json, Router};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use utoipa::IntoParams;
 
-#[derive(Deserialize)]
-struct Pagination {
-    offset: Option<usize>,
-    limit: Option<usize>,
-    split: Option<usize>,
+use crate::error::AppError;
+
+#[derive(Deserialize, Serialize, Debug, Clone, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct Pagination {
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+    pub split: Option<usize>,
 }
 
-async fn paginate_list(pagination: Query<Pagination>, Json(names): Json<Vec<String>>) -> String {
-    let offset = match pagination.offset {
-        Some(o) => o,
-        None => 0,
-    };
+#[utoipa::path(
+    post,
+    path = "/5",
+    tag = "day5",
+    params(Pagination),
+    request_body = Vec<String>,
+    responses(
+        (status = 200, description = "Requested page, optionally split", body = String),
+        (status = 400, description = "Split is 0", body = ProblemDetails)
+    )
+)]
+async fn paginate_list(
+    pagination: Query<Pagination>,
+    Json(names): Json<Vec<String>>,
+) -> Result<String, AppError> {
+    if pagination.split == Some(0) {
+        return Err(AppError::bad_request(anyhow!("Split must be at least 1")));
+    }
+    let offset = pagination.offset.unwrap_or_default();
     let limit = match pagination.limit {
         Some(l) => l,
         None => names.len(),
@@ -40,7 +59,7 @@ async fn paginate_list(pagination: Query<Pagination>, Json(names): Json<Vec<Stri
             format!("{:?}", v)
         }
     };
-    result
+    Ok(result)
 }
 
 pub fn get_routes() -> Router {
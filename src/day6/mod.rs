@@ -1,25 +1,22 @@
 use axum::{routing::post, Json, Router};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Serialize)]
-struct CountElvesResponse {
-    elf: usize,
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct CountElvesResponse {
+    pub elf: usize,
     #[serde(rename = "elf on a shelf")]
-    elf_on_a_shelf: usize,
+    pub elf_on_a_shelf: usize,
     #[serde(rename = "shelf with no elf on it")]
-    shelf_with_no_elf_on_it: usize,
+    pub shelf_with_no_elf_on_it: usize,
 }
 
+// Overlapping occurrences count, so only char boundaries are tried as starting points.
 fn count_occurrences(needle: &str, haystack: &str) -> usize {
-    let mut count = 0;
-    if haystack.len() > needle.len() {
-        for i in 0..=(haystack.len() - needle.len()) {
-            if haystack[i..].starts_with(needle) {
-                count += 1;
-            }
-        }
-    }
-    count
+    haystack
+        .char_indices()
+        .filter(|(i, _)| haystack[*i..].starts_with(needle))
+        .count()
 }
 
 impl From<&str> for CountElvesResponse {
@@ -36,6 +33,13 @@ impl From<&str> for CountElvesResponse {
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/6",
+    tag = "day6",
+    request_body(content = String, content_type = "text/plain"),
+    responses((status = 200, description = "Elf and shelf counts", body = CountElvesResponse))
+)]
 async fn count_elves(text: String) -> Json<CountElvesResponse> {
     Json(CountElvesResponse::from(text.as_str()))
 }
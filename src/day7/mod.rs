@@ -4,36 +4,48 @@ use anyhow::anyhow;
 use axum::{debug_handler, http::HeaderMap, routing::get, Json, Router};
 use base64::{engine::general_purpose, Engine};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 use crate::error::AppError;
 
-fn decode_secret(input: &str) -> Result<String, anyhow::Error> {
-    let encoded = input.strip_prefix("recipe=").unwrap();
-    let decoded = general_purpose::STANDARD.decode(encoded).unwrap();
-    Ok(String::from_utf8(decoded)?)
+fn decode_secret(input: &str) -> Result<String, AppError> {
+    let encoded = input
+        .strip_prefix("recipe=")
+        .ok_or_else(|| AppError::bad_request(anyhow!("Cookie is missing the recipe")))?;
+    let decoded = general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(AppError::bad_request)?;
+    String::from_utf8(decoded).map_err(AppError::bad_request)
 }
 
+#[utoipa::path(
+    get,
+    path = "/7/decode",
+    tag = "day7",
+    params(("Cookie" = String, Header, description = "`recipe=` followed by a base64 encoded recipe")),
+    responses(
+        (status = 200, description = "Decoded recipe", body = String),
+        (status = 400, description = "Missing or malformed cookie", body = ProblemDetails)
+    )
+)]
 async fn decode_recipe(headers: HeaderMap) -> Result<String, AppError> {
-    let recipe = if let Some(header) = headers.get("Cookie") {
-        let encoded = header.to_str()?;
-        decode_secret(encoded)
-    } else {
-        return Err(AppError(anyhow!("Unable to decode recipe")));
+    let Some(header) = headers.get("Cookie") else {
+        return Err(AppError::bad_request(anyhow!("Unable to decode recipe")));
     };
-
-    Ok(recipe?)
+    let encoded = header.to_str().map_err(AppError::bad_request)?;
+    decode_secret(encoded)
 }
 
-#[derive(Deserialize)]
-struct Recipe {
-    recipe: HashMap<String, i64>,
-    pantry: HashMap<String, i64>,
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Recipe {
+    pub recipe: HashMap<String, i64>,
+    pub pantry: HashMap<String, i64>,
 }
 
-#[derive(Serialize)]
-struct BakeResponse {
-    cookies: i64,
-    pantry: HashMap<String, i64>,
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct BakeResponse {
+    pub cookies: i64,
+    pub pantry: HashMap<String, i64>,
 }
 
 impl BakeResponse {
@@ -51,10 +63,20 @@ impl BakeResponse {
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/7/bake",
+    tag = "day7",
+    params(("Cookie" = String, Header, description = "`recipe=` followed by a base64 encoded recipe and pantry")),
+    responses(
+        (status = 200, description = "Cookies baked and what is left in the pantry", body = BakeResponse),
+        (status = 400, description = "Missing or malformed cookie", body = ProblemDetails)
+    )
+)]
 #[debug_handler]
 async fn bake(headers: HeaderMap) -> Result<Json<BakeResponse>, AppError> {
     let recipe = decode_recipe(headers).await?;
-    let recipe: Recipe = serde_json::from_str(recipe.as_str())?;
+    let recipe: Recipe = serde_json::from_str(recipe.as_str()).map_err(AppError::bad_request)?;
     let mut max_cookies = Vec::<i64>::new();
     for (ingredient, amount) in recipe.recipe.iter() {
         if *amount > 0 {
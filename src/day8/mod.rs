@@ -1,4 +1,5 @@
-use axum::{extract::Path, routing::get, Router};
+use anyhow::anyhow;
+use axum::{extract::Path, http::StatusCode, routing::get, Router};
 use serde::Deserialize;
 
 use crate::error::AppError;
@@ -8,18 +9,54 @@ struct PokemonData {
     weight: i32,
 }
 
-async fn weight(Path(pokedex_humber): Path<usize>) -> Result<String, AppError> {
+async fn fetch_pokemon(pokedex_humber: usize) -> Result<PokemonData, AppError> {
     let url = format!("https://pokeapi.co/api/v2/pokemon/{}", pokedex_humber);
-    let body = reqwest::get(url).await?.text().await?;
-    let data: PokemonData = serde_json::from_str(&body)?;
+    let response = reqwest::get(url).await.map_err(AppError::upstream)?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Err(AppError::not_found(anyhow!(
+            "Pokemon {} does not exist",
+            pokedex_humber
+        )));
+    }
+    let body = response
+        .error_for_status()
+        .map_err(AppError::upstream)?
+        .text()
+        .await
+        .map_err(AppError::upstream)?;
+    serde_json::from_str(&body).map_err(AppError::upstream)
+}
+
+#[utoipa::path(
+    get,
+    path = "/8/weight/{pokedex_number}",
+    tag = "day8",
+    params(("pokedex_number" = usize, Path, description = "Pokedex number")),
+    responses(
+        (status = 200, description = "Weight in kilograms", body = String),
+        (status = 404, description = "Unknown pokemon", body = ProblemDetails),
+        (status = 502, description = "PokeAPI failure", body = ProblemDetails)
+    )
+)]
+async fn weight(Path(pokedex_humber): Path<usize>) -> Result<String, AppError> {
+    let data = fetch_pokemon(pokedex_humber).await?;
     let weight_in_kilos = data.weight as f64 / 10.0;
     Ok(weight_in_kilos.to_string())
 }
 
+#[utoipa::path(
+    get,
+    path = "/8/drop/{pokedex_number}",
+    tag = "day8",
+    params(("pokedex_number" = usize, Path, description = "Pokedex number")),
+    responses(
+        (status = 200, description = "Momentum after a 10 meters drop", body = String),
+        (status = 404, description = "Unknown pokemon", body = ProblemDetails),
+        (status = 502, description = "PokeAPI failure", body = ProblemDetails)
+    )
+)]
 async fn drop(Path(pokedex_humber): Path<usize>) -> Result<String, AppError> {
-    let url = format!("https://pokeapi.co/api/v2/pokemon/{}", pokedex_humber);
-    let body = reqwest::get(url).await?.text().await?;
-    let data: PokemonData = serde_json::from_str(&body)?;
+    let data = fetch_pokemon(pokedex_humber).await?;
     let weight_in_kilos = data.weight as f64 / 10.0;
     let v = f64::sqrt(2.0 * 9.825 * 10.0);
     let momentum = v * weight_in_kilos;
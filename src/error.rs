@@ -1,15 +1,259 @@
-use axum::{http::StatusCode, response::IntoResponse};
+use std::time::Duration;
 
-pub struct AppError(pub anyhow::Error);
+use axum::{
+    http::{
+        header::{CONTENT_TYPE, RETRY_AFTER, WWW_AUTHENTICATE},
+        HeaderValue, StatusCode,
+    },
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::error::ErrorKind;
+use tracing::{debug, error, warn};
+use utoipa::ToSchema;
+
+#[derive(Debug)]
+pub enum AppError {
+    BadRequest(anyhow::Error),
+    Unauthorized(anyhow::Error),
+    Forbidden(anyhow::Error),
+    NotFound(anyhow::Error),
+    NotAcceptable(anyhow::Error),
+    Conflict(anyhow::Error),
+    UnprocessableEntity(anyhow::Error),
+    PayloadTooLarge(anyhow::Error),
+    UnsupportedMediaType(anyhow::Error),
+    TooManyRequests(anyhow::Error, Duration),
+    Upstream(anyhow::Error),
+    Internal(anyhow::Error),
+}
+
+impl AppError {
+    pub fn bad_request(err: impl Into<anyhow::Error>) -> Self {
+        Self::BadRequest(err.into())
+    }
+
+    pub fn unauthorized(err: impl Into<anyhow::Error>) -> Self {
+        Self::Unauthorized(err.into())
+    }
+
+    pub fn forbidden(err: impl Into<anyhow::Error>) -> Self {
+        Self::Forbidden(err.into())
+    }
+
+    pub fn not_found(err: impl Into<anyhow::Error>) -> Self {
+        Self::NotFound(err.into())
+    }
+
+    pub fn not_acceptable(err: impl Into<anyhow::Error>) -> Self {
+        Self::NotAcceptable(err.into())
+    }
+
+    pub fn conflict(err: impl Into<anyhow::Error>) -> Self {
+        Self::Conflict(err.into())
+    }
+
+    pub fn unprocessable_entity(err: impl Into<anyhow::Error>) -> Self {
+        Self::UnprocessableEntity(err.into())
+    }
+
+    pub fn payload_too_large(err: impl Into<anyhow::Error>) -> Self {
+        Self::PayloadTooLarge(err.into())
+    }
+
+    pub fn unsupported_media_type(err: impl Into<anyhow::Error>) -> Self {
+        Self::UnsupportedMediaType(err.into())
+    }
+
+    pub fn too_many_requests(err: impl Into<anyhow::Error>, retry_after: Duration) -> Self {
+        Self::TooManyRequests(err.into(), retry_after)
+    }
+
+    pub fn upstream(err: impl Into<anyhow::Error>) -> Self {
+        Self::Upstream(err.into())
+    }
+
+    pub fn internal(err: impl Into<anyhow::Error>) -> Self {
+        Self::Internal(err.into())
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::NotAcceptable(_) => StatusCode::NOT_ACCEPTABLE,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::UnprocessableEntity(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            AppError::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            AppError::TooManyRequests(..) => StatusCode::TOO_MANY_REQUESTS,
+            AppError::Upstream(_) => StatusCode::BAD_GATEWAY,
+            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn inner(&self) -> &anyhow::Error {
+        match self {
+            AppError::BadRequest(err)
+            | AppError::Unauthorized(err)
+            | AppError::Forbidden(err)
+            | AppError::NotFound(err)
+            | AppError::NotAcceptable(err)
+            | AppError::Conflict(err)
+            | AppError::UnprocessableEntity(err)
+            | AppError::PayloadTooLarge(err)
+            | AppError::UnsupportedMediaType(err)
+            | AppError::TooManyRequests(err, _)
+            | AppError::Upstream(err)
+            | AppError::Internal(err) => err,
+        }
+    }
+
+    // Rejections raised by Postgres rather than by the handlers, whose wording isn't meant for clients.
+    fn raised_by_database(&self) -> bool {
+        matches!(
+            self,
+            AppError::Conflict(_) | AppError::UnprocessableEntity(_)
+        ) && self.inner().is::<sqlx::Error>()
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.inner())
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct InvalidItem {
+    // Position of the item in the request body.
+    pub index: usize,
+    pub name: String,
+    pub reason: String,
+}
+
+// Rejects a request for the listed items, reported in the `errors` of the problem details.
+#[derive(Debug)]
+pub struct InvalidItems {
+    pub message: String,
+    pub items: Vec<InvalidItem>,
+}
+
+impl std::fmt::Display for InvalidItems {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for InvalidItems {}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<InvalidItem>,
+}
+
+impl From<&AppError> for ProblemDetails {
+    fn from(err: &AppError) -> Self {
+        let status = err.status();
+        ProblemDetails {
+            problem_type: "about:blank".to_string(),
+            title: status
+                .canonical_reason()
+                .unwrap_or("Unknown Error")
+                .to_string(),
+            status: status.as_u16(),
+            // The cause of internal, upstream and database errors is logged, not shown to clients.
+            detail: match err {
+                AppError::Internal(_) => "The server failed to handle the request".to_string(),
+                AppError::Upstream(_) => "An upstream service failed to answer".to_string(),
+                AppError::Conflict(_) if err.raised_by_database() => {
+                    "The request conflicts with an existing record".to_string()
+                }
+                AppError::UnprocessableEntity(_) if err.raised_by_database() => {
+                    "The request refers to a missing record or holds an invalid value".to_string()
+                }
+                _ => err.to_string(),
+            },
+            errors: err
+                .inner()
+                .downcast_ref::<InvalidItems>()
+                .map(|invalid| invalid.items.clone())
+                .unwrap_or_default(),
+        }
+    }
+}
 
 impl IntoResponse for AppError {
     fn into_response(self) -> axum::response::Response {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Something went wrong: {}", self.0),
+        match &self {
+            AppError::Upstream(err) | AppError::Internal(err) => {
+                error!(error = ?err, "request failed")
+            }
+            _ if self.raised_by_database() => {
+                warn!(error = %self, "request rejected by the database")
+            }
+            _ => debug!(error = %self, "request rejected"),
+        }
+        let mut response = (
+            self.status(),
+            [(CONTENT_TYPE, "application/problem+json")],
+            Json(ProblemDetails::from(&self)),
         )
-            .into_response()
+            .into_response();
+        match &self {
+            AppError::TooManyRequests(_, retry_after) => {
+                // Retry-After only carries whole seconds, round up so clients don't come back too early.
+                let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+                response
+                    .headers_mut()
+                    .insert(RETRY_AFTER, HeaderValue::from(seconds.max(1)));
+            }
+            AppError::Unauthorized(_) => {
+                response
+                    .headers_mut()
+                    .insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+            }
+            _ => {}
+        }
+        response
+    }
+}
+
+fn classify(err: anyhow::Error) -> AppError {
+    if let Some(sqlx_err) = err.downcast_ref::<sqlx::Error>() {
+        return match sqlx_err {
+            sqlx::Error::RowNotFound => AppError::NotFound(err),
+            sqlx::Error::Database(db_err) => match db_err.kind() {
+                ErrorKind::UniqueViolation => AppError::Conflict(err),
+                ErrorKind::ForeignKeyViolation
+                | ErrorKind::NotNullViolation
+                | ErrorKind::CheckViolation => AppError::UnprocessableEntity(err),
+                _ => AppError::Internal(err),
+            },
+            _ => AppError::Internal(err),
+        };
+    }
+    if err.is::<reqwest::Error>() {
+        return AppError::Upstream(err);
+    }
+    if err.is::<serde_json::Error>()
+        || err.is::<std::num::ParseIntError>()
+        || err.is::<std::string::FromUtf8Error>()
+        || err.is::<base64::DecodeError>()
+        || err.is::<axum::http::header::ToStrError>()
+    {
+        return AppError::BadRequest(err);
     }
+    AppError::Internal(err)
 }
 
 impl<E> From<E> for AppError
@@ -17,6 +261,6 @@ where
     E: Into<anyhow::Error>,
 {
     fn from(err: E) -> Self {
-        Self(err.into())
+        classify(err.into())
     }
 }
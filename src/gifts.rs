@@ -0,0 +1,308 @@
+use std::collections::BTreeMap;
+
+use anyhow::anyhow;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    middleware,
+    routing::{get, post, put},
+    Extension, Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    audit::Actor,
+    auth::{authorize, Role, RoleGuard},
+    config::Config,
+    error::AppError,
+    tenant::Tenant,
+    CommonState,
+};
+
+const NAME_MAX_CHARS: usize = 50;
+const CATEGORY_MAX_CHARS: usize = 50;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GiftMetric {
+    // Units ordered.
+    #[default]
+    Quantity,
+    // Units ordered times the unit price of the gift in the catalog, in cents. Gifts missing from
+    // the catalog bring nothing.
+    Revenue,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MetricParams {
+    // What the gifts are summed and ranked by.
+    #[serde(default)]
+    #[param(inline)]
+    pub by: GiftMetric,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct Gift {
+    pub id: i32,
+    // Name orders are stored and reported with.
+    pub name: String,
+    // Other names resolving to this gift.
+    pub aliases: Vec<String>,
+    pub unit_price_cents: i64,
+    pub category: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct NewGift {
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub unit_price_cents: i64,
+    pub category: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct CategoryTotal {
+    // Absent for gifts without a category and the ones missing from the catalog.
+    pub category: Option<String>,
+    pub orders: i64,
+    pub quantity: i64,
+    pub revenue_cents: i64,
+}
+
+// Collapses whitespace runs into a single space and trims the name.
+pub(crate) fn tidy(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Names with the same key are the same gift, whatever their case and spacing.
+pub(crate) fn name_key(name: &str) -> String {
+    tidy(name).to_lowercase()
+}
+
+fn check_name(name: &str, what: &str) -> Result<(), AppError> {
+    if name.is_empty() || name.chars().count() > NAME_MAX_CHARS {
+        return Err(AppError::unprocessable_entity(anyhow!(
+            "The {} must have between 1 and {} characters",
+            what,
+            NAME_MAX_CHARS
+        )));
+    }
+    Ok(())
+}
+
+impl NewGift {
+    // Tidies the names, dropping aliases that resolve to the name or to an earlier alias, and
+    // sorts the aliases by key.
+    pub(crate) fn checked(&self) -> Result<NewGift, AppError> {
+        let name = tidy(&self.name);
+        check_name(&name, "name")?;
+        let mut aliases = BTreeMap::new();
+        for alias in &self.aliases {
+            let alias = tidy(alias);
+            check_name(&alias, "aliases")?;
+            let key = name_key(&alias);
+            if key != name_key(&name) {
+                aliases.entry(key).or_insert(alias);
+            }
+        }
+        if self.unit_price_cents < 0 {
+            return Err(AppError::unprocessable_entity(anyhow!(
+                "The unit price can't be negative"
+            )));
+        }
+        let category = self.category.as_deref().map(tidy);
+        if category.as_ref().is_some_and(|category| {
+            category.is_empty() || category.chars().count() > CATEGORY_MAX_CHARS
+        }) {
+            return Err(AppError::unprocessable_entity(anyhow!(
+                "The category must have between 1 and {} characters",
+                CATEGORY_MAX_CHARS
+            )));
+        }
+        Ok(NewGift {
+            name,
+            aliases: aliases.into_values().collect(),
+            unit_price_cents: self.unit_price_cents,
+            category,
+        })
+    }
+
+    // Every name resolving to the gift, its own first.
+    pub(crate) fn names(&self) -> Vec<String> {
+        let mut names = vec![self.name.clone()];
+        names.extend(self.aliases.iter().cloned());
+        names
+    }
+}
+
+pub(crate) fn name_taken(name: &str, gift: &str) -> AppError {
+    AppError::conflict(anyhow!("{} already names the gift {}", name, gift))
+}
+
+pub(crate) fn gift_not_found(id: i32) -> AppError {
+    AppError::not_found(anyhow!("Gift {} does not exist", id))
+}
+
+#[utoipa::path(
+    get,
+    path = "/gifts",
+    tag = "gifts",
+    security((), ("bearer" = [])),
+    responses(
+        (status = 200, description = "Every gift of the catalog, sorted by name", body = Vec<Gift>),
+        (status = 401, description = "Missing or unknown API key when reads are not public", body = ProblemDetails)
+    )
+)]
+async fn list_gifts(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+) -> Result<Json<Vec<Gift>>, AppError> {
+    Ok(Json(state.gifts.list(&tenant).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/gifts",
+    tag = "gifts",
+    request_body = NewGift,
+    security(("bearer" = [])),
+    responses(
+        (status = 201, description = "Gift added, with the matching orders now counted under its name", body = Gift),
+        (status = 401, description = "Missing or unknown API key", body = ProblemDetails),
+        (status = 403, description = "Writer role required", body = ProblemDetails),
+        (status = 409, description = "The name or an alias already names another gift", body = ProblemDetails),
+        (status = 422, description = "Invalid name, alias, price or category", body = ProblemDetails)
+    )
+)]
+async fn create_gift(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Extension(actor): Extension<Actor>,
+    Json(gift): Json<NewGift>,
+) -> Result<(StatusCode, Json<Gift>), AppError> {
+    let gift = state
+        .gifts
+        .create(&tenant, &actor, &gift.checked()?)
+        .await?;
+    Ok((StatusCode::CREATED, Json(gift)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/gifts/{id}",
+    tag = "gifts",
+    params(("id" = i32, Path, description = "Gift id")),
+    security((), ("bearer" = [])),
+    responses(
+        (status = 200, description = "The gift", body = Gift),
+        (status = 401, description = "Missing or unknown API key when reads are not public", body = ProblemDetails),
+        (status = 404, description = "No gift with this id", body = ProblemDetails)
+    )
+)]
+async fn get_gift(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Path(id): Path<i32>,
+) -> Result<Json<Gift>, AppError> {
+    Ok(Json(state.gifts.get(&tenant, id).await?))
+}
+
+#[utoipa::path(
+    put,
+    path = "/gifts/{id}",
+    tag = "gifts",
+    params(("id" = i32, Path, description = "Gift id")),
+    request_body = NewGift,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Gift replaced, its orders following a new name", body = Gift),
+        (status = 401, description = "Missing or unknown API key", body = ProblemDetails),
+        (status = 403, description = "Writer role required", body = ProblemDetails),
+        (status = 404, description = "No gift with this id", body = ProblemDetails),
+        (status = 409, description = "The name or an alias already names another gift", body = ProblemDetails),
+        (status = 422, description = "Invalid name, alias, price or category", body = ProblemDetails)
+    )
+)]
+async fn put_gift(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Extension(actor): Extension<Actor>,
+    Path(id): Path<i32>,
+    Json(gift): Json<NewGift>,
+) -> Result<Json<Gift>, AppError> {
+    Ok(Json(
+        state
+            .gifts
+            .update(&tenant, &actor, id, &gift.checked()?)
+            .await?,
+    ))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/gifts/{id}",
+    tag = "gifts",
+    params(("id" = i32, Path, description = "Gift id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Gift removed from the catalog, its orders keeping their name"),
+        (status = 401, description = "Missing or unknown API key", body = ProblemDetails),
+        (status = 403, description = "Writer role required", body = ProblemDetails),
+        (status = 404, description = "No gift with this id", body = ProblemDetails)
+    )
+)]
+async fn delete_gift(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Extension(actor): Extension<Actor>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    state.gifts.delete(&tenant, &actor, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/gifts/categories",
+    tag = "gifts",
+    security((), ("bearer" = [])),
+    responses(
+        (status = 200, description = "Orders, quantity and revenue of each category, sorted by category and ending with the gifts without one", body = Vec<CategoryTotal>),
+        (status = 401, description = "Missing or unknown API key when reads are not public", body = ProblemDetails)
+    )
+)]
+async fn gift_categories(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+) -> Result<Json<Vec<CategoryTotal>>, AppError> {
+    Ok(Json(state.gifts.categories(&tenant).await?))
+}
+
+pub fn get_routes(state: CommonState, config: &Config) -> Router {
+    let reader =
+        middleware::from_fn_with_state(RoleGuard::new(&state, config, Role::Reader), authorize);
+    let writer =
+        middleware::from_fn_with_state(RoleGuard::new(&state, config, Role::Writer), authorize);
+    Router::new()
+        .route(
+            "/gifts",
+            get(list_gifts)
+                .route_layer(reader.clone())
+                .merge(post(create_gift).route_layer(writer.clone())),
+        )
+        .route(
+            "/gifts/categories",
+            get(gift_categories).route_layer(reader.clone()),
+        )
+        .route(
+            "/gifts/:id",
+            get(get_gift)
+                .route_layer(reader)
+                .merge(put(put_gift).delete(delete_gift).route_layer(writer)),
+        )
+        .with_state(state)
+}
@@ -0,0 +1,164 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use axum::{extract::State, http::StatusCode, routing::get, Json, Router};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tokio::time::timeout;
+use utoipa::ToSchema;
+
+use crate::{config::Config, CommonState, MIGRATOR};
+
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Ok,
+    Unavailable,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct CheckResult {
+    pub status: HealthStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl CheckResult {
+    fn ok() -> Self {
+        Self {
+            status: HealthStatus::Ok,
+            detail: None,
+        }
+    }
+
+    fn unavailable(detail: impl Into<String>) -> Self {
+        Self {
+            status: HealthStatus::Unavailable,
+            detail: Some(detail.into()),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct LivenessReport {
+    pub status: HealthStatus,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct ReadinessReport {
+    pub status: HealthStatus,
+    pub database: CheckResult,
+    pub migrations: CheckResult,
+    pub assets: CheckResult,
+}
+
+#[derive(Clone)]
+struct HealthState {
+    pool: PgPool,
+    assets_dir: PathBuf,
+}
+
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "health",
+    responses((status = 200, description = "The process is alive", body = LivenessReport))
+)]
+async fn healthz() -> Json<LivenessReport> {
+    Json(LivenessReport {
+        status: HealthStatus::Ok,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "health",
+    responses(
+        (status = 200, description = "Every dependency is available", body = ReadinessReport),
+        (status = 503, description = "At least one dependency is unavailable", body = ReadinessReport)
+    )
+)]
+async fn readyz(State(state): State<HealthState>) -> (StatusCode, Json<ReadinessReport>) {
+    let database = check_database(&state.pool).await;
+    let migrations = if database.status == HealthStatus::Ok {
+        check_migrations(&state.pool).await
+    } else {
+        CheckResult::unavailable("Database is unavailable")
+    };
+    let assets = check_assets(&state.assets_dir).await;
+
+    let ready = [&database, &migrations, &assets]
+        .iter()
+        .all(|check| check.status == HealthStatus::Ok);
+    let (code, status) = if ready {
+        (StatusCode::OK, HealthStatus::Ok)
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, HealthStatus::Unavailable)
+    };
+    (
+        code,
+        Json(ReadinessReport {
+            status,
+            database,
+            migrations,
+            assets,
+        }),
+    )
+}
+
+async fn check_database(pool: &PgPool) -> CheckResult {
+    let query = sqlx::query_scalar::<_, i32>("SELECT 1").fetch_one(pool);
+    match timeout(CHECK_TIMEOUT, query).await {
+        Ok(Ok(_)) => CheckResult::ok(),
+        Ok(Err(err)) => CheckResult::unavailable(err.to_string()),
+        Err(_) => CheckResult::unavailable("Timed out"),
+    }
+}
+
+async fn check_migrations(pool: &PgPool) -> CheckResult {
+    let Some(expected) = MIGRATOR
+        .iter()
+        .filter(|migration| migration.migration_type.is_up_migration())
+        .map(|migration| migration.version)
+        .max()
+    else {
+        return CheckResult::ok();
+    };
+    let query = sqlx::query_scalar::<_, Option<i64>>(
+        "SELECT MAX(version) FROM _sqlx_migrations WHERE success",
+    )
+    .fetch_one(pool);
+    match timeout(CHECK_TIMEOUT, query).await {
+        Ok(Ok(Some(applied))) if applied >= expected => CheckResult::ok(),
+        Ok(Ok(Some(applied))) => CheckResult::unavailable(format!(
+            "Database is at version {}, expected {}",
+            applied, expected
+        )),
+        Ok(Ok(None)) => CheckResult::unavailable("No migration applied"),
+        Ok(Err(err)) => CheckResult::unavailable(err.to_string()),
+        Err(_) => CheckResult::unavailable("Timed out"),
+    }
+}
+
+async fn check_assets(assets_dir: &Path) -> CheckResult {
+    match tokio::fs::metadata(assets_dir).await {
+        Ok(metadata) if metadata.is_dir() => CheckResult::ok(),
+        Ok(_) => CheckResult::unavailable(format!("{} is not a directory", assets_dir.display())),
+        Err(err) => CheckResult::unavailable(format!("{}: {}", assets_dir.display(), err)),
+    }
+}
+
+pub fn get_routes(state: CommonState, config: &Config) -> Router {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .with_state(HealthState {
+            pool: state.pool,
+            assets_dir: config.server.assets_dir.clone(),
+        })
+}
@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use axum::{http::StatusCode, routing::get, Router};
+use sqlx::{migrate::Migrator, PgPool};
+
+use crate::{
+    config::{Config, StorageBackend},
+    repository::{GiftRepository, OrderRepository, RegionRepository, Repositories},
+    shutdown::Shutdown,
+};
+
+pub mod audit;
+pub mod auth;
+pub mod config;
+pub mod day1;
+pub mod day11;
+pub mod day12;
+pub mod day13;
+pub mod day14;
+pub mod day15;
+pub mod day18;
+pub mod day19;
+pub mod day20;
+pub mod day21;
+pub mod day22;
+pub mod day4;
+pub mod day5;
+pub mod day6;
+pub mod day7;
+pub mod day8;
+pub mod error;
+pub mod gifts;
+pub mod health;
+pub mod limits;
+pub mod openapi;
+pub mod orders;
+pub mod reindeer;
+pub mod repository;
+pub mod shutdown;
+pub mod snapshots;
+pub mod telemetry;
+pub mod tenant;
+pub mod transfer;
+
+async fn hello_world() -> &'static str {
+    "Hello, world!"
+}
+
+async fn fake_error() -> StatusCode {
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+#[derive(Clone)]
+pub struct CommonState {
+    pool: PgPool,
+    backend: StorageBackend,
+    orders: Arc<dyn OrderRepository>,
+    regions: Arc<dyn RegionRepository>,
+    gifts: Arc<dyn GiftRepository>,
+}
+
+impl CommonState {
+    pub fn new(pool: PgPool) -> Self {
+        Self::with_backend(pool, StorageBackend::Postgres)
+    }
+
+    // The pool still serves the routes that have no repository, whatever the backend.
+    pub fn with_backend(pool: PgPool, backend: StorageBackend) -> Self {
+        let Repositories {
+            orders,
+            regions,
+            gifts,
+        } = Repositories::new(backend, &pool);
+        Self {
+            pool,
+            backend,
+            orders,
+            regions,
+            gifts,
+        }
+    }
+}
+
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+pub async fn migrate(pool: &PgPool) -> Result<(), sqlx::migrate::MigrateError> {
+    MIGRATOR.run(pool).await
+}
+
+pub fn router(state: CommonState, config: &Config, shutdown: &Shutdown) -> Router {
+    // Rate limiting runs before the route guards, it only looks keys up to pick a bucket.
+    let keys = auth::RoleGuard::new(&state, config, auth::Role::Reader);
+    let router = Router::new()
+        .route("/", get(hello_world))
+        .route("/-1/error", get(fake_error))
+        .merge(day1::get_routes())
+        .merge(day4::get_routes(config))
+        .merge(day5::get_routes())
+        .merge(day6::get_routes())
+        .merge(day7::get_routes())
+        .merge(day8::get_routes())
+        .merge(day11::get_routes(config))
+        .merge(day12::get_routes())
+        .merge(day13::get_routes(state.clone(), config, shutdown))
+        .merge(day14::get_routes())
+        .merge(day15::get_routes())
+        .merge(day18::get_routes(state.clone(), config))
+        .merge(day19::get_routes(shutdown))
+        .merge(day20::get_routes(shutdown, config))
+        .merge(day21::get_routes())
+        .merge(day22::get_routes(config))
+        .merge(gifts::get_routes(state.clone(), config))
+        .merge(health::get_routes(state.clone(), config))
+        .merge(openapi::get_routes())
+        .merge(telemetry::get_routes());
+    // These routes query Postgres directly, next to the repositories. With the memory backend they
+    // would work on another dataset than the rest of the API, so they are left out.
+    let router = match state.backend {
+        StorageBackend::Postgres => router
+            .merge(orders::get_routes(state.clone(), config))
+            .merge(transfer::get_routes(state.clone(), config, shutdown))
+            .merge(snapshots::get_routes(state.clone(), config))
+            .merge(reindeer::get_routes(state.clone(), config))
+            .merge(auth::get_routes(state.clone(), config))
+            .merge(audit::get_routes(state, config)),
+        StorageBackend::Memory => router,
+    };
+    let router = limits::layer(router, &config.limits, keys);
+    telemetry::layer(router)
+}
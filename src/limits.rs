@@ -0,0 +1,248 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, DefaultBodyLimit, MatchedPath, Request, State},
+    http::{header::CONTENT_LENGTH, HeaderMap, HeaderName},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Router,
+};
+use http_body_util::Limited;
+
+use crate::{
+    auth::{bearer_token, hash_key, RoleGuard},
+    config::{LimitsConfig, RateLimitConfig},
+    error::AppError,
+};
+
+const FORWARDED_FOR_HEADER: HeaderName = HeaderName::from_static("x-forwarded-for");
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+// Share of the tracked clients kept when the least recently seen ones have to be evicted.
+const KEPT_ON_EVICTION: usize = MAX_TRACKED_CLIENTS * 9 / 10;
+// How long a resolved key keeps its own bucket before it is looked up again.
+const KNOWN_KEY_TTL: Duration = Duration::from_secs(60);
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<String, Bucket>>,
+    per_second: f64,
+    burst: f64,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            buckets: Mutex::new(HashMap::new()),
+            per_second: f64::from(config.requests_per_minute.max(1)) / 60.0,
+            burst: f64::from(config.burst.max(1)),
+        }
+    }
+
+    // Takes a token from the client's bucket, or tells how long until the next one is available.
+    pub fn check(&self, client: &str) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut buckets = self
+            .buckets
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if buckets.len() >= MAX_TRACKED_CLIENTS && !buckets.contains_key(client) {
+            buckets.retain(|_, bucket| self.refill(bucket, now) < self.burst);
+            if buckets.len() >= MAX_TRACKED_CLIENTS {
+                evict_least_recent(&mut buckets);
+            }
+        }
+
+        let bucket = buckets.entry(client.to_string()).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+        bucket.tokens = self.refill(bucket, now);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / self.per_second,
+            ))
+        }
+    }
+
+    fn refill(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        (bucket.tokens + elapsed * self.per_second).min(self.burst)
+    }
+}
+
+// Drops the buckets that were used the longest time ago, so the map stays bounded even when
+// every bucket is still draining.
+fn evict_least_recent(buckets: &mut HashMap<String, Bucket>) {
+    let mut by_age = buckets
+        .iter()
+        .map(|(client, bucket)| (bucket.updated, client.clone()))
+        .collect::<Vec<_>>();
+    by_age.sort_unstable();
+    let evicted = by_age.len().saturating_sub(KEPT_ON_EVICTION);
+    for (_, client) in by_age.into_iter().take(evicted) {
+        buckets.remove(&client);
+    }
+}
+
+// Keys recently resolved by the limiter, by hash, so their requests skip the database.
+#[derive(Default)]
+struct KnownKeys {
+    keys: Mutex<HashMap<String, (i32, Instant)>>,
+}
+
+impl KnownKeys {
+    fn get(&self, key_hash: &str) -> Option<i32> {
+        let keys = self
+            .keys
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        keys.get(key_hash)
+            .filter(|(_, resolved)| resolved.elapsed() < KNOWN_KEY_TTL)
+            .map(|(id, _)| *id)
+    }
+
+    fn insert(&self, key_hash: String, id: i32) {
+        let mut keys = self
+            .keys
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if keys.len() >= MAX_TRACKED_CLIENTS {
+            keys.retain(|_, (_, resolved)| resolved.elapsed() < KNOWN_KEY_TTL);
+            if keys.len() >= MAX_TRACKED_CLIENTS {
+                keys.clear();
+            }
+        }
+        keys.insert(key_hash, (id, Instant::now()));
+    }
+}
+
+#[derive(Clone)]
+struct LimitsState {
+    config: Arc<LimitsConfig>,
+    limiter: Arc<RateLimiter>,
+    keys: RoleGuard,
+    known_keys: Arc<KnownKeys>,
+}
+
+fn too_many_requests(retry_after: Duration) -> AppError {
+    AppError::too_many_requests(anyhow!("Rate limit exceeded"), retry_after)
+}
+
+// A key only gets its own bucket once it resolves to a live API key, any other token would let a
+// client dodge the limit by making one up per request. Until then the request is charged to the
+// client address, so the key lookup only runs for requests the limiter already admitted.
+async fn charge_client(
+    state: &LimitsState,
+    token: Option<String>,
+    address: String,
+) -> Result<(), AppError> {
+    let key_hash = token.as_deref().map(hash_key);
+    if let Some(id) = key_hash
+        .as_deref()
+        .and_then(|hash| state.known_keys.get(hash))
+    {
+        return state
+            .limiter
+            .check(&format!("key:{}", id))
+            .map_err(too_many_requests);
+    }
+    state.limiter.check(&address).map_err(too_many_requests)?;
+    if let (Some(token), Some(key_hash)) = (token, key_hash) {
+        if let Ok(Some(key)) = state.keys.find_key(&token).await {
+            state.known_keys.insert(key_hash, key.id);
+        }
+    }
+    Ok(())
+}
+
+fn client_address(request: &Request, trust_forwarded_for: bool) -> String {
+    let forwarded = trust_forwarded_for
+        .then(|| forwarded_for(request.headers()))
+        .flatten();
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    match forwarded.or(peer) {
+        Some(ip) => format!("ip:{}", ip),
+        None => "unknown".to_string(),
+    }
+}
+
+fn forwarded_for(headers: &HeaderMap) -> Option<IpAddr> {
+    headers
+        .get(&FORWARDED_FOR_HEADER)?
+        .to_str()
+        .ok()?
+        .split(',')
+        .next()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+async fn enforce_limits(
+    State(state): State<LimitsState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_default();
+
+    let rate_limit = &state.config.rate_limit;
+    if rate_limit.enabled && !rate_limit.exempt_routes.contains(&route) {
+        let token = bearer_token(request.headers()).map(str::to_string);
+        let address = client_address(&request, rate_limit.trust_forwarded_for);
+        charge_client(&state, token, address).await?;
+    }
+
+    if state.config.streaming_routes.contains(&route) {
+        return Ok(next.run(request).await.into_response());
+    }
+    let limit = state.config.body_bytes_for(&route);
+    let declared = request
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    if declared.is_some_and(|length| length > limit) {
+        return Err(AppError::payload_too_large(anyhow!(
+            "Request body is larger than {} bytes",
+            limit
+        )));
+    }
+    let request = request.map(|body| Body::new(Limited::new(body, limit)));
+    Ok(next.run(request).await.into_response())
+}
+
+pub fn layer(router: Router, config: &LimitsConfig, keys: RoleGuard) -> Router {
+    let state = LimitsState {
+        config: Arc::new(config.clone()),
+        limiter: Arc::new(RateLimiter::new(&config.rate_limit)),
+        keys,
+        known_keys: Arc::new(KnownKeys::default()),
+    };
+    // The per-route limit replaces axum's default one, which would otherwise cap every body at 2MB.
+    router
+        .layer(middleware::from_fn_with_state(state, enforce_limits))
+        .layer(DefaultBodyLimit::disable())
+}
@@ -0,0 +1,267 @@
+use askama::Template;
+use askama_axum::IntoResponse;
+use axum::{routing::get, Json, Router};
+use utoipa::{
+    openapi::{
+        path::{ParameterBuilder, ParameterIn},
+        security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+        Object, Required, SchemaType,
+    },
+    Modify, OpenApi,
+};
+
+use crate::{
+    audit, auth, day1, day11, day12, day13, day14, day15, day18, day19, day20, day21, day22, day4,
+    day5, day6, day7, day8,
+    error::{self, ProblemDetails},
+    gifts, health, orders, reindeer, snapshots, telemetry,
+    tenant::TENANT_HEADER,
+    transfer,
+};
+
+pub const SPEC_PATH: &str = "/api-docs/openapi.json";
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "CCH23", description = "Shuttle Christmas Code Hunt 2023 solutions"),
+    paths(
+        day1::recalibrate_packet_ids,
+        day4::calculate_strength,
+        day4::get_contest_results,
+        day5::paginate_list,
+        day6::count_elves,
+        day7::decode_recipe,
+        day7::bake,
+        day8::weight,
+        day8::drop,
+        day11::red_pixels,
+        day12::save_packet,
+        day12::load_packet,
+        day12::ulids,
+        day12::ulids_weekday,
+        day13::sql,
+        day13::reset,
+        day13::orders,
+        day13::orders_total,
+        day13::orders_popular,
+        day13::orders_feed,
+        day13::orders_feed_ws,
+        day14::unsafe_route,
+        day14::safe_route,
+        day15::nice,
+        day15::game,
+        day18::regions,
+        day18::regions_total,
+        day18::top_list,
+        day18::analytics,
+        day18::region_parent,
+        day18::tree,
+        day19::ping,
+        day19::reset,
+        day19::views,
+        day19::room,
+        day20::archive_files,
+        day20::archive_files_size,
+        day20::cookie,
+        day21::coords,
+        day21::country,
+        day22::integers,
+        day22::rocket,
+        orders::list_orders,
+        orders::get_order,
+        orders::put_order,
+        orders::patch_order,
+        orders::delete_order,
+        transfer::import_orders,
+        transfer::import_regions,
+        transfer::export_orders,
+        transfer::export_regions,
+        snapshots::create_snapshot,
+        snapshots::list_snapshots,
+        snapshots::get_snapshot,
+        snapshots::delete_snapshot,
+        snapshots::restore_snapshot,
+        snapshots::diff_snapshot,
+        gifts::list_gifts,
+        gifts::create_gift,
+        gifts::get_gift,
+        gifts::put_gift,
+        gifts::delete_gift,
+        gifts::gift_categories,
+        reindeer::list_reindeer,
+        reindeer::register_reindeer,
+        reindeer::get_reindeer,
+        reindeer::delete_reindeer,
+        reindeer::hold_contest,
+        reindeer::list_contests,
+        reindeer::get_contest,
+        reindeer::leaderboard,
+        auth::create_key,
+        auth::list_keys,
+        auth::revoke_key,
+        audit::list_entries,
+        health::healthz,
+        health::readyz,
+        telemetry::render_metrics,
+    ),
+    components(schemas(
+        ProblemDetails,
+        error::InvalidItem,
+        day4::Reindeer,
+        day4::ContestResult,
+        day4::MissingStats,
+        day6::CountElvesResponse,
+        day7::BakeResponse,
+        day12::UlidsWeekdayResult,
+        day13::Order,
+        day13::IngestMode,
+        day13::IngestReport,
+        day13::RejectedOrder,
+        day13::RejectReason,
+        day13::OrderTotalResponse,
+        day13::OrderPopularResponse,
+        day13::OrderFeedEvent,
+        day14::UnsafeRequest,
+        day15::Password,
+        day15::CheckNiceResult,
+        day15::CheckGameResult,
+        day18::Region,
+        day18::RegionsTotalResponse,
+        day18::RegionTopList,
+        day18::AnalyticsBucket,
+        day18::RankedGift,
+        day18::RegionAnalytics,
+        day18::RegionParent,
+        day18::RegionNode,
+        orders::OrderPage,
+        orders::OrderFields,
+        orders::OrderPatch,
+        orders::OrderSort,
+        orders::SortDirection,
+        transfer::RegionImportReport,
+        snapshots::Snapshot,
+        snapshots::NewSnapshot,
+        snapshots::OrderChange,
+        snapshots::SnapshotDiff,
+        gifts::GiftMetric,
+        gifts::Gift,
+        gifts::NewGift,
+        gifts::CategoryTotal,
+        reindeer::NewContest,
+        reindeer::Contest,
+        reindeer::LeaderboardEntry,
+        auth::Role,
+        auth::NewApiKey,
+        auth::ApiKeyInfo,
+        auth::CreatedApiKey,
+        audit::AuditOperation,
+        audit::AuditEntity,
+        audit::AuditEntry,
+        audit::AuditPage,
+        health::HealthStatus,
+        health::CheckResult,
+        health::LivenessReport,
+        health::ReadinessReport,
+    )),
+    modifiers(&SharedRoutes, &BearerAuth, &TenantHeader)
+)]
+pub struct ApiDoc;
+
+// `/18/reset` and `/18/orders` are served by the day 13 handlers.
+struct SharedRoutes;
+
+impl Modify for SharedRoutes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        for (source, target, tag) in [
+            ("/13/reset", "/18/reset", "day18"),
+            ("/13/orders", "/18/orders", "day18"),
+        ] {
+            let Some(mut item) = openapi.paths.get_path_item(source).cloned() else {
+                continue;
+            };
+            for operation in item.operations.values_mut() {
+                operation.tags = Some(vec![tag.to_string()]);
+                operation.operation_id = operation
+                    .operation_id
+                    .as_ref()
+                    .map(|id| format!("{}_{}", tag, id));
+            }
+            openapi.paths.paths.insert(target.to_string(), item);
+        }
+    }
+}
+
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+// Every route reading or changing orders, regions, gifts or snapshots is scoped to a tenant.
+struct TenantHeader;
+
+const TENANT_SCOPED: [&str; 10] = [
+    "/13/reset",
+    "/13/orders",
+    "/18/",
+    "/orders",
+    "/regions",
+    "/snapshots",
+    "/gifts",
+    "/audit",
+    "/reindeer",
+    "/contests",
+];
+
+impl Modify for TenantHeader {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let header = ParameterBuilder::new()
+            .name(TENANT_HEADER)
+            .parameter_in(ParameterIn::Header)
+            .required(Required::False)
+            .description(Some(
+                "Tenant of the request, `default` when missing. Keys bound to a tenant can omit it",
+            ))
+            .schema(Some(Object::with_type(SchemaType::String)))
+            .build();
+        for (path, item) in openapi.paths.paths.iter_mut() {
+            if !TENANT_SCOPED.iter().any(|prefix| path.starts_with(prefix)) {
+                continue;
+            }
+            for operation in item.operations.values_mut() {
+                operation
+                    .parameters
+                    .get_or_insert_with(Vec::new)
+                    .push(header.clone());
+            }
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "api_docs.html")]
+struct ApiDocsTemplate {
+    spec_url: &'static str,
+}
+
+async fn spec() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+async fn viewer() -> impl IntoResponse {
+    ApiDocsTemplate {
+        spec_url: SPEC_PATH,
+    }
+}
+
+pub fn get_routes() -> Router {
+    Router::new()
+        .route(SPEC_PATH, get(spec))
+        .route("/api-docs", get(viewer))
+}
@@ -0,0 +1,471 @@
+use anyhow::anyhow;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    middleware,
+    routing::{get, put},
+    Extension, Json, Router,
+};
+use base64::{engine::general_purpose, Engine};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, Postgres, QueryBuilder};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    audit::{self, Actor},
+    auth::{authorize, Role, RoleGuard},
+    config::Config,
+    day13::{check_fields, Order},
+    error::AppError,
+    tenant::{self, Tenant},
+    CommonState,
+};
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 500;
+
+const ORDER_COLUMNS: &str = "id, region_id, gift_name, quantity";
+
+pub(crate) type OrderRow = (i32, i32, String, i32);
+type UpsertedRow = (i32, i32, String, i32, bool);
+
+pub(crate) fn order((id, region_id, gift_name, quantity): OrderRow) -> Order {
+    Order {
+        id,
+        region_id,
+        gift_name,
+        quantity,
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderSort {
+    #[default]
+    Id,
+    RegionId,
+    GiftName,
+    Quantity,
+}
+
+impl OrderSort {
+    fn column(&self) -> &'static str {
+        match self {
+            OrderSort::Id => "id",
+            OrderSort::RegionId => "region_id",
+            OrderSort::GiftName => "gift_name",
+            OrderSort::Quantity => "quantity",
+        }
+    }
+
+    fn key(&self, order: &Order) -> serde_json::Value {
+        match self {
+            OrderSort::Id => order.id.into(),
+            OrderSort::RegionId => order.region_id.into(),
+            OrderSort::GiftName => order.gift_name.clone().into(),
+            OrderSort::Quantity => order.quantity.into(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OrderListParams {
+    pub region_id: Option<i32>,
+    pub gift_name: Option<String>,
+    pub min_quantity: Option<i32>,
+    pub max_quantity: Option<i32>,
+    #[serde(default)]
+    #[param(inline)]
+    pub sort: OrderSort,
+    #[serde(default)]
+    #[param(inline)]
+    pub direction: SortDirection,
+    // Number of orders per page, 50 by default and at most 500.
+    pub limit: Option<i64>,
+    // `next_cursor` of the previous page.
+    pub cursor: Option<String>,
+}
+
+// Position of the last order of a page, only valid for the same sort.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct Cursor {
+    sort: OrderSort,
+    direction: SortDirection,
+    key: serde_json::Value,
+    id: i32,
+}
+
+impl Cursor {
+    fn encode(&self) -> Result<String, AppError> {
+        let json = serde_json::to_vec(self).map_err(AppError::internal)?;
+        Ok(general_purpose::URL_SAFE_NO_PAD.encode(json))
+    }
+
+    fn decode(cursor: &str) -> Result<Self, AppError> {
+        let json = general_purpose::URL_SAFE_NO_PAD
+            .decode(cursor)
+            .map_err(|_| AppError::bad_request(anyhow!("Invalid cursor")))?;
+        serde_json::from_slice(&json).map_err(|_| AppError::bad_request(anyhow!("Invalid cursor")))
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct OrderPage {
+    pub orders: Vec<Order>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct OrderFields {
+    pub region_id: i32,
+    pub gift_name: String,
+    pub quantity: i32,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, ToSchema)]
+pub struct OrderPatch {
+    pub region_id: Option<i32>,
+    pub gift_name: Option<String>,
+    pub quantity: Option<i32>,
+}
+
+fn push_key(
+    query: &mut QueryBuilder<'_, Postgres>,
+    key: &serde_json::Value,
+) -> Result<(), AppError> {
+    match key {
+        serde_json::Value::String(key) => {
+            query.push_bind(key.clone());
+        }
+        key => {
+            let key = key
+                .as_i64()
+                .and_then(|key| i32::try_from(key).ok())
+                .ok_or_else(|| AppError::bad_request(anyhow!("Invalid cursor")))?;
+            query.push_bind(key);
+        }
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/orders",
+    tag = "orders",
+    params(OrderListParams),
+    security((), ("bearer" = [])),
+    responses(
+        (status = 200, description = "A page of orders, with the cursor of the next one", body = OrderPage),
+        (status = 400, description = "Invalid limit or cursor", body = ProblemDetails),
+        (status = 401, description = "Missing or unknown API key when reads are not public", body = ProblemDetails)
+    )
+)]
+async fn list_orders(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Query(params): Query<OrderListParams>,
+) -> Result<Json<OrderPage>, AppError> {
+    let limit = params.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return Err(AppError::bad_request(anyhow!(
+            "Limit must be between 1 and {}",
+            MAX_PAGE_SIZE
+        )));
+    }
+    let cursor = params.cursor.as_deref().map(Cursor::decode).transpose()?;
+    if let Some(cursor) = &cursor {
+        if cursor.sort != params.sort || cursor.direction != params.direction {
+            return Err(AppError::bad_request(anyhow!(
+                "Cursor was issued for another sort"
+            )));
+        }
+    }
+
+    let mut query =
+        QueryBuilder::<Postgres>::new(format!("SELECT {} FROM orders WHERE TRUE", ORDER_COLUMNS));
+    if let Some(region_id) = params.region_id {
+        query.push(" AND region_id = ").push_bind(region_id);
+    }
+    if let Some(gift_name) = &params.gift_name {
+        // Any spelling or alias of the gift finds its orders.
+        query
+            .push(" AND (name_key = gift_name_key(")
+            .push_bind(gift_name.clone())
+            .push(") OR gift_id = (SELECT gift_id FROM gift_names WHERE name_key = gift_name_key(")
+            .push_bind(gift_name.clone())
+            .push(")))");
+    }
+    if let Some(min_quantity) = params.min_quantity {
+        query.push(" AND quantity >= ").push_bind(min_quantity);
+    }
+    if let Some(max_quantity) = params.max_quantity {
+        query.push(" AND quantity <= ").push_bind(max_quantity);
+    }
+
+    // The id breaks ties, so that the (key, id) pair gives every order a unique position.
+    let column = params.sort.column();
+    let (comparison, direction) = match params.direction {
+        SortDirection::Asc => (">", "ASC"),
+        SortDirection::Desc => ("<", "DESC"),
+    };
+    if let Some(cursor) = &cursor {
+        if params.sort == OrderSort::Id {
+            query
+                .push(format!(" AND id {} ", comparison))
+                .push_bind(cursor.id);
+        } else {
+            query.push(format!(" AND ({}, id) {} (", column, comparison));
+            push_key(&mut query, &cursor.key)?;
+            query.push(", ").push_bind(cursor.id).push(")");
+        }
+    }
+    if params.sort == OrderSort::Id {
+        query.push(format!(" ORDER BY id {}", direction));
+    } else {
+        query.push(format!(
+            " ORDER BY {} {}, id {}",
+            column, direction, direction
+        ));
+    }
+    // One more row than requested tells whether there is a next page.
+    query.push(" LIMIT ").push_bind(limit + 1);
+
+    let mut tx = tenant::begin(&state.pool, &tenant).await?;
+    let mut orders = query
+        .build_query_as::<OrderRow>()
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(order)
+        .collect::<Vec<_>>();
+    let next_cursor = if orders.len() as i64 > limit {
+        orders.truncate(limit as usize);
+        orders
+            .last()
+            .map(|last| {
+                Cursor {
+                    sort: params.sort,
+                    direction: params.direction,
+                    key: params.sort.key(last),
+                    id: last.id,
+                }
+                .encode()
+            })
+            .transpose()?
+    } else {
+        None
+    };
+    Ok(Json(OrderPage {
+        orders,
+        next_cursor,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/orders/{id}",
+    tag = "orders",
+    params(("id" = i32, Path, description = "Order id")),
+    security((), ("bearer" = [])),
+    responses(
+        (status = 200, description = "The order", body = Order),
+        (status = 401, description = "Missing or unknown API key when reads are not public", body = ProblemDetails),
+        (status = 404, description = "No order with this id", body = ProblemDetails)
+    )
+)]
+async fn get_order(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Path(id): Path<i32>,
+) -> Result<Json<Order>, AppError> {
+    let mut tx = tenant::begin(&state.pool, &tenant).await?;
+    let row = sqlx::query_as::<_, OrderRow>(&format!(
+        "SELECT {} FROM orders WHERE id = $1",
+        ORDER_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::not_found(anyhow!("No order {}", id)))?;
+    Ok(Json(order(row)))
+}
+
+async fn check_order(executor: impl PgExecutor<'_>, fields: &OrderFields) -> Result<(), AppError> {
+    if let Some(reason) = check_fields(&fields.gift_name, fields.quantity) {
+        return Err(AppError::unprocessable_entity(anyhow!("{}", reason)));
+    }
+    let region_exists =
+        sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM regions WHERE id = $1)")
+            .bind(fields.region_id)
+            .fetch_one(executor)
+            .await?;
+    if !region_exists {
+        return Err(AppError::unprocessable_entity(anyhow!(
+            "Region {} does not exist",
+            fields.region_id
+        )));
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    put,
+    path = "/orders/{id}",
+    tag = "orders",
+    params(("id" = i32, Path, description = "Order id")),
+    request_body = OrderFields,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Order replaced", body = Order),
+        (status = 201, description = "Order created", body = Order),
+        (status = 401, description = "Missing or unknown API key", body = ProblemDetails),
+        (status = 403, description = "Writer role required", body = ProblemDetails),
+        (status = 422, description = "Invalid quantity, gift name or region", body = ProblemDetails)
+    )
+)]
+async fn put_order(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Extension(actor): Extension<Actor>,
+    Path(id): Path<i32>,
+    Json(fields): Json<OrderFields>,
+) -> Result<(StatusCode, Json<Order>), AppError> {
+    let mut tx = audit::begin(&state.pool, &tenant, &actor).await?;
+    check_order(&mut *tx, &fields).await?;
+    let (id, region_id, gift_name, quantity, created) =
+        sqlx::query_as::<_, UpsertedRow>(&format!(
+            r#"
+            INSERT INTO orders (id, region_id, gift_name, quantity) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (tenant, id) DO UPDATE
+            SET region_id = EXCLUDED.region_id, gift_name = EXCLUDED.gift_name, quantity = EXCLUDED.quantity
+            RETURNING {}, xmax = 0
+            "#,
+            ORDER_COLUMNS
+        ))
+        .bind(id)
+        .bind(fields.region_id)
+        .bind(&fields.gift_name)
+        .bind(fields.quantity)
+        .fetch_one(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    let status = if created {
+        StatusCode::CREATED
+    } else {
+        StatusCode::OK
+    };
+    Ok((status, Json(order((id, region_id, gift_name, quantity)))))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/orders/{id}",
+    tag = "orders",
+    params(("id" = i32, Path, description = "Order id")),
+    request_body = OrderPatch,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Order updated", body = Order),
+        (status = 401, description = "Missing or unknown API key", body = ProblemDetails),
+        (status = 403, description = "Writer role required", body = ProblemDetails),
+        (status = 404, description = "No order with this id", body = ProblemDetails),
+        (status = 422, description = "Invalid quantity, gift name or region", body = ProblemDetails)
+    )
+)]
+async fn patch_order(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Extension(actor): Extension<Actor>,
+    Path(id): Path<i32>,
+    Json(patch): Json<OrderPatch>,
+) -> Result<Json<Order>, AppError> {
+    let mut tx = audit::begin(&state.pool, &tenant, &actor).await?;
+    let current = sqlx::query_as::<_, OrderRow>(&format!(
+        "SELECT {} FROM orders WHERE id = $1 FOR UPDATE",
+        ORDER_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .map(order)
+    .ok_or_else(|| AppError::not_found(anyhow!("No order {}", id)))?;
+
+    let fields = OrderFields {
+        region_id: patch.region_id.unwrap_or(current.region_id),
+        gift_name: patch.gift_name.unwrap_or(current.gift_name),
+        quantity: patch.quantity.unwrap_or(current.quantity),
+    };
+    check_order(&mut *tx, &fields).await?;
+    let row = sqlx::query_as::<_, OrderRow>(&format!(
+        "UPDATE orders SET region_id = $2, gift_name = $3, quantity = $4 WHERE id = $1 RETURNING {}",
+        ORDER_COLUMNS
+    ))
+    .bind(id)
+    .bind(fields.region_id)
+    .bind(&fields.gift_name)
+    .bind(fields.quantity)
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(Json(order(row)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/orders/{id}",
+    tag = "orders",
+    params(("id" = i32, Path, description = "Order id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Order deleted"),
+        (status = 401, description = "Missing or unknown API key", body = ProblemDetails),
+        (status = 403, description = "Writer role required", body = ProblemDetails),
+        (status = 404, description = "No order with this id", body = ProblemDetails)
+    )
+)]
+async fn delete_order(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Extension(actor): Extension<Actor>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    let mut tx = audit::begin(&state.pool, &tenant, &actor).await?;
+    let deleted = sqlx::query("DELETE FROM orders WHERE id = $1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    if deleted.rows_affected() == 0 {
+        return Err(AppError::not_found(anyhow!("No order {}", id)));
+    }
+    tx.commit().await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub fn get_routes(state: CommonState, config: &Config) -> Router {
+    let reader =
+        middleware::from_fn_with_state(RoleGuard::new(&state, config, Role::Reader), authorize);
+    let writer =
+        middleware::from_fn_with_state(RoleGuard::new(&state, config, Role::Writer), authorize);
+    Router::new()
+        .route("/orders", get(list_orders).route_layer(reader.clone()))
+        .route(
+            "/orders/:id",
+            get(get_order).route_layer(reader).merge(
+                put(put_order)
+                    .patch(patch_order)
+                    .delete(delete_order)
+                    .route_layer(writer),
+            ),
+        )
+        .with_state(state)
+}
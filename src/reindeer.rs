@@ -0,0 +1,530 @@
+use std::collections::HashSet;
+
+use anyhow::anyhow;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    middleware,
+    routing::{delete, get, post},
+    Extension, Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json as Stored, PgConnection};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    auth::{authorize, Role, RoleGuard},
+    config::Config,
+    day4::{ContestResult, ContestRules, MissingStats, Reindeer},
+    error::AppError,
+    tenant::{self, Tenant},
+    CommonState,
+};
+
+const NAME_MAX_CHARS: usize = 50;
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 500;
+
+type ReindeerRow = (
+    String,
+    i32,
+    Option<f32>,
+    Option<i32>,
+    Option<i32>,
+    Option<i32>,
+    Option<String>,
+    Option<i32>,
+);
+type ContestRow = (
+    i32,
+    DateTime<Utc>,
+    Stored<Vec<Reindeer>>,
+    Stored<ContestResult>,
+    String,
+);
+type LeaderboardRow = (String, String, i64, DateTime<Utc>);
+
+const SELECT_REINDEER: &str = r#"
+    SELECT name, strength, speed, height, antler_width, snow_magic_power, favorite_food,
+      candies_eaten_yesterday
+    FROM reindeer
+"#;
+
+fn reindeer(
+    (
+        name,
+        strength,
+        speed,
+        height,
+        antler_width,
+        snow_magic_power,
+        favorite_food,
+        candies_eaten_yesterday,
+    ): ReindeerRow,
+) -> Reindeer {
+    Reindeer {
+        name,
+        strength,
+        speed,
+        height,
+        antler_width,
+        snow_magic_power,
+        favorite_food,
+        candies_eaten_yesterday,
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, ToSchema)]
+pub struct NewContest {
+    // Names of the reindeer taking part, in the order they line up. The whole roster, by name,
+    // when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reindeer: Option<Vec<String>>,
+    #[serde(default)]
+    pub missing: MissingStats,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct Contest {
+    pub id: i32,
+    pub held_at: DateTime<Utc>,
+    // The reindeer as they were when the contest was held.
+    pub reindeer: Vec<Reindeer>,
+    pub missing: MissingStats,
+    pub result: ContestResult,
+}
+
+fn contest(
+    (id, held_at, Stored(reindeer), Stored(result), missing): ContestRow,
+) -> Result<Contest, AppError> {
+    let missing = serde_json::from_value(missing.into()).map_err(AppError::internal)?;
+    Ok(Contest {
+        id,
+        held_at,
+        reindeer,
+        missing,
+        result,
+    })
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ContestParams {
+    // Contests held at or after this time.
+    pub from: Option<DateTime<Utc>>,
+    // Contests held before this time.
+    pub to: Option<DateTime<Utc>>,
+    // Number of contests, 50 by default and at most 500.
+    pub limit: Option<i64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LeaderboardParams {
+    // Name of a built-in or configured category.
+    pub category: Option<String>,
+    // Contests held at or after this time.
+    pub from: Option<DateTime<Utc>>,
+    // Contests held before this time.
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct LeaderboardEntry {
+    pub category: String,
+    pub reindeer: String,
+    pub wins: i64,
+    pub last_won_at: DateTime<Utc>,
+}
+
+fn check_length(value: &str, what: &str) -> Result<(), AppError> {
+    if value.is_empty() || value.chars().count() > NAME_MAX_CHARS {
+        return Err(AppError::unprocessable_entity(anyhow!(
+            "The {} must have between 1 and {} characters",
+            what,
+            NAME_MAX_CHARS
+        )));
+    }
+    Ok(())
+}
+
+fn check_reindeer(reindeer: &Reindeer) -> Result<(), AppError> {
+    check_length(&reindeer.name, "name")?;
+    if let Some(food) = &reindeer.favorite_food {
+        check_length(food, "favorite food")?;
+    }
+    Ok(())
+}
+
+fn reindeer_not_found(name: &str) -> AppError {
+    AppError::not_found(anyhow!("No reindeer named {}", name))
+}
+
+// The reindeer of the roster with the given names, in the order given and each one once.
+async fn line_up(
+    conn: &mut PgConnection,
+    names: Option<&[String]>,
+) -> Result<Vec<Reindeer>, AppError> {
+    let Some(names) = names else {
+        let rows = sqlx::query_as::<_, ReindeerRow>(&format!("{} ORDER BY name", SELECT_REINDEER))
+            .fetch_all(&mut *conn)
+            .await?;
+        return Ok(rows.into_iter().map(reindeer).collect());
+    };
+    let mut seen = HashSet::new();
+    let names = names
+        .iter()
+        .filter(|name| seen.insert(name.as_str()))
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut stored =
+        sqlx::query_as::<_, ReindeerRow>(&format!("{} WHERE name = ANY($1)", SELECT_REINDEER))
+            .bind(&names)
+            .fetch_all(&mut *conn)
+            .await?
+            .into_iter()
+            .map(reindeer)
+            .collect::<Vec<_>>();
+    let mut lined_up = Vec::with_capacity(names.len());
+    let mut unknown = Vec::new();
+    for name in names {
+        match stored.iter().position(|reindeer| reindeer.name == name) {
+            Some(index) => lined_up.push(stored.swap_remove(index)),
+            None => unknown.push(name),
+        }
+    }
+    if !unknown.is_empty() {
+        return Err(AppError::unprocessable_entity(anyhow!(
+            "Unknown reindeer: {}",
+            unknown.join(", ")
+        )));
+    }
+    Ok(lined_up)
+}
+
+#[utoipa::path(
+    get,
+    path = "/reindeer",
+    tag = "reindeer",
+    security((), ("bearer" = [])),
+    responses(
+        (status = 200, description = "The roster, by name", body = Vec<Reindeer>),
+        (status = 401, description = "Missing or unknown API key when reads are not public", body = ProblemDetails)
+    )
+)]
+async fn list_reindeer(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+) -> Result<Json<Vec<Reindeer>>, AppError> {
+    let mut tx = tenant::begin(&state.pool, &tenant).await?;
+    Ok(Json(line_up(&mut tx, None).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/reindeer",
+    tag = "reindeer",
+    request_body = Vec<Reindeer>,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Reindeer added to the roster, or their stats replaced", body = Vec<Reindeer>),
+        (status = 401, description = "Missing or unknown API key", body = ProblemDetails),
+        (status = 403, description = "Writer role required", body = ProblemDetails),
+        (status = 422, description = "Name or favorite food empty or too long", body = ProblemDetails)
+    )
+)]
+async fn register_reindeer(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Json(reindeers): Json<Vec<Reindeer>>,
+) -> Result<Json<Vec<Reindeer>>, AppError> {
+    for reindeer in &reindeers {
+        check_reindeer(reindeer)?;
+    }
+    let mut tx = tenant::begin(&state.pool, &tenant).await?;
+    for reindeer in &reindeers {
+        sqlx::query(
+            r#"
+            INSERT INTO reindeer (name, strength, speed, height, antler_width, snow_magic_power,
+              favorite_food, candies_eaten_yesterday)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (tenant, name) DO UPDATE SET
+              strength = EXCLUDED.strength,
+              speed = EXCLUDED.speed,
+              height = EXCLUDED.height,
+              antler_width = EXCLUDED.antler_width,
+              snow_magic_power = EXCLUDED.snow_magic_power,
+              favorite_food = EXCLUDED.favorite_food,
+              candies_eaten_yesterday = EXCLUDED.candies_eaten_yesterday
+            "#,
+        )
+        .bind(&reindeer.name)
+        .bind(reindeer.strength)
+        .bind(reindeer.speed)
+        .bind(reindeer.height)
+        .bind(reindeer.antler_width)
+        .bind(reindeer.snow_magic_power)
+        .bind(&reindeer.favorite_food)
+        .bind(reindeer.candies_eaten_yesterday)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(Json(reindeers))
+}
+
+#[utoipa::path(
+    get,
+    path = "/reindeer/{name}",
+    tag = "reindeer",
+    params(("name" = String, Path, description = "Reindeer name")),
+    security((), ("bearer" = [])),
+    responses(
+        (status = 200, description = "The reindeer", body = Reindeer),
+        (status = 401, description = "Missing or unknown API key when reads are not public", body = ProblemDetails),
+        (status = 404, description = "No reindeer with this name", body = ProblemDetails)
+    )
+)]
+async fn get_reindeer(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Path(name): Path<String>,
+) -> Result<Json<Reindeer>, AppError> {
+    let mut tx = tenant::begin(&state.pool, &tenant).await?;
+    let row = sqlx::query_as::<_, ReindeerRow>(&format!("{} WHERE name = $1", SELECT_REINDEER))
+        .bind(&name)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| reindeer_not_found(&name))?;
+    Ok(Json(reindeer(row)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/reindeer/{name}",
+    tag = "reindeer",
+    params(("name" = String, Path, description = "Reindeer name")),
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Reindeer removed from the roster, past contests keep it"),
+        (status = 401, description = "Missing or unknown API key", body = ProblemDetails),
+        (status = 403, description = "Writer role required", body = ProblemDetails),
+        (status = 404, description = "No reindeer with this name", body = ProblemDetails)
+    )
+)]
+async fn delete_reindeer(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let mut tx = tenant::begin(&state.pool, &tenant).await?;
+    let deleted = sqlx::query("DELETE FROM reindeer WHERE name = $1")
+        .bind(&name)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    if deleted == 0 {
+        return Err(reindeer_not_found(&name));
+    }
+    tx.commit().await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/contests",
+    tag = "reindeer",
+    request_body = NewContest,
+    security(("bearer" = [])),
+    responses(
+        (status = 201, description = "Contest held and recorded", body = Contest),
+        (status = 401, description = "Missing or unknown API key", body = ProblemDetails),
+        (status = 403, description = "Writer role required", body = ProblemDetails),
+        (status = 422, description = "Empty line-up, reindeer missing from the roster, or lacking stats the contest needs", body = ProblemDetails)
+    )
+)]
+async fn hold_contest(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Extension(rules): Extension<ContestRules>,
+    Json(new): Json<NewContest>,
+) -> Result<(StatusCode, Json<Contest>), AppError> {
+    let mut tx = tenant::begin(&state.pool, &tenant).await?;
+    let reindeers = line_up(&mut tx, new.reindeer.as_deref()).await?;
+    let (result, winners) = rules.run(&reindeers, new.missing)?;
+    let row = sqlx::query_as::<_, ContestRow>(
+        r#"
+        INSERT INTO contests (roster, result, missing_stats) VALUES ($1, $2, $3)
+        RETURNING id, held_at, roster, result, missing_stats
+        "#,
+    )
+    .bind(Stored(&reindeers))
+    .bind(Stored(&result))
+    .bind(new.missing.as_str())
+    .fetch_one(&mut *tx)
+    .await?;
+    for (category, winner) in winners {
+        sqlx::query(
+            "INSERT INTO contest_winners (contest_id, category, reindeer) VALUES ($1, $2, $3)",
+        )
+        .bind(row.0)
+        .bind(category)
+        .bind(winner)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok((StatusCode::CREATED, Json(contest(row)?)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/contests",
+    tag = "reindeer",
+    params(ContestParams),
+    security((), ("bearer" = [])),
+    responses(
+        (status = 200, description = "Contests held in the time range, newest first", body = Vec<Contest>),
+        (status = 400, description = "Invalid limit", body = ProblemDetails),
+        (status = 401, description = "Missing or unknown API key when reads are not public", body = ProblemDetails)
+    )
+)]
+async fn list_contests(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Query(params): Query<ContestParams>,
+) -> Result<Json<Vec<Contest>>, AppError> {
+    let limit = params.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return Err(AppError::bad_request(anyhow!(
+            "Limit must be between 1 and {}",
+            MAX_PAGE_SIZE
+        )));
+    }
+    let mut tx = tenant::begin(&state.pool, &tenant).await?;
+    let rows = sqlx::query_as::<_, ContestRow>(
+        r#"
+        SELECT id, held_at, roster, result, missing_stats FROM contests
+        WHERE ($1::TIMESTAMPTZ IS NULL OR held_at >= $1)
+          AND ($2::TIMESTAMPTZ IS NULL OR held_at < $2)
+        ORDER BY held_at DESC, id DESC
+        LIMIT $3
+        "#,
+    )
+    .bind(params.from)
+    .bind(params.to)
+    .bind(limit)
+    .fetch_all(&mut *tx)
+    .await?;
+    Ok(Json(
+        rows.into_iter().map(contest).collect::<Result<_, _>>()?,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/contests/{id}",
+    tag = "reindeer",
+    params(("id" = i32, Path, description = "Contest id")),
+    security((), ("bearer" = [])),
+    responses(
+        (status = 200, description = "The contest", body = Contest),
+        (status = 401, description = "Missing or unknown API key when reads are not public", body = ProblemDetails),
+        (status = 404, description = "No contest with this id", body = ProblemDetails)
+    )
+)]
+async fn get_contest(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Path(id): Path<i32>,
+) -> Result<Json<Contest>, AppError> {
+    let mut tx = tenant::begin(&state.pool, &tenant).await?;
+    let row = sqlx::query_as::<_, ContestRow>(
+        "SELECT id, held_at, roster, result, missing_stats FROM contests WHERE id = $1",
+    )
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::not_found(anyhow!("Contest {} does not exist", id)))?;
+    Ok(Json(contest(row)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/contests/leaderboard",
+    tag = "reindeer",
+    params(LeaderboardParams),
+    security((), ("bearer" = [])),
+    responses(
+        (status = 200, description = "Wins of every reindeer in each category, most first", body = Vec<LeaderboardEntry>),
+        (status = 401, description = "Missing or unknown API key when reads are not public", body = ProblemDetails)
+    )
+)]
+async fn leaderboard(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Query(params): Query<LeaderboardParams>,
+) -> Result<Json<Vec<LeaderboardEntry>>, AppError> {
+    let mut tx = tenant::begin(&state.pool, &tenant).await?;
+    let rows = sqlx::query_as::<_, LeaderboardRow>(
+        r#"
+        SELECT w.category, w.reindeer, COUNT(*), MAX(c.held_at)
+        FROM contest_winners w
+        JOIN contests c ON c.id = w.contest_id
+        WHERE ($1::TEXT IS NULL OR w.category = $1)
+          AND ($2::TIMESTAMPTZ IS NULL OR c.held_at >= $2)
+          AND ($3::TIMESTAMPTZ IS NULL OR c.held_at < $3)
+        GROUP BY w.category, w.reindeer
+        ORDER BY w.category, COUNT(*) DESC, MAX(c.held_at) DESC, w.reindeer
+        "#,
+    )
+    .bind(&params.category)
+    .bind(params.from)
+    .bind(params.to)
+    .fetch_all(&mut *tx)
+    .await?;
+    let entries = rows
+        .into_iter()
+        .map(|(category, reindeer, wins, last_won_at)| LeaderboardEntry {
+            category,
+            reindeer,
+            wins,
+            last_won_at,
+        })
+        .collect();
+    Ok(Json(entries))
+}
+
+pub fn get_routes(state: CommonState, config: &Config) -> Router {
+    let reader =
+        middleware::from_fn_with_state(RoleGuard::new(&state, config, Role::Reader), authorize);
+    let writer =
+        middleware::from_fn_with_state(RoleGuard::new(&state, config, Role::Writer), authorize);
+    Router::new()
+        .route(
+            "/reindeer",
+            get(list_reindeer)
+                .route_layer(reader.clone())
+                .merge(post(register_reindeer).route_layer(writer.clone())),
+        )
+        .route(
+            "/reindeer/:name",
+            get(get_reindeer)
+                .route_layer(reader.clone())
+                .merge(delete(delete_reindeer).route_layer(writer.clone())),
+        )
+        .route(
+            "/contests",
+            get(list_contests)
+                .route_layer(reader.clone())
+                .merge(post(hold_contest).route_layer(writer)),
+        )
+        .route(
+            "/contests/leaderboard",
+            get(leaderboard).route_layer(reader.clone()),
+        )
+        .route("/contests/:id", get(get_contest).route_layer(reader))
+        .layer(Extension(ContestRules::new(&config.contest)))
+        .with_state(state)
+}
@@ -0,0 +1,676 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        RwLock, RwLockReadGuard, RwLockWriteGuard,
+    },
+};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+
+use super::{GiftRepository, OrderRepository, RegionRepository};
+use crate::{
+    audit::Actor,
+    day13::{check_order, reject, IngestMode, IngestReport, Order, RejectReason},
+    day18::{
+        nest, region_id, AnalyticsBucket, AnalyticsParams, NodeRow, RankedGift, Region,
+        RegionAnalytics, RegionNode, RegionTopList, RegionsTotalResponse,
+    },
+    error::AppError,
+    gifts::{gift_not_found, name_key, name_taken, tidy, CategoryTotal, Gift, GiftMetric, NewGift},
+    tenant::Tenant,
+};
+
+#[derive(Debug, Clone)]
+struct StoredOrder {
+    order: Order,
+    gift_id: Option<i32>,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Dataset {
+    regions: BTreeMap<i64, Region>,
+    orders: BTreeMap<i32, StoredOrder>,
+    gifts: BTreeMap<i32, Gift>,
+}
+
+// Tenants that never stored anything read as empty.
+static EMPTY: Dataset = Dataset {
+    regions: BTreeMap::new(),
+    orders: BTreeMap::new(),
+    gifts: BTreeMap::new(),
+};
+
+fn dataset<'a>(datasets: &'a HashMap<Tenant, Dataset>, tenant: &Tenant) -> &'a Dataset {
+    datasets.get(tenant).unwrap_or(&EMPTY)
+}
+
+impl Dataset {
+    fn gift_named(&self, key: &str) -> Option<&Gift> {
+        self.gifts.values().find(|gift| {
+            name_key(&gift.name) == key || gift.aliases.iter().any(|alias| name_key(alias) == key)
+        })
+    }
+
+    // Name an order is stored with, like the database trigger resolves it: the catalog name, else
+    // the spelling of another order with the same key.
+    fn resolve(&self, id: i32, gift_name: &str) -> (String, Option<i32>) {
+        let key = name_key(gift_name);
+        if let Some(gift) = self.gift_named(&key) {
+            return (gift.name.clone(), Some(gift.id));
+        }
+        let spelling = self
+            .orders
+            .values()
+            .find(|stored| stored.order.id != id && name_key(&stored.order.gift_name) == key)
+            .map_or_else(|| tidy(gift_name), |stored| stored.order.gift_name.clone());
+        (spelling, None)
+    }
+
+    fn store(&mut self, order: &Order, created_at: DateTime<Utc>) {
+        let (gift_name, gift_id) = self.resolve(order.id, &order.gift_name);
+        let order = Order {
+            gift_name,
+            ..order.clone()
+        };
+        self.orders.insert(
+            order.id,
+            StoredOrder {
+                order,
+                gift_id,
+                created_at,
+            },
+        );
+    }
+
+    fn value(&self, stored: &StoredOrder, by: GiftMetric) -> i64 {
+        let quantity = i64::from(stored.order.quantity);
+        match by {
+            GiftMetric::Quantity => quantity,
+            GiftMetric::Revenue => {
+                quantity
+                    * stored
+                        .gift_id
+                        .and_then(|id| self.gifts.get(&id))
+                        .map_or(0, |gift| gift.unit_price_cents)
+            }
+        }
+    }
+
+    // Stores the names of a gift, once none of them names another gift, then links its orders
+    // and the ones matching one of its names to it.
+    fn add_gift(&mut self, id: i32, gift: &NewGift) -> Result<Gift, AppError> {
+        let names = gift.names();
+        let keys = names
+            .iter()
+            .map(|name| name_key(name))
+            .collect::<BTreeSet<_>>();
+        let taken = self
+            .gifts
+            .values()
+            .filter(|other| other.id != id)
+            .flat_map(|other| {
+                std::iter::once(&other.name)
+                    .chain(&other.aliases)
+                    .map(move |name| (name_key(name), name, &other.name))
+            })
+            .filter(|(key, _, _)| keys.contains(key))
+            .min_by(|a, b| a.0.cmp(&b.0));
+        if let Some((_, name, other)) = taken {
+            return Err(name_taken(name, other));
+        }
+
+        let gift = Gift {
+            id,
+            name: gift.name.clone(),
+            aliases: gift.aliases.clone(),
+            unit_price_cents: gift.unit_price_cents,
+            category: gift.category.clone(),
+        };
+        self.gifts.insert(id, gift.clone());
+        for stored in self.orders.values_mut() {
+            let linked = stored.gift_id == Some(id)
+                || (stored.gift_id.is_none() && keys.contains(&name_key(&stored.order.gift_name)));
+            if linked {
+                stored.order.gift_name = gift.name.clone();
+                stored.gift_id = Some(id);
+            }
+        }
+        Ok(gift)
+    }
+
+    // Regions ordered by name, like the reports list them.
+    fn regions_by_name(&self) -> Vec<&Region> {
+        let mut regions = self.regions.values().collect::<Vec<_>>();
+        regions.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+        regions
+    }
+
+    // The region and every region below it.
+    fn subtree(&self, id: i64) -> HashSet<i64> {
+        let mut subtree = HashSet::from([id]);
+        let mut pending = vec![id];
+        while let Some(parent) = pending.pop() {
+            for region in self.regions.values() {
+                if region.parent_id == Some(parent) && subtree.insert(region.id) {
+                    pending.push(region.id);
+                }
+            }
+        }
+        subtree
+    }
+}
+
+// Sums the value of each gift, largest first and by name on a tie.
+fn rank_gifts<'a>(values: impl Iterator<Item = (&'a str, i64)>) -> Vec<(String, i64)> {
+    let mut totals = HashMap::<&str, i64>::new();
+    for (gift_name, value) in values {
+        *totals.entry(gift_name).or_default() += value;
+    }
+    let mut gifts = totals
+        .into_iter()
+        .map(|(gift_name, total)| (gift_name.to_string(), total))
+        .collect::<Vec<_>>();
+    gifts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    gifts
+}
+
+fn by_quantity<'a>(
+    orders: impl Iterator<Item = &'a Order>,
+) -> impl Iterator<Item = (&'a str, i64)> {
+    orders.map(|order| (order.gift_name.as_str(), i64::from(order.quantity)))
+}
+
+fn top(gifts: &[(String, i64)], number: i64) -> Vec<String> {
+    let number = usize::try_from(number).unwrap_or(0);
+    gifts
+        .iter()
+        .take(number)
+        .map(|(gift_name, _)| gift_name.clone())
+        .collect()
+}
+
+// Continuous percentile of sorted values, interpolating between the closest two like
+// `percentile_cont` does.
+fn percentile(sorted: &[i32], fraction: f64) -> f64 {
+    let position = fraction * (sorted.len() - 1) as f64;
+    let lower = f64::from(sorted[position.floor() as usize]);
+    let upper = f64::from(sorted[position.ceil() as usize]);
+    lower + (upper - lower) * (position - position.floor())
+}
+
+fn bucket_of(bucket: Option<AnalyticsBucket>, created_at: DateTime<Utc>) -> Option<NaiveDate> {
+    let day = created_at.date_naive();
+    bucket.map(|bucket| match bucket {
+        AnalyticsBucket::Day => day,
+        AnalyticsBucket::Week => day - Duration::days(day.weekday().num_days_from_monday().into()),
+    })
+}
+
+// Keeps orders and regions in the process, with the same rules and results as the database.
+#[derive(Debug, Default)]
+pub struct MemoryRepository {
+    data: RwLock<HashMap<Tenant, Dataset>>,
+    // Gift ids are shared by every tenant, like the sequence of the gifts table.
+    last_gift_id: AtomicI32,
+}
+
+impl MemoryRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, HashMap<Tenant, Dataset>> {
+        self.data
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, HashMap<Tenant, Dataset>> {
+        self.data
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[async_trait]
+impl OrderRepository for MemoryRepository {
+    async fn ingest(
+        &self,
+        tenant: &Tenant,
+        _actor: &Actor,
+        orders: &[Order],
+        mode: IngestMode,
+    ) -> Result<IngestReport, AppError> {
+        let mut datasets = self.write();
+        let data = datasets.entry(tenant.clone()).or_default();
+        let regions = data
+            .regions
+            .keys()
+            .filter_map(|id| i32::try_from(*id).ok())
+            .collect::<HashSet<_>>();
+
+        let mut seen = HashSet::new();
+        let mut rejected = Vec::new();
+        let mut valid = Vec::new();
+        for (index, order) in orders.iter().enumerate() {
+            match check_order(order, &mut seen, &regions) {
+                Some(reason) => reject(&mut rejected, index, order, reason),
+                None => valid.push((index, order)),
+            }
+        }
+
+        // Like the database, existing ids are only looked at when the batch gets stored.
+        let mut stored = Vec::new();
+        let mut inserted = 0;
+        if !valid.is_empty() && (mode != IngestMode::AllOrNothing || rejected.is_empty()) {
+            for (index, order) in valid {
+                let exists = data.orders.contains_key(&order.id);
+                if exists && mode != IngestMode::Upsert {
+                    reject(&mut rejected, index, order, RejectReason::DuplicateId);
+                    continue;
+                }
+                if !exists {
+                    inserted += 1;
+                }
+                stored.push(order);
+            }
+        }
+
+        rejected.sort_by_key(|rejected| rejected.index);
+        let committed = mode != IngestMode::AllOrNothing || rejected.is_empty();
+        if !committed {
+            return Ok(IngestReport {
+                mode,
+                committed,
+                inserted: 0,
+                updated: 0,
+                rejected,
+            });
+        }
+        let now = Utc::now();
+        for order in &stored {
+            let created_at = data
+                .orders
+                .get(&order.id)
+                .map_or(now, |stored| stored.created_at);
+            data.store(order, created_at);
+        }
+        Ok(IngestReport {
+            mode,
+            committed,
+            inserted,
+            updated: stored.len() - inserted,
+            rejected,
+        })
+    }
+
+    async fn total(&self, tenant: &Tenant, by: GiftMetric) -> Result<i64, AppError> {
+        let datasets = self.read();
+        let data = dataset(&datasets, tenant);
+        Ok(data
+            .orders
+            .values()
+            .map(|stored| data.value(stored, by))
+            .sum())
+    }
+
+    async fn popular(&self, tenant: &Tenant, by: GiftMetric) -> Result<Option<String>, AppError> {
+        let datasets = self.read();
+        let data = dataset(&datasets, tenant);
+        let gifts = rank_gifts(
+            data.orders
+                .values()
+                .map(|stored| (stored.order.gift_name.as_str(), data.value(stored, by))),
+        );
+        Ok(gifts.into_iter().next().map(|(gift_name, _)| gift_name))
+    }
+
+    async fn reset(
+        &self,
+        tenant: &Tenant,
+        _actor: &Actor,
+        snapshot: Option<&str>,
+    ) -> Result<(), AppError> {
+        if snapshot.is_some() {
+            return Err(AppError::unprocessable_entity(anyhow!(
+                "Snapshots are only kept by the Postgres backend"
+            )));
+        }
+        // The catalog is kept, like the gift tables are.
+        let mut datasets = self.write();
+        let data = datasets.entry(tenant.clone()).or_default();
+        data.orders.clear();
+        data.regions.clear();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RegionRepository for MemoryRepository {
+    async fn add(
+        &self,
+        tenant: &Tenant,
+        _actor: &Actor,
+        regions: &[Region],
+    ) -> Result<(), AppError> {
+        let mut datasets = self.write();
+        let data = datasets.entry(tenant.clone()).or_default();
+        let mut added = data.regions.clone();
+        for region in regions {
+            region_id(region.id)?;
+            region.parent_id.map(region_id).transpose()?;
+            if added.contains_key(&region.id) {
+                return Err(AppError::conflict(anyhow!(
+                    "Region {} already exists",
+                    region.id
+                )));
+            }
+            if let Some(parent_id) = region.parent_id {
+                if parent_id == region.id {
+                    return Err(AppError::unprocessable_entity(anyhow!(
+                        "Region {} can't be its own parent",
+                        region.id
+                    )));
+                }
+                if !added.contains_key(&parent_id) {
+                    return Err(AppError::unprocessable_entity(anyhow!(
+                        "Region {} does not exist",
+                        parent_id
+                    )));
+                }
+            }
+            added.insert(region.id, region.clone());
+        }
+        data.regions = added;
+        Ok(())
+    }
+
+    async fn move_region(
+        &self,
+        tenant: &Tenant,
+        _actor: &Actor,
+        id: i64,
+        parent_id: Option<i64>,
+    ) -> Result<Region, AppError> {
+        region_id(id)?;
+        parent_id.map(region_id).transpose()?;
+        let mut datasets = self.write();
+        let data = datasets.entry(tenant.clone()).or_default();
+        if let Some(parent_id) = parent_id {
+            if !data.regions.contains_key(&parent_id) {
+                return Err(AppError::unprocessable_entity(anyhow!(
+                    "Region {} does not exist",
+                    parent_id
+                )));
+            }
+            if data.regions.contains_key(&id) && data.subtree(id).contains(&parent_id) {
+                return Err(AppError::unprocessable_entity(anyhow!(
+                    "Region {} is below region {}",
+                    parent_id,
+                    id
+                )));
+            }
+        }
+        let region = data
+            .regions
+            .get_mut(&id)
+            .ok_or_else(|| AppError::not_found(anyhow!("Region {} does not exist", id)))?;
+        region.parent_id = parent_id;
+        Ok(region.clone())
+    }
+
+    async fn totals(
+        &self,
+        tenant: &Tenant,
+        by: GiftMetric,
+    ) -> Result<Vec<RegionsTotalResponse>, AppError> {
+        let datasets = self.read();
+        let data = dataset(&datasets, tenant);
+        let mut totals = BTreeMap::<&str, i64>::new();
+        for stored in data.orders.values() {
+            if let Some(region) = data.regions.get(&stored.order.region_id.into()) {
+                *totals.entry(&region.name).or_default() += data.value(stored, by);
+            }
+        }
+        Ok(totals
+            .into_iter()
+            .map(|(region, total)| RegionsTotalResponse {
+                region: region.to_string(),
+                total,
+            })
+            .collect())
+    }
+
+    async fn top_lists(
+        &self,
+        tenant: &Tenant,
+        number: i64,
+        by: GiftMetric,
+    ) -> Result<Vec<RegionTopList>, AppError> {
+        let datasets = self.read();
+        let data = dataset(&datasets, tenant);
+        Ok(data
+            .regions_by_name()
+            .into_iter()
+            .map(|region| {
+                let values = data
+                    .orders
+                    .values()
+                    .filter(|stored| i64::from(stored.order.region_id) == region.id)
+                    .map(|stored| (stored.order.gift_name.as_str(), data.value(stored, by)));
+                RegionTopList {
+                    region: region.name.clone(),
+                    top_gifts: top(&rank_gifts(values), number),
+                }
+            })
+            .collect())
+    }
+
+    async fn tree(&self, tenant: &Tenant, top_gifts: i64) -> Result<Vec<RegionNode>, AppError> {
+        let datasets = self.read();
+        let data = dataset(&datasets, tenant);
+        let mut children = HashMap::<Option<i32>, Vec<NodeRow>>::new();
+        for region in data.regions_by_name() {
+            let subtree = data.subtree(region.id);
+            let orders = data
+                .orders
+                .values()
+                .map(|stored| &stored.order)
+                .filter(|order| subtree.contains(&order.region_id.into()))
+                .collect::<Vec<_>>();
+            let own_total = orders
+                .iter()
+                .filter(|order| i64::from(order.region_id) == region.id)
+                .map(|order| i64::from(order.quantity))
+                .sum();
+            let total = orders.iter().map(|order| i64::from(order.quantity)).sum();
+            let gifts = rank_gifts(by_quantity(orders.into_iter()));
+            let parent_id = region.parent_id.map(region_id).transpose()?;
+            children.entry(parent_id).or_default().push((
+                region_id(region.id)?,
+                parent_id,
+                region.name.clone(),
+                own_total,
+                total,
+                top(&gifts, top_gifts),
+            ));
+        }
+        Ok(nest(&mut children, None))
+    }
+
+    async fn analytics(
+        &self,
+        tenant: &Tenant,
+        params: &AnalyticsParams,
+        top: i64,
+    ) -> Result<Vec<RegionAnalytics>, AppError> {
+        let datasets = self.read();
+        let data = dataset(&datasets, tenant);
+        let mut groups = BTreeMap::<(Option<NaiveDate>, i64), Vec<&Order>>::new();
+        for stored in data.orders.values() {
+            if params.from.is_some_and(|from| stored.created_at < from)
+                || params.to.is_some_and(|to| stored.created_at >= to)
+            {
+                continue;
+            }
+            let bucket = bucket_of(params.bucket, stored.created_at);
+            groups
+                .entry((bucket, stored.order.region_id.into()))
+                .or_default()
+                .push(&stored.order);
+        }
+
+        let mut bucket_totals = HashMap::<Option<NaiveDate>, i64>::new();
+        for ((bucket, _), orders) in &groups {
+            *bucket_totals.entry(*bucket).or_default() += orders
+                .iter()
+                .map(|order| i64::from(order.quantity))
+                .sum::<i64>();
+        }
+
+        let mut rows = groups
+            .into_iter()
+            .filter_map(|((bucket, region_id), orders)| {
+                let region = data.regions.get(&region_id)?;
+                let total = orders
+                    .iter()
+                    .map(|order| i64::from(order.quantity))
+                    .sum::<i64>();
+                let bucket_total = bucket_totals[&bucket];
+                let mut quantities = orders
+                    .iter()
+                    .map(|order| order.quantity)
+                    .collect::<Vec<_>>();
+                quantities.sort_unstable();
+                let gifts = rank_gifts(by_quantity(orders.iter().copied()));
+                let top_gifts = gifts
+                    .iter()
+                    .take(usize::try_from(top).unwrap_or(0))
+                    .map(|(gift_name, total)| RankedGift {
+                        gift_name: gift_name.clone(),
+                        total: *total,
+                        rank: 1 + gifts.iter().filter(|(_, other)| other > total).count() as i64,
+                    })
+                    .collect();
+                Some((
+                    region_id,
+                    RegionAnalytics {
+                        region: region.name.clone(),
+                        bucket,
+                        orders: orders.len() as i64,
+                        total,
+                        share: if bucket_total == 0 {
+                            0.0
+                        } else {
+                            total as f64 / bucket_total as f64
+                        },
+                        distinct_gifts: orders
+                            .iter()
+                            .map(|order| &order.gift_name)
+                            .collect::<BTreeSet<_>>()
+                            .len() as i64,
+                        median_quantity: percentile(&quantities, 0.5),
+                        p90_quantity: percentile(&quantities, 0.9),
+                        top_gifts,
+                    },
+                ))
+            })
+            .collect::<Vec<_>>();
+        rows.sort_by(|(a_id, a), (b_id, b)| {
+            a.bucket
+                .cmp(&b.bucket)
+                .then_with(|| a.region.cmp(&b.region))
+                .then(a_id.cmp(b_id))
+        });
+        Ok(rows.into_iter().map(|(_, row)| row).collect())
+    }
+}
+
+#[async_trait]
+impl GiftRepository for MemoryRepository {
+    async fn list(&self, tenant: &Tenant) -> Result<Vec<Gift>, AppError> {
+        let datasets = self.read();
+        let mut gifts = dataset(&datasets, tenant)
+            .gifts
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        gifts.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+        Ok(gifts)
+    }
+
+    async fn get(&self, tenant: &Tenant, id: i32) -> Result<Gift, AppError> {
+        dataset(&self.read(), tenant)
+            .gifts
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| gift_not_found(id))
+    }
+
+    async fn create(
+        &self,
+        tenant: &Tenant,
+        _actor: &Actor,
+        gift: &NewGift,
+    ) -> Result<Gift, AppError> {
+        let mut datasets = self.write();
+        let data = datasets.entry(tenant.clone()).or_default();
+        // Like a sequence, ids aren't given back when the gift can't be added.
+        let id = self.last_gift_id.fetch_add(1, Ordering::Relaxed) + 1;
+        data.add_gift(id, gift)
+    }
+
+    async fn update(
+        &self,
+        tenant: &Tenant,
+        _actor: &Actor,
+        id: i32,
+        gift: &NewGift,
+    ) -> Result<Gift, AppError> {
+        let mut datasets = self.write();
+        let data = datasets.entry(tenant.clone()).or_default();
+        if !data.gifts.contains_key(&id) {
+            return Err(gift_not_found(id));
+        }
+        data.add_gift(id, gift)
+    }
+
+    async fn delete(&self, tenant: &Tenant, _actor: &Actor, id: i32) -> Result<(), AppError> {
+        let mut datasets = self.write();
+        let data = datasets.entry(tenant.clone()).or_default();
+        data.gifts.remove(&id).ok_or_else(|| gift_not_found(id))?;
+        for stored in data.orders.values_mut() {
+            if stored.gift_id == Some(id) {
+                stored.gift_id = None;
+            }
+        }
+        Ok(())
+    }
+
+    async fn categories(&self, tenant: &Tenant) -> Result<Vec<CategoryTotal>, AppError> {
+        let datasets = self.read();
+        let data = dataset(&datasets, tenant);
+        let mut categories = BTreeMap::<Option<&str>, CategoryTotal>::new();
+        for stored in data.orders.values() {
+            let gift = stored.gift_id.and_then(|id| data.gifts.get(&id));
+            let category = gift.and_then(|gift| gift.category.as_deref());
+            let total = categories.entry(category).or_insert_with(|| CategoryTotal {
+                category: category.map(str::to_string),
+                orders: 0,
+                quantity: 0,
+                revenue_cents: 0,
+            });
+            total.orders += 1;
+            total.quantity += i64::from(stored.order.quantity);
+            total.revenue_cents += data.value(stored, GiftMetric::Revenue);
+        }
+        // Orders without a category come last, like `NULLS LAST`.
+        let mut categories = categories.into_values().collect::<Vec<_>>();
+        categories.sort_by_key(|total| (total.category.is_none(), total.category.clone()));
+        Ok(categories)
+    }
+}
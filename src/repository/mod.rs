@@ -0,0 +1,150 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use sqlx::PgPool;
+
+use crate::{
+    audit::Actor,
+    config::StorageBackend,
+    day13::{IngestMode, IngestReport, Order},
+    day18::{
+        AnalyticsParams, Region, RegionAnalytics, RegionNode, RegionTopList, RegionsTotalResponse,
+    },
+    error::AppError,
+    gifts::{CategoryTotal, Gift, GiftMetric, NewGift},
+    tenant::Tenant,
+};
+
+mod memory;
+mod postgres;
+
+pub use self::{memory::MemoryRepository, postgres::PgRepository};
+
+#[async_trait]
+pub trait OrderRepository: Send + Sync {
+    // Stores a batch of orders as a whole, reporting the ones that were rejected.
+    async fn ingest(
+        &self,
+        tenant: &Tenant,
+        actor: &Actor,
+        orders: &[Order],
+        mode: IngestMode,
+    ) -> Result<IngestReport, AppError>;
+
+    // Total quantity or revenue of the orders, 0 without orders.
+    async fn total(&self, tenant: &Tenant, by: GiftMetric) -> Result<i64, AppError>;
+
+    // Gift with the largest quantity or revenue, the first by name on a tie.
+    async fn popular(&self, tenant: &Tenant, by: GiftMetric) -> Result<Option<String>, AppError>;
+
+    // Deletes every order and region of the tenant, first saving them into the named snapshot if
+    // there is one.
+    async fn reset(
+        &self,
+        tenant: &Tenant,
+        actor: &Actor,
+        snapshot: Option<&str>,
+    ) -> Result<(), AppError>;
+}
+
+#[async_trait]
+pub trait RegionRepository: Send + Sync {
+    // Stores the whole batch or none of it, parents coming before their children.
+    async fn add(&self, tenant: &Tenant, actor: &Actor, regions: &[Region])
+        -> Result<(), AppError>;
+
+    // Moves a region, with everything below it, under another one or to the top level.
+    async fn move_region(
+        &self,
+        tenant: &Tenant,
+        actor: &Actor,
+        id: i64,
+        parent_id: Option<i64>,
+    ) -> Result<Region, AppError>;
+
+    // Total quantity or revenue of each region name with orders, sorted by name.
+    async fn totals(
+        &self,
+        tenant: &Tenant,
+        by: GiftMetric,
+    ) -> Result<Vec<RegionsTotalResponse>, AppError>;
+
+    // The `number` gifts of every region with the largest quantity or revenue, sorted by name.
+    async fn top_lists(
+        &self,
+        tenant: &Tenant,
+        number: i64,
+        by: GiftMetric,
+    ) -> Result<Vec<RegionTopList>, AppError>;
+
+    // Every region nested under its parent, with the figures of its subtree.
+    async fn tree(&self, tenant: &Tenant, top: i64) -> Result<Vec<RegionNode>, AppError>;
+
+    // Figures of every region (and bucket) with orders.
+    async fn analytics(
+        &self,
+        tenant: &Tenant,
+        params: &AnalyticsParams,
+        top: i64,
+    ) -> Result<Vec<RegionAnalytics>, AppError>;
+}
+
+// Gifts given to `create` and `update` have gone through `NewGift::checked`. Every tenant has a
+// catalog of its own.
+#[async_trait]
+pub trait GiftRepository: Send + Sync {
+    async fn list(&self, tenant: &Tenant) -> Result<Vec<Gift>, AppError>;
+
+    async fn get(&self, tenant: &Tenant, id: i32) -> Result<Gift, AppError>;
+
+    // Adds a gift, resolving the orders that match one of its names to it.
+    async fn create(
+        &self,
+        tenant: &Tenant,
+        actor: &Actor,
+        gift: &NewGift,
+    ) -> Result<Gift, AppError>;
+
+    // Replaces a gift, its orders taking the new name.
+    async fn update(
+        &self,
+        tenant: &Tenant,
+        actor: &Actor,
+        id: i32,
+        gift: &NewGift,
+    ) -> Result<Gift, AppError>;
+
+    // Removes a gift, its orders keeping their name.
+    async fn delete(&self, tenant: &Tenant, actor: &Actor, id: i32) -> Result<(), AppError>;
+
+    async fn categories(&self, tenant: &Tenant) -> Result<Vec<CategoryTotal>, AppError>;
+}
+
+// Each call only sees and changes the data of the given tenant. Postgres logs the changes to orders
+// and regions with their actor.
+#[derive(Clone)]
+pub struct Repositories {
+    pub orders: Arc<dyn OrderRepository>,
+    pub regions: Arc<dyn RegionRepository>,
+    pub gifts: Arc<dyn GiftRepository>,
+}
+
+impl Repositories {
+    pub fn new(backend: StorageBackend, pool: &PgPool) -> Self {
+        match backend {
+            StorageBackend::Postgres => Self::from_store(Arc::new(PgRepository::new(pool.clone()))),
+            StorageBackend::Memory => Self::from_store(Arc::new(MemoryRepository::new())),
+        }
+    }
+
+    // Orders refer to regions and gifts, so every repository is served by the same store.
+    fn from_store<S: OrderRepository + RegionRepository + GiftRepository + 'static>(
+        store: Arc<S>,
+    ) -> Self {
+        Self {
+            orders: store.clone(),
+            regions: store.clone(),
+            gifts: store,
+        }
+    }
+}
@@ -0,0 +1,578 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use sqlx::{types::Json, PgConnection, PgExecutor, PgPool};
+
+use super::{GiftRepository, OrderRepository, RegionRepository};
+use crate::{
+    audit::{self, Actor},
+    day13::{ingest_orders, IngestMode, IngestReport, Order},
+    day18::{
+        nest, region_id, AnalyticsParams, NodeRow, RankedGift, Region, RegionAnalytics, RegionNode,
+        RegionTopList, RegionsTotalResponse,
+    },
+    error::AppError,
+    gifts::{gift_not_found, name_taken, CategoryTotal, Gift, GiftMetric, NewGift},
+    snapshots,
+    tenant::{self, Tenant},
+};
+
+type AnalyticsRow = (
+    String,
+    Option<NaiveDate>,
+    i64,
+    i64,
+    f64,
+    i64,
+    f64,
+    f64,
+    Json<Vec<RankedGift>>,
+);
+type GiftRow = (i32, String, Vec<String>, i64, Option<String>);
+
+pub struct PgRepository {
+    pool: PgPool,
+}
+
+impl PgRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+async fn add_region(region: &Region, executor: impl PgExecutor<'_>) -> Result<(), AppError> {
+    sqlx::query!(
+        "INSERT INTO regions (id, name, parent_id) VALUES ($1, $2, $3)",
+        region_id(region.id)?,
+        region.name,
+        region.parent_id.map(region_id).transpose()?,
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+#[async_trait]
+impl OrderRepository for PgRepository {
+    async fn ingest(
+        &self,
+        tenant: &Tenant,
+        actor: &Actor,
+        orders: &[Order],
+        mode: IngestMode,
+    ) -> Result<IngestReport, AppError> {
+        ingest_orders(&self.pool, tenant, actor, orders, mode).await
+    }
+
+    async fn total(&self, tenant: &Tenant, by: GiftMetric) -> Result<i64, AppError> {
+        let mut tx = tenant::begin(&self.pool, tenant).await?;
+        Ok(sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COALESCE(
+              SUM(o.quantity * CASE WHEN $1 THEN COALESCE(g.unit_price_cents, 0) ELSE 1 END), 0
+            )::BIGINT
+            FROM orders o
+            LEFT JOIN gifts g ON g.id = o.gift_id
+            "#,
+        )
+        .bind(by == GiftMetric::Revenue)
+        .fetch_one(&mut *tx)
+        .await?)
+    }
+
+    async fn popular(&self, tenant: &Tenant, by: GiftMetric) -> Result<Option<String>, AppError> {
+        let mut tx = tenant::begin(&self.pool, tenant).await?;
+        Ok(sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT o.gift_name
+            FROM orders o
+            LEFT JOIN gifts g ON g.id = o.gift_id
+            GROUP BY o.gift_name
+            ORDER BY
+              SUM(o.quantity * CASE WHEN $1 THEN COALESCE(g.unit_price_cents, 0) ELSE 1 END) DESC,
+              o.gift_name COLLATE "C" ASC
+            LIMIT 1
+            "#,
+        )
+        .bind(by == GiftMetric::Revenue)
+        .fetch_optional(&mut *tx)
+        .await?)
+    }
+
+    async fn reset(
+        &self,
+        tenant: &Tenant,
+        actor: &Actor,
+        snapshot: Option<&str>,
+    ) -> Result<(), AppError> {
+        let mut tx = audit::begin(&self.pool, tenant, actor).await?;
+        // Writes wait until the reset is over, so the snapshot holds exactly what gets deleted.
+        snapshots::lock(&mut tx).await?;
+        if let Some(name) = snapshot {
+            snapshots::save(&mut tx, name).await?;
+        }
+        snapshots::clear(&mut tx).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RegionRepository for PgRepository {
+    async fn add(
+        &self,
+        tenant: &Tenant,
+        actor: &Actor,
+        regions: &[Region],
+    ) -> Result<(), AppError> {
+        let mut transaction = audit::begin(&self.pool, tenant, actor).await?;
+        for region in regions {
+            add_region(region, &mut *transaction).await?;
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn move_region(
+        &self,
+        tenant: &Tenant,
+        actor: &Actor,
+        id: i64,
+        parent_id: Option<i64>,
+    ) -> Result<Region, AppError> {
+        let (id, parent_id) = (region_id(id)?, parent_id.map(region_id).transpose()?);
+        let mut transaction = audit::begin(&self.pool, tenant, actor).await?;
+        // Concurrent moves could otherwise each pass the cycle check and together close a loop.
+        sqlx::query("LOCK TABLE regions IN SHARE ROW EXCLUSIVE MODE")
+            .execute(&mut *transaction)
+            .await?;
+        if let Some(parent_id) = parent_id {
+            let (parent_exists, creates_cycle) = sqlx::query_as::<_, (bool, bool)>(
+                r#"
+                WITH RECURSIVE subtree AS (
+                  SELECT id FROM regions WHERE id = $1
+                  UNION
+                  SELECT r.id FROM regions r JOIN subtree s ON r.parent_id = s.id
+                )
+                SELECT EXISTS (SELECT 1 FROM regions WHERE id = $2),
+                  EXISTS (SELECT 1 FROM subtree WHERE id = $2)
+                "#,
+            )
+            .bind(id)
+            .bind(parent_id)
+            .fetch_one(&mut *transaction)
+            .await?;
+            if !parent_exists {
+                return Err(AppError::unprocessable_entity(anyhow!(
+                    "Region {} does not exist",
+                    parent_id
+                )));
+            }
+            if creates_cycle {
+                return Err(AppError::unprocessable_entity(anyhow!(
+                    "Region {} is below region {}",
+                    parent_id,
+                    id
+                )));
+            }
+        }
+        let (id, name, parent_id) = sqlx::query_as::<_, (i32, String, Option<i32>)>(
+            "UPDATE regions SET parent_id = $2 WHERE id = $1 RETURNING id, name, parent_id",
+        )
+        .bind(id)
+        .bind(parent_id)
+        .fetch_optional(&mut *transaction)
+        .await?
+        .ok_or_else(|| AppError::not_found(anyhow!("Region {} does not exist", id)))?;
+        transaction.commit().await?;
+
+        Ok(Region {
+            id: id.into(),
+            name,
+            parent_id: parent_id.map(Into::into),
+        })
+    }
+
+    async fn totals(
+        &self,
+        tenant: &Tenant,
+        by: GiftMetric,
+    ) -> Result<Vec<RegionsTotalResponse>, AppError> {
+        let mut tx = tenant::begin(&self.pool, tenant).await?;
+        Ok(sqlx::query_as::<_, (String, i64)>(
+            r#"
+            SELECT r.name,
+              SUM(o.quantity * CASE WHEN $1 THEN COALESCE(g.unit_price_cents, 0) ELSE 1 END)::BIGINT
+            FROM orders o
+            JOIN regions r ON o.region_id = r.id
+            LEFT JOIN gifts g ON g.id = o.gift_id
+            GROUP BY r.name
+            ORDER BY r.name COLLATE "C" ASC
+            "#,
+        )
+        .bind(by == GiftMetric::Revenue)
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(|(region, total)| RegionsTotalResponse { region, total })
+        .collect())
+    }
+
+    async fn top_lists(
+        &self,
+        tenant: &Tenant,
+        number: i64,
+        by: GiftMetric,
+    ) -> Result<Vec<RegionTopList>, AppError> {
+        let mut tx = tenant::begin(&self.pool, tenant).await?;
+        Ok(sqlx::query_as::<_, (String, Vec<String>)>(
+            r#"
+            WITH ranked AS (
+              SELECT o.region_id, o.gift_name,
+                ROW_NUMBER() OVER (
+                  PARTITION BY o.region_id
+                  ORDER BY
+                    SUM(o.quantity * CASE WHEN $2 THEN COALESCE(g.unit_price_cents, 0) ELSE 1 END) DESC,
+                    o.gift_name COLLATE "C" ASC
+                ) AS position
+              FROM orders o
+              LEFT JOIN gifts g ON g.id = o.gift_id
+              GROUP BY o.region_id, o.gift_name
+            )
+            SELECT r.name,
+              COALESCE(
+                ARRAY_AGG(g.gift_name ORDER BY g.position) FILTER (WHERE g.gift_name IS NOT NULL),
+                '{}'
+              )
+            FROM regions r
+            LEFT JOIN ranked g ON g.region_id = r.id AND g.position <= $1
+            GROUP BY r.id, r.name
+            ORDER BY r.name COLLATE "C" ASC, r.id ASC
+            "#,
+        )
+        .bind(number)
+        .bind(by == GiftMetric::Revenue)
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(|(region, top_gifts)| RegionTopList { region, top_gifts })
+        .collect())
+    }
+
+    async fn tree(&self, tenant: &Tenant, top: i64) -> Result<Vec<RegionNode>, AppError> {
+        let mut tx = tenant::begin(&self.pool, tenant).await?;
+        let rows = sqlx::query_as::<_, NodeRow>(
+            r#"
+            WITH RECURSIVE subtree AS (
+              SELECT id AS root, id FROM regions
+              UNION
+              SELECT s.root, r.id FROM subtree s JOIN regions r ON r.parent_id = s.id
+            ), gifts AS (
+              SELECT s.root, o.gift_name, SUM(o.quantity) AS total,
+                ROW_NUMBER() OVER (
+                  PARTITION BY s.root ORDER BY SUM(o.quantity) DESC, o.gift_name COLLATE "C" ASC
+                ) AS position
+              FROM subtree s
+              JOIN orders o ON o.region_id = s.id
+              GROUP BY s.root, o.gift_name
+            )
+            SELECT r.id, r.parent_id, r.name,
+              COALESCE((SELECT SUM(o.quantity) FROM orders o WHERE o.region_id = r.id), 0)::INT8,
+              COALESCE((SELECT SUM(g.total) FROM gifts g WHERE g.root = r.id), 0)::INT8,
+              COALESCE(
+                (SELECT ARRAY_AGG(g.gift_name ORDER BY g.position)
+                  FROM gifts g WHERE g.root = r.id AND g.position <= $1),
+                '{}'
+              )
+            FROM regions r
+            ORDER BY r.name COLLATE "C" ASC, r.id ASC
+            "#,
+        )
+        .bind(top)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let mut children = HashMap::<Option<i32>, Vec<NodeRow>>::new();
+        for row in rows {
+            children.entry(row.1).or_default().push(row);
+        }
+        Ok(nest(&mut children, None))
+    }
+
+    async fn analytics(
+        &self,
+        tenant: &Tenant,
+        params: &AnalyticsParams,
+        top: i64,
+    ) -> Result<Vec<RegionAnalytics>, AppError> {
+        let mut tx = tenant::begin(&self.pool, tenant).await?;
+        let rows = sqlx::query_as::<_, AnalyticsRow>(
+            r#"
+            WITH filtered AS (
+              SELECT region_id, gift_name, quantity,
+                date_trunc($1, created_at AT TIME ZONE 'UTC')::DATE AS bucket
+              FROM orders
+              WHERE ($2::TIMESTAMPTZ IS NULL OR created_at >= $2)
+                AND ($3::TIMESTAMPTZ IS NULL OR created_at < $3)
+            ), stats AS (
+              SELECT region_id, bucket,
+                COUNT(*) AS orders,
+                SUM(quantity) AS total,
+                COUNT(DISTINCT gift_name) AS distinct_gifts,
+                percentile_cont(0.5) WITHIN GROUP (ORDER BY quantity) AS median_quantity,
+                percentile_cont(0.9) WITHIN GROUP (ORDER BY quantity) AS p90_quantity
+              FROM filtered
+              GROUP BY region_id, bucket
+            ), gifts AS (
+              SELECT region_id, bucket, gift_name, SUM(quantity) AS total,
+                RANK() OVER (PARTITION BY region_id, bucket ORDER BY SUM(quantity) DESC) AS rank,
+                ROW_NUMBER() OVER (
+                  PARTITION BY region_id, bucket ORDER BY SUM(quantity) DESC, gift_name COLLATE "C" ASC
+                ) AS position
+              FROM filtered
+              GROUP BY region_id, bucket, gift_name
+            )
+            SELECT r.name, s.bucket, s.orders, s.total,
+              COALESCE(s.total::FLOAT8 / NULLIF(SUM(s.total) OVER (PARTITION BY s.bucket), 0), 0),
+              s.distinct_gifts, s.median_quantity, s.p90_quantity,
+              COALESCE(
+                JSONB_AGG(
+                  JSONB_BUILD_OBJECT('gift_name', g.gift_name, 'total', g.total, 'rank', g.rank)
+                  ORDER BY g.position
+                ) FILTER (WHERE g.gift_name IS NOT NULL),
+                '[]'
+              )
+            FROM stats s
+            JOIN regions r ON r.id = s.region_id
+            LEFT JOIN gifts g ON g.region_id = s.region_id
+              AND g.bucket IS NOT DISTINCT FROM s.bucket
+              AND g.position <= $4
+            GROUP BY r.name, s.region_id, s.bucket, s.orders, s.total, s.distinct_gifts,
+              s.median_quantity, s.p90_quantity
+            ORDER BY s.bucket ASC, r.name COLLATE "C" ASC, s.region_id ASC
+            "#,
+        )
+        .bind(params.bucket.map(|bucket| bucket.field()))
+        .bind(params.from)
+        .bind(params.to)
+        .bind(top)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(
+                |(
+                    region,
+                    bucket,
+                    orders,
+                    total,
+                    share,
+                    distinct_gifts,
+                    median_quantity,
+                    p90_quantity,
+                    Json(top_gifts),
+                )| RegionAnalytics {
+                    region,
+                    bucket,
+                    orders,
+                    total,
+                    share,
+                    distinct_gifts,
+                    median_quantity,
+                    p90_quantity,
+                    top_gifts,
+                },
+            )
+            .collect())
+    }
+}
+
+async fn fetch_gifts(
+    executor: impl PgExecutor<'_>,
+    id: Option<i32>,
+) -> Result<Vec<Gift>, AppError> {
+    Ok(sqlx::query_as::<_, GiftRow>(
+        r#"
+        SELECT g.id, g.name,
+          COALESCE(
+            ARRAY_AGG(n.name ORDER BY n.name_key COLLATE "C") FILTER (WHERE n.name_key <> gift_name_key(g.name)),
+            '{}'
+          ),
+          g.unit_price_cents, g.category
+        FROM gifts g
+        LEFT JOIN gift_names n ON n.gift_id = g.id
+        WHERE $1::INT IS NULL OR g.id = $1
+        GROUP BY g.id
+        ORDER BY g.name COLLATE "C" ASC, g.id ASC
+        "#,
+    )
+    .bind(id)
+    .fetch_all(executor)
+    .await?
+    .into_iter()
+    .map(|(id, name, aliases, unit_price_cents, category)| Gift {
+        id,
+        name,
+        aliases,
+        unit_price_cents,
+        category,
+    })
+    .collect())
+}
+
+// Stores the names of a gift, once none of them names another gift.
+async fn add_names(conn: &mut PgConnection, id: i32, gift: &NewGift) -> Result<(), AppError> {
+    let names = gift.names();
+    let taken = sqlx::query_as::<_, (String, String)>(
+        r#"
+        SELECT n.name, g.name
+        FROM gift_names n
+        JOIN gifts g ON g.id = n.gift_id
+        WHERE n.name_key IN (SELECT gift_name_key(name) FROM UNNEST($1::TEXT[]) AS name)
+          AND n.gift_id <> $2
+        ORDER BY n.name_key COLLATE "C"
+        LIMIT 1
+        "#,
+    )
+    .bind(&names)
+    .bind(id)
+    .fetch_optional(&mut *conn)
+    .await?;
+    if let Some((name, gift)) = taken {
+        return Err(name_taken(&name, &gift));
+    }
+    sqlx::query("INSERT INTO gift_names (gift_id, name) SELECT $1, UNNEST($2::TEXT[])")
+        .bind(id)
+        .bind(&names)
+        .execute(&mut *conn)
+        .await?;
+
+    // The gift's orders take its name, then the orders matching one of its names join them.
+    sqlx::query("UPDATE orders SET gift_name = $2 WHERE gift_id = $1")
+        .bind(id)
+        .bind(&gift.name)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query(
+        r#"
+        UPDATE orders SET gift_name = gift_name
+        WHERE gift_id IS NULL
+          AND name_key IN (SELECT gift_name_key(name) FROM UNNEST($1::TEXT[]) AS name)
+        "#,
+    )
+    .bind(&names)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+#[async_trait]
+impl GiftRepository for PgRepository {
+    async fn list(&self, tenant: &Tenant) -> Result<Vec<Gift>, AppError> {
+        let mut tx = tenant::begin(&self.pool, tenant).await?;
+        fetch_gifts(&mut *tx, None).await
+    }
+
+    async fn get(&self, tenant: &Tenant, id: i32) -> Result<Gift, AppError> {
+        let mut tx = tenant::begin(&self.pool, tenant).await?;
+        fetch_gifts(&mut *tx, Some(id))
+            .await?
+            .pop()
+            .ok_or_else(|| gift_not_found(id))
+    }
+
+    async fn create(
+        &self,
+        tenant: &Tenant,
+        actor: &Actor,
+        gift: &NewGift,
+    ) -> Result<Gift, AppError> {
+        let mut tx = audit::begin(&self.pool, tenant, actor).await?;
+        let id = sqlx::query_scalar::<_, i32>(
+            "INSERT INTO gifts (name, unit_price_cents, category) VALUES ($1, $2, $3) RETURNING id",
+        )
+        .bind(&gift.name)
+        .bind(gift.unit_price_cents)
+        .bind(&gift.category)
+        .fetch_one(&mut *tx)
+        .await?;
+        add_names(&mut tx, id, gift).await?;
+        let created = fetch_gifts(&mut *tx, Some(id)).await?.pop();
+        tx.commit().await?;
+        created.ok_or_else(|| gift_not_found(id))
+    }
+
+    async fn update(
+        &self,
+        tenant: &Tenant,
+        actor: &Actor,
+        id: i32,
+        gift: &NewGift,
+    ) -> Result<Gift, AppError> {
+        let mut tx = audit::begin(&self.pool, tenant, actor).await?;
+        let updated = sqlx::query(
+            "UPDATE gifts SET name = $2, unit_price_cents = $3, category = $4 WHERE id = $1",
+        )
+        .bind(id)
+        .bind(&gift.name)
+        .bind(gift.unit_price_cents)
+        .bind(&gift.category)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if updated == 0 {
+            return Err(gift_not_found(id));
+        }
+        sqlx::query("DELETE FROM gift_names WHERE gift_id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        add_names(&mut tx, id, gift).await?;
+        let updated = fetch_gifts(&mut *tx, Some(id)).await?.pop();
+        tx.commit().await?;
+        updated.ok_or_else(|| gift_not_found(id))
+    }
+
+    async fn delete(&self, tenant: &Tenant, actor: &Actor, id: i32) -> Result<(), AppError> {
+        let mut tx = audit::begin(&self.pool, tenant, actor).await?;
+        let deleted = sqlx::query("DELETE FROM gifts WHERE id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        if deleted == 0 {
+            return Err(gift_not_found(id));
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn categories(&self, tenant: &Tenant) -> Result<Vec<CategoryTotal>, AppError> {
+        let mut tx = tenant::begin(&self.pool, tenant).await?;
+        Ok(sqlx::query_as::<_, (Option<String>, i64, i64, i64)>(
+            r#"
+            SELECT g.category, COUNT(*), SUM(o.quantity)::BIGINT,
+              SUM(o.quantity * COALESCE(g.unit_price_cents, 0))::BIGINT
+            FROM orders o
+            LEFT JOIN gifts g ON g.id = o.gift_id
+            GROUP BY g.category
+            ORDER BY g.category ASC NULLS LAST
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(
+            |(category, orders, quantity, revenue_cents)| CategoryTotal {
+                category,
+                orders,
+                quantity,
+                revenue_cents,
+            },
+        )
+        .collect())
+    }
+}
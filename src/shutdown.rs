@@ -0,0 +1,116 @@
+use std::{future::Future, net::SocketAddr, pin::pin, time::Duration};
+
+use axum::Router;
+use sqlx::PgPool;
+use tokio::{net::TcpListener, task::JoinHandle};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+use tracing::{info, warn};
+
+#[derive(Clone, Debug, Default)]
+pub struct Shutdown {
+    token: CancellationToken,
+    tracker: TaskTracker,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn trigger(&self) {
+        self.token.cancel();
+        self.tracker.close();
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    pub async fn triggered(&self) {
+        self.token.cancelled().await
+    }
+
+    pub fn track<F: Future>(&self, future: F) -> impl Future<Output = F::Output> {
+        self.tracker.track_future(future)
+    }
+
+    pub fn spawn_blocking<F, T>(&self, task: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        self.tracker.spawn_blocking(task)
+    }
+
+    async fn drained(&self) {
+        self.tracker.wait().await
+    }
+}
+
+async fn signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Ctrl+C handler should install");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("SIGTERM handler should install")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+pub async fn serve(
+    listener: TcpListener,
+    router: Router,
+    shutdown: Shutdown,
+    pool: PgPool,
+    grace_period: Duration,
+) -> std::io::Result<()> {
+    let signal_shutdown = shutdown.clone();
+    tokio::spawn(async move {
+        signal().await;
+        info!("Shutdown signal received");
+        signal_shutdown.trigger();
+    });
+
+    let server_token = shutdown.token.clone();
+    let server = axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(async move { server_token.cancelled_owned().await });
+    let mut drained = pin!(async {
+        server.await?;
+        shutdown.drained().await;
+        Ok::<_, std::io::Error>(())
+    });
+
+    tokio::select! {
+        result = &mut drained => result?,
+        _ = shutdown.triggered() => {
+            match tokio::time::timeout(grace_period, &mut drained).await {
+                Ok(result) => result?,
+                Err(_) => warn!(
+                    "Requests still running after {}s, shutting down anyway",
+                    grace_period.as_secs()
+                ),
+            }
+        }
+    }
+
+    pool.close().await;
+    info!("Shutdown complete");
+    Ok(())
+}
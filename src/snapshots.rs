@@ -0,0 +1,452 @@
+use anyhow::anyhow;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    middleware,
+    routing::{delete, get, post},
+    Extension, Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgExecutor};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    audit::{self, Actor},
+    auth::{authorize, Role, RoleGuard},
+    config::Config,
+    day13::Order,
+    error::AppError,
+    tenant::{self, Tenant},
+    CommonState,
+};
+
+const NAME_MAX_CHARS: usize = 64;
+
+type SnapshotRow = (String, i64, i64, DateTime<Utc>);
+type DiffRow = (
+    Option<i32>,
+    Option<i32>,
+    Option<String>,
+    Option<i32>,
+    Option<i32>,
+    Option<i32>,
+    Option<String>,
+    Option<i32>,
+);
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct Snapshot {
+    pub name: String,
+    pub orders: i64,
+    pub regions: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+fn snapshot((name, orders, regions, created_at): SnapshotRow) -> Snapshot {
+    Snapshot {
+        name,
+        orders,
+        regions,
+        created_at,
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct NewSnapshot {
+    pub name: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DiffParams {
+    // Snapshot to compare with, the current orders when absent.
+    pub to: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct OrderChange {
+    pub before: Order,
+    pub after: Order,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct SnapshotDiff {
+    pub from: String,
+    // Absent when compared with the current orders.
+    pub to: Option<String>,
+    pub added: Vec<Order>,
+    pub removed: Vec<Order>,
+    pub changed: Vec<OrderChange>,
+}
+
+fn check_name(name: &str) -> Result<(), AppError> {
+    let valid = !name.is_empty()
+        && name.chars().count() <= NAME_MAX_CHARS
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(AppError::unprocessable_entity(anyhow!(
+            "Snapshot names have at most {} letters, digits, '-', '_' or '.'",
+            NAME_MAX_CHARS
+        )))
+    }
+}
+
+async fn snapshot_id(executor: impl PgExecutor<'_>, name: &str) -> Result<i32, AppError> {
+    sqlx::query_scalar::<_, i32>("SELECT id FROM snapshots WHERE name = $1")
+        .bind(name)
+        .fetch_optional(executor)
+        .await?
+        .ok_or_else(|| AppError::not_found(anyhow!("No snapshot named {}", name)))
+}
+
+// Copies the current orders and regions under a new name, within the caller's transaction.
+pub(crate) async fn save(conn: &mut PgConnection, name: &str) -> Result<Snapshot, AppError> {
+    check_name(name)?;
+    let id = sqlx::query_scalar::<_, i32>(
+        r#"
+        INSERT INTO snapshots (name, order_count, region_count)
+        VALUES ($1, (SELECT COUNT(*) FROM orders), (SELECT COUNT(*) FROM regions))
+        RETURNING id
+        "#,
+    )
+    .bind(name)
+    .fetch_one(&mut *conn)
+    .await?;
+    sqlx::query(
+        r#"
+        INSERT INTO snapshot_regions (snapshot_id, id, name, parent_id)
+        SELECT $1, id, name, parent_id FROM regions
+        "#,
+    )
+    .bind(id)
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query(
+        r#"
+        INSERT INTO snapshot_orders (snapshot_id, id, region_id, gift_name, quantity)
+        SELECT $1, id, region_id, gift_name, quantity FROM orders
+        "#,
+    )
+    .bind(id)
+    .execute(&mut *conn)
+    .await?;
+    let row = sqlx::query_as::<_, SnapshotRow>(
+        "SELECT name, order_count, region_count, created_at FROM snapshots WHERE id = $1",
+    )
+    .bind(id)
+    .fetch_one(&mut *conn)
+    .await?;
+    Ok(snapshot(row))
+}
+
+// Makes writes to orders and regions wait for the caller's transaction, so that what it saves,
+// clears or restores is not mixed with concurrent changes.
+pub(crate) async fn lock(conn: &mut PgConnection) -> Result<(), AppError> {
+    sqlx::query("LOCK TABLE orders, regions IN EXCLUSIVE MODE")
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+// Deletes the current orders and regions of the tenant, in a way concurrent readers see
+// atomically.
+pub(crate) async fn clear(conn: &mut PgConnection) -> Result<(), AppError> {
+    audit::resetting(&mut *conn, true).await?;
+    sqlx::query("DELETE FROM orders")
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query("DELETE FROM regions")
+        .execute(&mut *conn)
+        .await?;
+    audit::resetting(&mut *conn, false).await?;
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/snapshots",
+    tag = "snapshots",
+    request_body = NewSnapshot,
+    security(("bearer" = [])),
+    responses(
+        (status = 201, description = "Current orders and regions saved", body = Snapshot),
+        (status = 401, description = "Missing or unknown API key", body = ProblemDetails),
+        (status = 403, description = "Admin role required", body = ProblemDetails),
+        (status = 409, description = "A snapshot with the same name already exists", body = ProblemDetails),
+        (status = 422, description = "Invalid snapshot name", body = ProblemDetails)
+    )
+)]
+async fn create_snapshot(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Json(new): Json<NewSnapshot>,
+) -> Result<(StatusCode, Json<Snapshot>), AppError> {
+    // Repeatable read, so that orders and regions are copied as of the same instant.
+    let mut tx = state.pool.begin().await?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ")
+        .execute(&mut *tx)
+        .await?;
+    tenant::enter(&mut tx, &tenant).await?;
+    let snapshot = save(&mut tx, &new.name).await?;
+    tx.commit().await?;
+    Ok((StatusCode::CREATED, Json(snapshot)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/snapshots",
+    tag = "snapshots",
+    security((), ("bearer" = [])),
+    responses(
+        (status = 200, description = "Every snapshot, newest first", body = Vec<Snapshot>),
+        (status = 401, description = "Missing or unknown API key when reads are not public", body = ProblemDetails)
+    )
+)]
+async fn list_snapshots(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+) -> Result<Json<Vec<Snapshot>>, AppError> {
+    let mut tx = tenant::begin(&state.pool, &tenant).await?;
+    let rows = sqlx::query_as::<_, SnapshotRow>(
+        r#"
+        SELECT name, order_count, region_count, created_at FROM snapshots
+        ORDER BY created_at DESC, id DESC
+        "#,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    Ok(Json(rows.into_iter().map(snapshot).collect()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/snapshots/{name}",
+    tag = "snapshots",
+    params(("name" = String, Path, description = "Snapshot name")),
+    security((), ("bearer" = [])),
+    responses(
+        (status = 200, description = "The snapshot", body = Snapshot),
+        (status = 401, description = "Missing or unknown API key when reads are not public", body = ProblemDetails),
+        (status = 404, description = "No snapshot with this name", body = ProblemDetails)
+    )
+)]
+async fn get_snapshot(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Path(name): Path<String>,
+) -> Result<Json<Snapshot>, AppError> {
+    let mut tx = tenant::begin(&state.pool, &tenant).await?;
+    let row = sqlx::query_as::<_, SnapshotRow>(
+        "SELECT name, order_count, region_count, created_at FROM snapshots WHERE name = $1",
+    )
+    .bind(&name)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::not_found(anyhow!("No snapshot named {}", name)))?;
+    Ok(Json(snapshot(row)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/snapshots/{name}",
+    tag = "snapshots",
+    params(("name" = String, Path, description = "Snapshot name")),
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Snapshot deleted"),
+        (status = 401, description = "Missing or unknown API key", body = ProblemDetails),
+        (status = 403, description = "Admin role required", body = ProblemDetails),
+        (status = 404, description = "No snapshot with this name", body = ProblemDetails)
+    )
+)]
+async fn delete_snapshot(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let mut tx = tenant::begin(&state.pool, &tenant).await?;
+    let deleted = sqlx::query("DELETE FROM snapshots WHERE name = $1")
+        .bind(&name)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    if deleted == 0 {
+        return Err(AppError::not_found(anyhow!("No snapshot named {}", name)));
+    }
+    tx.commit().await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/snapshots/{name}/restore",
+    tag = "snapshots",
+    params(("name" = String, Path, description = "Snapshot name")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Orders and regions replaced by the ones of the snapshot", body = Snapshot),
+        (status = 401, description = "Missing or unknown API key", body = ProblemDetails),
+        (status = 403, description = "Admin role required", body = ProblemDetails),
+        (status = 404, description = "No snapshot with this name", body = ProblemDetails)
+    )
+)]
+async fn restore_snapshot(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Extension(actor): Extension<Actor>,
+    Path(name): Path<String>,
+) -> Result<Json<Snapshot>, AppError> {
+    let mut tx = audit::begin(&state.pool, &tenant, &actor).await?;
+    let id = snapshot_id(&mut *tx, &name).await?;
+    lock(&mut tx).await?;
+    clear(&mut tx).await?;
+    sqlx::query(
+        r#"
+        INSERT INTO regions (id, name, parent_id)
+        SELECT id, name, parent_id FROM snapshot_regions WHERE snapshot_id = $1
+        "#,
+    )
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(
+        r#"
+        INSERT INTO orders (id, region_id, gift_name, quantity)
+        SELECT id, region_id, gift_name, quantity FROM snapshot_orders WHERE snapshot_id = $1
+        "#,
+    )
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+    let row = sqlx::query_as::<_, SnapshotRow>(
+        "SELECT name, order_count, region_count, created_at FROM snapshots WHERE id = $1",
+    )
+    .bind(id)
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(Json(snapshot(row)))
+}
+
+fn diff_order(
+    id: Option<i32>,
+    region_id: Option<i32>,
+    gift_name: Option<String>,
+    quantity: Option<i32>,
+) -> Option<Order> {
+    Some(Order {
+        id: id?,
+        region_id: region_id?,
+        gift_name: gift_name?,
+        quantity: quantity?,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/snapshots/{name}/diff",
+    tag = "snapshots",
+    params(("name" = String, Path, description = "Snapshot name"), DiffParams),
+    security((), ("bearer" = [])),
+    responses(
+        (status = 200, description = "Orders added, removed and changed since the snapshot", body = SnapshotDiff),
+        (status = 401, description = "Missing or unknown API key when reads are not public", body = ProblemDetails),
+        (status = 404, description = "No snapshot with this name", body = ProblemDetails)
+    )
+)]
+async fn diff_snapshot(
+    State(state): State<CommonState>,
+    Extension(tenant): Extension<Tenant>,
+    Path(name): Path<String>,
+    Query(params): Query<DiffParams>,
+) -> Result<Json<SnapshotDiff>, AppError> {
+    let mut tx = tenant::begin(&state.pool, &tenant).await?;
+    let from = snapshot_id(&mut *tx, &name).await?;
+    let to = match &params.to {
+        Some(to) => Some(snapshot_id(&mut *tx, to).await?),
+        None => None,
+    };
+    let rows = sqlx::query_as::<_, DiffRow>(
+        r#"
+        WITH before AS (
+          SELECT id, region_id, gift_name, quantity FROM snapshot_orders WHERE snapshot_id = $1
+        ), after AS (
+          SELECT id, region_id, gift_name, quantity FROM snapshot_orders WHERE snapshot_id = $2
+          UNION ALL
+          SELECT id, region_id, gift_name, quantity FROM orders WHERE $2::INT IS NULL
+        )
+        SELECT b.id, b.region_id, b.gift_name, b.quantity, a.id, a.region_id, a.gift_name, a.quantity
+        FROM before b
+        FULL JOIN after a ON a.id = b.id
+        WHERE (b.region_id, b.gift_name, b.quantity) IS DISTINCT FROM (a.region_id, a.gift_name, a.quantity)
+        ORDER BY COALESCE(b.id, a.id)
+        "#,
+    )
+    .bind(from)
+    .bind(to)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut diff = SnapshotDiff {
+        from: name,
+        to: params.to,
+        added: Vec::new(),
+        removed: Vec::new(),
+        changed: Vec::new(),
+    };
+    for (
+        id,
+        region_id,
+        gift_name,
+        quantity,
+        after_id,
+        after_region_id,
+        after_gift_name,
+        after_quantity,
+    ) in rows
+    {
+        let before = diff_order(id, region_id, gift_name, quantity);
+        let after = diff_order(after_id, after_region_id, after_gift_name, after_quantity);
+        match (before, after) {
+            (Some(before), Some(after)) => diff.changed.push(OrderChange { before, after }),
+            (Some(before), None) => diff.removed.push(before),
+            (None, Some(after)) => diff.added.push(after),
+            (None, None) => {}
+        }
+    }
+    Ok(Json(diff))
+}
+
+pub fn get_routes(state: CommonState, config: &Config) -> Router {
+    let reader =
+        middleware::from_fn_with_state(RoleGuard::new(&state, config, Role::Reader), authorize);
+    let admin =
+        middleware::from_fn_with_state(RoleGuard::new(&state, config, Role::Admin), authorize);
+    Router::new()
+        .route(
+            "/snapshots",
+            get(list_snapshots)
+                .route_layer(reader.clone())
+                .merge(post(create_snapshot).route_layer(admin.clone())),
+        )
+        .route(
+            "/snapshots/:name",
+            get(get_snapshot)
+                .route_layer(reader.clone())
+                .merge(delete(delete_snapshot).route_layer(admin.clone())),
+        )
+        .route(
+            "/snapshots/:name/restore",
+            post(restore_snapshot).route_layer(admin),
+        )
+        .route(
+            "/snapshots/:name/diff",
+            get(diff_snapshot).route_layer(reader),
+        )
+        .with_state(state)
+}
@@ -0,0 +1,172 @@
+use std::{sync::OnceLock, time::Instant};
+
+use axum::{
+    body::HttpBody,
+    extract::{MatchedPath, Request},
+    http::{header::CONTENT_LENGTH, HeaderMap, HeaderName, HeaderValue},
+    middleware::{self, Next},
+    response::Response,
+    routing::get,
+    Router,
+};
+use metrics::{counter, describe_counter, describe_gauge, describe_histogram, histogram, Unit};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use tracing::{info, info_span, warn, Instrument};
+use tracing_subscriber::EnvFilter;
+use ulid::Ulid;
+
+use crate::config::LoggingConfig;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+// Longest client-supplied request id that is kept, longer ones are replaced.
+const MAX_REQUEST_ID_LEN: usize = 64;
+
+pub const HTTP_REQUESTS: &str = "http_requests_total";
+pub const HTTP_REQUEST_DURATION: &str = "http_request_duration_seconds";
+pub const CHAT_ACTIVE_ROOMS: &str = "chat_active_rooms";
+pub const CHAT_CONNECTED_SOCKETS: &str = "chat_connected_sockets";
+
+const LATENCY_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+pub fn init_tracing(config: &LoggingConfig) -> anyhow::Result<()> {
+    let filter =
+        EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new(&config.filter))?;
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+    let result = if config.json {
+        subscriber
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .try_init()
+    } else {
+        subscriber.try_init()
+    };
+    result.map_err(|err| anyhow::anyhow!(err))
+}
+
+fn prometheus() -> &'static PrometheusHandle {
+    static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+    HANDLE.get_or_init(|| {
+        let recorder = PrometheusBuilder::new()
+            .set_buckets_for_metric(
+                Matcher::Full(HTTP_REQUEST_DURATION.to_string()),
+                LATENCY_BUCKETS,
+            )
+            .expect("Latency buckets should not be empty")
+            .build_recorder();
+        let handle = recorder.handle();
+        if metrics::set_global_recorder(recorder).is_err() {
+            warn!("A metrics recorder is already installed, /metrics will stay empty");
+        }
+
+        describe_counter!(HTTP_REQUESTS, "Requests served, by route and status");
+        describe_histogram!(
+            HTTP_REQUEST_DURATION,
+            Unit::Seconds,
+            "Time spent serving a request, by route and status"
+        );
+        describe_gauge!(CHAT_ACTIVE_ROOMS, "Chat rooms with at least one member");
+        describe_gauge!(CHAT_CONNECTED_SOCKETS, "Open WebSocket connections");
+        handle
+    })
+}
+
+// Id of the request, echoed in the `x-request-id` response header.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+// The id ends up in logs, response headers and audit rows, so only short ids made of
+// alphanumerics, `-`, `_`, `.` and `:` are taken from the client.
+fn is_valid_request_id(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= MAX_REQUEST_ID_LEN
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b':'))
+}
+
+fn request_id(headers: &HeaderMap) -> String {
+    headers
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| is_valid_request_id(value))
+        .map(str::to_string)
+        .unwrap_or_else(|| Ulid::new().to_string())
+}
+
+fn body_size(headers: &HeaderMap, body: &impl HttpBody) -> u64 {
+    body.size_hint().exact().unwrap_or_else(|| {
+        headers
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(0)
+    })
+}
+
+async fn track_requests(mut request: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let request_id = request_id(request.headers());
+    request
+        .extensions_mut()
+        .insert(RequestId(request_id.clone()));
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let request_bytes = body_size(request.headers(), request.body());
+
+    let span = info_span!("request", %request_id, %method, %route);
+    let mut response = next.run(request).instrument(span.clone()).await;
+
+    let latency = start.elapsed();
+    let status = response.status().as_u16();
+    let response_bytes = body_size(response.headers(), response.body());
+    span.in_scope(|| {
+        info!(
+            status,
+            latency_ms = latency.as_secs_f64() * 1000.0,
+            request_bytes,
+            response_bytes,
+            "request completed"
+        )
+    });
+
+    let labels = [
+        ("method", method),
+        ("route", route),
+        ("status", status.to_string()),
+    ];
+    counter!(HTTP_REQUESTS, &labels).increment(1);
+    histogram!(HTTP_REQUEST_DURATION, &labels).record(latency.as_secs_f64());
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "telemetry",
+    responses((status = 200, description = "Prometheus text exposition of the request and chat metrics", body = String))
+)]
+async fn render_metrics() -> String {
+    prometheus().render()
+}
+
+pub fn get_routes() -> Router {
+    Router::new().route("/metrics", get(render_metrics))
+}
+
+pub fn layer(router: Router) -> Router {
+    prometheus();
+    router.layer(middleware::from_fn(track_requests))
+}
@@ -0,0 +1,99 @@
+use std::fmt;
+
+use anyhow::anyhow;
+use axum::http::HeaderMap;
+use sqlx::{PgConnection, PgPool, Postgres, Transaction};
+
+use crate::error::AppError;
+
+pub const TENANT_HEADER: &str = "x-tenant";
+pub const DEFAULT_TENANT: &str = "default";
+
+const NAME_MAX_CHARS: usize = 63;
+
+// Dataset the orders, regions, gifts and snapshots of a request belong to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Tenant(String);
+
+impl Tenant {
+    pub fn parse(name: &str) -> Result<Self, AppError> {
+        let valid = !name.is_empty()
+            && name.len() <= NAME_MAX_CHARS
+            && name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '_'));
+        if valid {
+            Ok(Self(name.to_string()))
+        } else {
+            Err(AppError::bad_request(anyhow!(
+                "Tenants have at most {} lowercase letters, digits, '-' or '_'",
+                NAME_MAX_CHARS
+            )))
+        }
+    }
+
+    // The tenant named by the `X-Tenant` header, if there is one.
+    pub fn from_headers(headers: &HeaderMap) -> Result<Option<Self>, AppError> {
+        headers
+            .get(TENANT_HEADER)
+            .map(|value| {
+                value
+                    .to_str()
+                    .map_err(|_| AppError::bad_request(anyhow!("Invalid {} header", TENANT_HEADER)))
+                    .and_then(Self::parse)
+            })
+            .transpose()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Default for Tenant {
+    fn default() -> Self {
+        Self(DEFAULT_TENANT.to_string())
+    }
+}
+
+impl fmt::Display for Tenant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// Row-level security only shows the rows of the tenant set here, until the transaction ends.
+pub(crate) async fn enter(conn: &mut PgConnection, tenant: &Tenant) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT set_config('app.tenant', $1, true)")
+        .bind(tenant.as_str())
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+// Every query on the tenant tables runs in a transaction started here.
+pub(crate) async fn begin(
+    pool: &PgPool,
+    tenant: &Tenant,
+) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    enter(&mut tx, tenant).await?;
+    Ok(tx)
+}
+
+// Superusers and roles with BYPASSRLS see every tenant, whatever the policies say, so resets and
+// restores would wipe every tenant. The server refuses to run with such a role.
+pub async fn ensure_isolated(pool: &PgPool) -> anyhow::Result<()> {
+    let (role, bypasses) = sqlx::query_as::<_, (String, bool)>(
+        "SELECT rolname::TEXT, rolsuper OR rolbypassrls FROM pg_roles WHERE rolname = current_user",
+    )
+    .fetch_one(pool)
+    .await?;
+    if bypasses {
+        return Err(anyhow!(
+            "The database role {} bypasses row-level security, connect as a regular role owning the tables so that tenants are isolated",
+            role
+        ));
+    }
+    Ok(())
+}
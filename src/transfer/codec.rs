@@ -0,0 +1,180 @@
+use std::{io, mem};
+
+use axum::{
+    body::Body,
+    http::header::CONTENT_TYPE,
+    response::{IntoResponse, Response},
+};
+use futures::{stream, StreamExt, TryStreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::{postgres::PgRow, FromRow, PgPool};
+use tokio::sync::mpsc;
+use tokio_util::io::{StreamReader, SyncIoBridge};
+use tracing::error;
+
+use super::Format;
+use crate::{
+    error::AppError,
+    shutdown::Shutdown,
+    tenant::{self, Tenant},
+};
+
+// Records handed over at once by the import parser, and rows fetched per export chunk.
+const CHUNK_ROWS: usize = 1000;
+
+pub(crate) struct Encoder {
+    format: Format,
+    rows: usize,
+}
+
+impl Encoder {
+    pub(crate) fn new(format: Format) -> Self {
+        Self { format, rows: 0 }
+    }
+
+    pub(crate) fn row<T: Serialize>(&mut self, row: &T) -> anyhow::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        match self.format {
+            Format::Json => {
+                out.push(if self.rows == 0 { b'[' } else { b',' });
+                serde_json::to_writer(&mut out, row)?;
+            }
+            Format::Ndjson => {
+                serde_json::to_writer(&mut out, row)?;
+                out.push(b'\n');
+            }
+            Format::Csv => {
+                let mut writer = csv::WriterBuilder::new()
+                    .has_headers(self.rows == 0)
+                    .from_writer(out);
+                writer.serialize(row)?;
+                out = writer.into_inner().map_err(|err| err.into_error())?;
+            }
+        }
+        self.rows += 1;
+        Ok(out)
+    }
+
+    pub(crate) fn finish(&self) -> Vec<u8> {
+        match self.format {
+            Format::Json if self.rows == 0 => b"[]".to_vec(),
+            Format::Json => b"]".to_vec(),
+            Format::Ndjson | Format::Csv => Vec::new(),
+        }
+    }
+}
+
+fn response(format: Format, body: Body) -> Response {
+    ([(CONTENT_TYPE, format.content_type())], body).into_response()
+}
+
+// Encodes rows that are already in memory, like the reports.
+pub(crate) fn encode<T: Serialize>(format: Format, rows: &[T]) -> Result<Response, AppError> {
+    let mut encoder = Encoder::new(format);
+    let mut body = Vec::new();
+    for row in rows {
+        body.extend(encoder.row(row)?);
+    }
+    body.extend(encoder.finish());
+    Ok(response(format, Body::from(body)))
+}
+
+// Streams the rows of a query as they are fetched, never holding the whole table.
+pub(crate) fn stream<R, T>(
+    format: Format,
+    pool: PgPool,
+    tenant: Tenant,
+    query: &'static str,
+    map: fn(R) -> T,
+) -> Response
+where
+    R: for<'r> FromRow<'r, PgRow> + Send + Unpin + 'static,
+    T: Serialize + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel::<anyhow::Result<Vec<u8>>>(2);
+    tokio::spawn(async move {
+        let mut encoder = Encoder::new(format);
+        let mut tx = match tenant::begin(&pool, &tenant).await {
+            Ok(tx) => tx,
+            Err(err) => {
+                error!(error = ?err, "export failed");
+                let _ = sender.send(Err(err.into())).await;
+                return;
+            }
+        };
+        let mut rows = sqlx::query_as::<_, R>(query)
+            .fetch(&mut *tx)
+            .map_err(anyhow::Error::from)
+            .chunks(CHUNK_ROWS);
+        while let Some(rows) = rows.next().await {
+            let chunk = rows.into_iter().try_fold(Vec::new(), |mut chunk, row| {
+                chunk.extend(encoder.row(&map(row?))?);
+                anyhow::Ok(chunk)
+            });
+            if let Err(err) = &chunk {
+                error!(error = ?err, "export failed");
+            }
+            let failed = chunk.is_err();
+            if sender.send(chunk).await.is_err() || failed {
+                return;
+            }
+        }
+        let _ = sender.send(Ok(encoder.finish())).await;
+    });
+    let chunks = stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|chunk| (chunk, receiver))
+    });
+    response(format, Body::from_stream(chunks))
+}
+
+// Parses an imported body on a blocking thread, handing its records over in chunks as they are
+// read so that only a couple of chunks are ever held in memory.
+pub(crate) fn records<T>(
+    shutdown: &Shutdown,
+    format: Format,
+    body: Body,
+) -> mpsc::Receiver<Result<Vec<T>, AppError>>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel(2);
+    let reader = SyncIoBridge::new(StreamReader::new(
+        body.into_data_stream().map_err(io::Error::other),
+    ));
+    shutdown.spawn_blocking(move || {
+        let records: Box<dyn Iterator<Item = anyhow::Result<T>>> = match format {
+            Format::Csv => Box::new(
+                csv::ReaderBuilder::new()
+                    .trim(csv::Trim::All)
+                    .from_reader(reader)
+                    .into_deserialize()
+                    .map(|record| record.map_err(anyhow::Error::from)),
+            ),
+            Format::Json | Format::Ndjson => Box::new(
+                serde_json::Deserializer::from_reader(reader)
+                    .into_iter()
+                    .map(|record| record.map_err(anyhow::Error::from)),
+            ),
+        };
+        let mut chunk = Vec::with_capacity(CHUNK_ROWS);
+        for record in records {
+            match record {
+                Ok(record) => chunk.push(record),
+                Err(err) => {
+                    let _ = sender.blocking_send(Err(AppError::bad_request(err)));
+                    return;
+                }
+            }
+            if chunk.len() == CHUNK_ROWS {
+                let full = mem::replace(&mut chunk, Vec::with_capacity(CHUNK_ROWS));
+                if sender.blocking_send(Ok(full)).is_err() {
+                    return;
+                }
+            }
+        }
+        if !chunk.is_empty() {
+            let _ = sender.blocking_send(Ok(chunk));
+        }
+    });
+    receiver
+}
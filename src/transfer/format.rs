@@ -0,0 +1,78 @@
+use anyhow::anyhow;
+use axum::http::{
+    header::{ACCEPT, CONTENT_TYPE},
+    HeaderMap,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    Json,
+    Ndjson,
+    Csv,
+}
+
+impl Format {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Ndjson => "application/x-ndjson",
+            Format::Csv => "text/csv",
+        }
+    }
+
+    fn from_media_type(media_type: &str) -> Option<Self> {
+        match media_type {
+            "application/json" | "application/*" | "*/*" => Some(Format::Json),
+            "application/x-ndjson" | "application/ndjson" => Some(Format::Ndjson),
+            "text/csv" | "text/*" => Some(Format::Csv),
+            _ => None,
+        }
+    }
+
+    // Format preferred by the Accept header, JSON when there is none.
+    pub(crate) fn negotiate(headers: &HeaderMap) -> Result<Self, AppError> {
+        let Some(accept) = headers.get(ACCEPT) else {
+            return Ok(Format::Json);
+        };
+        let mut best: Option<(Format, f32)> = None;
+        for range in accept.to_str()?.split(',') {
+            let mut parts = range.split(';').map(str::trim);
+            let media_type = parts.next().unwrap_or_default().to_ascii_lowercase();
+            let quality = parts
+                .filter_map(|param| param.strip_prefix("q="))
+                .find_map(|quality| quality.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            if let Some(format) = Format::from_media_type(&media_type) {
+                if quality > 0.0 && !best.is_some_and(|(_, best)| quality <= best) {
+                    best = Some((format, quality));
+                }
+            }
+        }
+        best.map(|(format, _)| format).ok_or_else(|| {
+            AppError::not_acceptable(anyhow!(
+                "Only application/json, application/x-ndjson and text/csv can be returned"
+            ))
+        })
+    }
+
+    // Format of an imported body, which has to be read one record at a time.
+    pub(crate) fn of_import(headers: &HeaderMap) -> Result<Self, AppError> {
+        let content_type = headers
+            .get(CONTENT_TYPE)
+            .map(|value| value.to_str())
+            .transpose()?
+            .and_then(|value| value.split(';').next())
+            .map(|media_type| media_type.trim().to_ascii_lowercase());
+        match content_type.as_deref() {
+            Some("application/x-ndjson" | "application/ndjson") => Ok(Format::Ndjson),
+            Some("text/csv") => Ok(Format::Csv),
+            _ => Err(AppError::unsupported_media_type(anyhow!(
+                "Imports are read as application/x-ndjson or text/csv"
+            ))),
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    middleware,
+    response::Response,
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+
+use crate::{
+    audit::{self, Actor},
+    auth::{authorize, Role, RoleGuard},
+    config::Config,
+    day13::{IngestParams, IngestReport, Order, OrderIngest},
+    day18::{region_id, Region},
+    error::AppError,
+    orders::{order, OrderRow},
+    shutdown::Shutdown,
+    tenant::Tenant,
+    CommonState,
+};
+
+mod codec;
+mod format;
+
+pub(crate) use self::codec::encode;
+use self::codec::{records, stream};
+pub use self::format::Format;
+
+#[derive(Clone)]
+struct TransferState {
+    pool: PgPool,
+    shutdown: Shutdown,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct RegionImportReport {
+    pub inserted: u64,
+}
+
+// Exported regions always carry a parent_id column, CSV rows can't leave it out.
+#[derive(Serialize)]
+struct RegionRecord {
+    id: i64,
+    name: String,
+    parent_id: Option<i64>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/orders/import",
+    tag = "transfer",
+    params(IngestParams),
+    request_body(content = String, content_type = "text/csv", description = "Orders as CSV with an `id,region_id,gift_name,quantity` header, or as application/x-ndjson"),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Orders stored, with the ones that were rejected", body = IngestReport),
+        (status = 400, description = "Malformed record, nothing stored", body = ProblemDetails),
+        (status = 401, description = "Missing or unknown API key", body = ProblemDetails),
+        (status = 403, description = "Writer role required", body = ProblemDetails),
+        (status = 415, description = "Body is neither CSV nor NDJSON", body = ProblemDetails),
+        (status = 422, description = "Nothing stored because an order was rejected", body = IngestReport)
+    )
+)]
+async fn import_orders(
+    State(state): State<TransferState>,
+    Extension(tenant): Extension<Tenant>,
+    Extension(actor): Extension<Actor>,
+    Query(params): Query<IngestParams>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<(StatusCode, Json<IngestReport>), AppError> {
+    let format = Format::of_import(&headers)?;
+    let mut chunks = records::<Order>(&state.shutdown, format, body);
+    let mut ingest = OrderIngest::begin(&state.pool, &tenant, &actor, params.mode).await?;
+    while let Some(orders) = chunks.recv().await {
+        ingest.add(&orders?).await?;
+    }
+    let report = ingest.finish().await?;
+    Ok((report.status(), Json(report)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/regions/import",
+    tag = "transfer",
+    request_body(content = String, content_type = "text/csv", description = "Regions as CSV with an `id,name,parent_id` header, or as application/x-ndjson, parents first"),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Regions stored", body = RegionImportReport),
+        (status = 400, description = "Malformed record, nothing stored", body = ProblemDetails),
+        (status = 401, description = "Missing or unknown API key", body = ProblemDetails),
+        (status = 403, description = "Writer role required", body = ProblemDetails),
+        (status = 409, description = "A region with the same id already exists, nothing stored", body = ProblemDetails),
+        (status = 415, description = "Body is neither CSV nor NDJSON", body = ProblemDetails),
+        (status = 422, description = "A parent region does not exist or an id is out of range, nothing stored", body = ProblemDetails)
+    )
+)]
+async fn import_regions(
+    State(state): State<TransferState>,
+    Extension(tenant): Extension<Tenant>,
+    Extension(actor): Extension<Actor>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Json<RegionImportReport>, AppError> {
+    let format = Format::of_import(&headers)?;
+    let mut chunks = records::<Region>(&state.shutdown, format, body);
+    let mut tx = audit::begin(&state.pool, &tenant, &actor).await?;
+    let mut inserted = 0;
+    while let Some(regions) = chunks.recv().await {
+        let regions = regions?;
+        inserted += sqlx::query(
+            r#"
+            INSERT INTO regions (id, name, parent_id)
+            SELECT * FROM UNNEST($1::INT[], $2::VARCHAR[], $3::INT[])
+            "#,
+        )
+        .bind(
+            regions
+                .iter()
+                .map(|r| region_id(r.id))
+                .collect::<Result<Vec<_>, _>>()?,
+        )
+        .bind(regions.iter().map(|r| r.name.clone()).collect::<Vec<_>>())
+        .bind(
+            regions
+                .iter()
+                .map(|r| r.parent_id.map(region_id).transpose())
+                .collect::<Result<Vec<_>, _>>()?,
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    }
+    tx.commit().await?;
+    Ok(Json(RegionImportReport { inserted }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/orders/export",
+    tag = "transfer",
+    security((), ("bearer" = [])),
+    responses(
+        (status = 200, description = "Every order, by id", content(
+            ("application/json" = Vec<Order>),
+            ("application/x-ndjson" = Order),
+            ("text/csv" = String)
+        )),
+        (status = 401, description = "Missing or unknown API key when reads are not public", body = ProblemDetails),
+        (status = 406, description = "None of the accepted formats can be returned", body = ProblemDetails)
+    )
+)]
+async fn export_orders(
+    State(state): State<TransferState>,
+    Extension(tenant): Extension<Tenant>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let format = Format::negotiate(&headers)?;
+    Ok(stream::<OrderRow, _>(
+        format,
+        state.pool,
+        tenant,
+        "SELECT id, region_id, gift_name, quantity FROM orders ORDER BY id",
+        order,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/regions/export",
+    tag = "transfer",
+    security((), ("bearer" = [])),
+    responses(
+        (status = 200, description = "Every region, parents before their children", content(
+            ("application/json" = Vec<Region>),
+            ("application/x-ndjson" = Region),
+            ("text/csv" = String)
+        )),
+        (status = 401, description = "Missing or unknown API key when reads are not public", body = ProblemDetails),
+        (status = 406, description = "None of the accepted formats can be returned", body = ProblemDetails)
+    )
+)]
+async fn export_regions(
+    State(state): State<TransferState>,
+    Extension(tenant): Extension<Tenant>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let format = Format::negotiate(&headers)?;
+    // Ordered by depth so that the export can be imported back as is.
+    Ok(stream::<(i32, String, Option<i32>), _>(
+        format,
+        state.pool,
+        tenant,
+        r#"
+        WITH RECURSIVE tree AS (
+          SELECT id, name, parent_id, 0 AS depth FROM regions WHERE parent_id IS NULL
+          UNION ALL
+          SELECT r.id, r.name, r.parent_id, t.depth + 1
+          FROM regions r JOIN tree t ON r.parent_id = t.id
+        )
+        SELECT id, name, parent_id FROM tree ORDER BY depth, id
+        "#,
+        |(id, name, parent_id)| RegionRecord {
+            id: id.into(),
+            name,
+            parent_id: parent_id.map(Into::into),
+        },
+    ))
+}
+
+pub fn get_routes(state: CommonState, config: &Config, shutdown: &Shutdown) -> Router {
+    let reader =
+        middleware::from_fn_with_state(RoleGuard::new(&state, config, Role::Reader), authorize);
+    let writer =
+        middleware::from_fn_with_state(RoleGuard::new(&state, config, Role::Writer), authorize);
+    Router::new()
+        .route(
+            "/orders/import",
+            post(import_orders).route_layer(writer.clone()),
+        )
+        .route("/regions/import", post(import_regions).route_layer(writer))
+        .route(
+            "/orders/export",
+            get(export_orders).route_layer(reader.clone()),
+        )
+        .route("/regions/export", get(export_regions).route_layer(reader))
+        .with_state(TransferState {
+            pool: state.pool,
+            shutdown: shutdown.clone(),
+        })
+}
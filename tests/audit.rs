@@ -0,0 +1,156 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::{json, Value};
+
+async fn create_key(app: &TestApp, name: &str, role: &str) -> String {
+    let created = app
+        .post("/admin/api-keys")
+        .admin()
+        .json(&json!({ "name": name, "role": role }))
+        .send()
+        .await;
+    created.assert_status(StatusCode::CREATED);
+    created.json::<Value>()["key"].as_str().unwrap().to_string()
+}
+
+async fn entries(app: &TestApp, query: &str) -> Value {
+    let response = app.get(&format!("/audit{}", query)).admin().send().await;
+    response.assert_status(StatusCode::OK);
+    response.json::<Value>()
+}
+
+#[tokio::test]
+async fn logs_every_change_with_its_actor() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    let key = create_key(&app, "elf", "writer").await;
+    app.post("/18/regions")
+        .bearer(&key)
+        .header("x-request-id", "add-regions")
+        .json(&json!([{ "id": 1, "name": "North Pole" }]))
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    app.post("/13/orders")
+        .bearer(&key)
+        .header("x-request-id", "add-orders")
+        .json(&json!([{ "id": 1, "region_id": 1, "gift_name": "Sled", "quantity": 3 }]))
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    app.patch("/orders/1")
+        .bearer(&key)
+        .json(&json!({ "quantity": 4 }))
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    app.post("/13/reset")
+        .admin()
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+
+    let page = entries(&app, "?order_id=1").await;
+    let history = page["entries"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| {
+            (
+                entry["operation"].as_str().unwrap(),
+                entry["actor"].as_str().unwrap(),
+                entry["before"]["quantity"].clone(),
+                entry["after"]["quantity"].clone(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        history,
+        [
+            ("insert", "elf", Value::Null, json!(3)),
+            ("update", "elf", json!(3), json!(4)),
+            ("reset", "bootstrap", json!(4), Value::Null),
+        ]
+    );
+    assert_eq!(page["entries"][0]["request_id"], "add-orders");
+    assert_eq!(page["entries"][0]["entity"], "order");
+    assert!(page.get("next_cursor").is_none());
+
+    let regions = entries(&app, "?region_id=1").await;
+    assert_eq!(regions["entries"][0]["operation"], "insert");
+    assert_eq!(regions["entries"][0]["request_id"], "add-regions");
+    assert_eq!(regions["entries"][0]["after"]["name"], "North Pole");
+    assert_eq!(regions["entries"][1]["operation"], "reset");
+}
+
+#[tokio::test]
+async fn pages_through_a_time_range() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    app.post("/18/regions")
+        .admin()
+        .json(&json!([
+            { "id": 1, "name": "North Pole" },
+            { "id": 2, "name": "Lapland" },
+            { "id": 3, "name": "Greenland" }
+        ]))
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+
+    let first = entries(&app, "?limit=2").await;
+    assert_eq!(first["entries"].as_array().unwrap().len(), 2);
+    let cursor = first["next_cursor"].as_i64().unwrap();
+    let second = entries(&app, &format!("?limit=2&cursor={}", cursor)).await;
+    assert_eq!(second["entries"].as_array().unwrap().len(), 1);
+    assert_eq!(second["entries"][0]["entity_id"], 3);
+    assert!(second.get("next_cursor").is_none());
+
+    let before = entries(&app, "?to=2000-01-01T00:00:00Z").await;
+    assert_eq!(before["entries"], json!([]));
+    let since = entries(&app, "?from=2000-01-01T00:00:00Z").await;
+    assert_eq!(since["entries"].as_array().unwrap().len(), 3);
+
+    app.get("/audit?order_id=1&region_id=1")
+        .admin()
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    let key = create_key(&app, "elf", "writer").await;
+    app.get("/audit")
+        .bearer(&key)
+        .send()
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn the_log_is_append_only() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    app.post("/18/regions")
+        .admin()
+        .json(&json!([{ "id": 1, "name": "North Pole" }]))
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+
+    let mut connection = app.tenant_connection("default").await;
+    for statement in [
+        "UPDATE audit_log SET actor = 'grinch'",
+        "DELETE FROM audit_log",
+        "TRUNCATE audit_log",
+    ] {
+        sqlx::query(statement)
+            .execute(&mut connection)
+            .await
+            .unwrap_err();
+    }
+    let page = entries(&app, "").await;
+    assert_eq!(page["entries"][0]["actor"], "bootstrap");
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::{json, Value};
+
+#[tokio::test]
+async fn manages_api_keys() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+
+    let created = app
+        .post("/admin/api-keys")
+        .admin()
+        .json(&json!({ "name": "reindeer", "role": "reader" }))
+        .send()
+        .await;
+    created.assert_status(StatusCode::CREATED);
+    let created = created.json::<Value>();
+    assert_eq!(created["name"], "reindeer");
+    assert_eq!(created["role"], "reader");
+    let key = created["key"].as_str().unwrap();
+    assert!(key.starts_with("cch23_"));
+
+    let keys = app.get("/admin/api-keys").admin().send().await;
+    keys.assert_status(StatusCode::OK);
+    let keys = keys.json::<Value>();
+    let names = keys
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|key| key["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["bootstrap", "reindeer"]);
+
+    app.get("/admin/api-keys")
+        .bearer(key)
+        .send()
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    let uri = format!("/admin/api-keys/{}", created["id"]);
+    app.delete(&uri)
+        .admin()
+        .send()
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+    app.delete(&uri)
+        .admin()
+        .send()
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    app.get("/admin/api-keys")
+        .bearer(key)
+        .send()
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn rejects_requests_without_a_key() {
+    let app = TestApp::new();
+
+    let response = app.get("/admin/api-keys").send().await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+    assert_eq!(response.headers["www-authenticate"], "Bearer");
+}
@@ -0,0 +1,33 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+
+#[tokio::test]
+async fn cubes_the_xor_of_the_packet_ids() {
+    let app = TestApp::new();
+
+    let response = app.get("/1/4/8").send().await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(response.text(), "1728");
+
+    assert_eq!(app.get("/1/10").send().await.text(), "1000");
+    assert_eq!(app.get("/1/4/5/8/10").send().await.text(), "27");
+}
+
+#[tokio::test]
+async fn ignores_segments_that_are_not_numbers() {
+    let app = TestApp::new();
+
+    assert_eq!(app.get("/1/4/elf/8").send().await.text(), "1728");
+}
+
+#[tokio::test]
+async fn rejects_a_cube_that_overflows() {
+    let app = TestApp::new();
+
+    app.get("/1/2000000")
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
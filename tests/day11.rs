@@ -0,0 +1,64 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+
+const BOUNDARY: &str = "cch23-boundary";
+
+fn multipart(name: &str, filename: &str, content: &[u8]) -> Vec<u8> {
+    let mut body = format!(
+        "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"{name}\"; filename=\"{filename}\"\r\nContent-Type: image/png\r\n\r\n"
+    )
+    .into_bytes();
+    body.extend_from_slice(content);
+    body.extend_from_slice(format!("\r\n--{BOUNDARY}--\r\n").as_bytes());
+    body
+}
+
+#[tokio::test]
+async fn serves_the_assets() {
+    let app = TestApp::new();
+
+    let response = app.get("/11/assets/decoration.png").send().await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(response.headers["content-type"], "image/png");
+    assert_eq!(response.body.len(), 787297);
+}
+
+#[tokio::test]
+async fn counts_the_magical_red_pixels() {
+    let app = TestApp::new();
+    let image = std::fs::read("resources/decoration.png").expect("Asset should exist");
+
+    let response = app
+        .post("/11/red_pixels")
+        .header(
+            "content-type",
+            &format!("multipart/form-data; boundary={BOUNDARY}"),
+        )
+        .body(multipart("image", "decoration.png", &image))
+        .send()
+        .await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(response.text(), "73034");
+}
+
+#[tokio::test]
+async fn rejects_a_body_that_is_not_an_image() {
+    let app = TestApp::new();
+
+    app.post("/11/red_pixels")
+        .body("not multipart")
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    app.post("/11/red_pixels")
+        .header(
+            "content-type",
+            &format!("multipart/form-data; boundary={BOUNDARY}"),
+        )
+        .body(multipart("image", "decoration.png", b"not a png"))
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
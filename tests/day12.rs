@@ -0,0 +1,84 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::{json, Value};
+
+#[tokio::test]
+async fn times_saved_packets() {
+    let app = TestApp::new();
+
+    app.post("/12/save/packet20231212")
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    let response = app.get("/12/load/packet20231212").send().await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(response.text(), "0");
+
+    assert_eq!(app.get("/12/load/unknown").send().await.text(), "0");
+}
+
+#[tokio::test]
+async fn converts_ulids_to_uuids() {
+    let app = TestApp::new();
+    let ulids = json!([
+        "01BJQ0E1C3Z56ABCD0E11HYX4M",
+        "01BJQ0E1C3Z56ABCD0E11HYX5N",
+        "01BJQ0E1C3Z56ABCD0E11HYX6Q",
+        "01BJQ0E1C3Z56ABCD0E11HYX7R",
+        "01BJQ0E1C3Z56ABCD0E11HYX8P"
+    ]);
+
+    let response = app.post("/12/ulids").json(&ulids).send().await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(
+        response.json::<Value>(),
+        json!([
+            "015cae07-0583-f94c-a5b1-a070431f7516",
+            "015cae07-0583-f94c-a5b1-a070431f74f8",
+            "015cae07-0583-f94c-a5b1-a070431f74d7",
+            "015cae07-0583-f94c-a5b1-a070431f74b5",
+            "015cae07-0583-f94c-a5b1-a070431f7494"
+        ])
+    );
+}
+
+#[tokio::test]
+async fn counts_ulids_by_date() {
+    let app = TestApp::new();
+    let ulids = json!([
+        "00WEGGF0G0J5HEYXS3D7RWZGV8",
+        "76EP4G39R8JD1N8AQNYDVJBRCF",
+        "018CJ7KMG0051CDCS3B7BFJ3AK",
+        "00Y986KPG0AMGB78RD45E9109K",
+        "010451HTG0NYWMPWCEXG6AJ8F2",
+        "01HH9SJEG0KY16H81S3N1BMXM4",
+        "01HH9SJEG0P9M22Z9VGHH9C8CX",
+        "017F8YY0G0NQA16HHC2QT5JD6X",
+        "03QCPC7P003V1NND3B3QJW72QJ"
+    ]);
+
+    let response = app.post("/12/ulids/5").json(&ulids).send().await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(
+        response.json::<Value>(),
+        json!({
+            "christmas eve": 3,
+            "weekday": 1,
+            "in the future": 2,
+            "LSB is 1": 5
+        })
+    );
+}
+
+#[tokio::test]
+async fn rejects_an_invalid_weekday() {
+    let app = TestApp::new();
+
+    app.post("/12/ulids/friday")
+        .json(&json!([]))
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,392 @@
+mod common;
+
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use futures::StreamExt;
+use serde_json::{json, Value};
+use tokio::{net::TcpStream, time::timeout};
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn orders() -> Value {
+    json!([
+        { "id": 1, "region_id": 2, "gift_name": "Toy Train", "quantity": 5 },
+        { "id": 2, "region_id": 2, "gift_name": "Doll", "quantity": 8 },
+        { "id": 3, "region_id": 3, "gift_name": "Action Figure", "quantity": 12 },
+        { "id": 4, "region_id": 4, "gift_name": "Board Game", "quantity": 10 },
+        { "id": 5, "region_id": 2, "gift_name": "Teddy Bear", "quantity": 6 },
+        { "id": 6, "region_id": 3, "gift_name": "Toy Train", "quantity": 3 }
+    ])
+}
+
+async fn seed_regions(app: &TestApp) {
+    let regions = json!([
+        { "id": 1, "name": "North Pole" },
+        { "id": 2, "name": "Europe" },
+        { "id": 3, "name": "North America" },
+        { "id": 4, "name": "South America" }
+    ]);
+    app.post("/18/regions")
+        .admin()
+        .json(&regions)
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+}
+
+#[tokio::test]
+async fn runs_a_query() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+
+    let response = app.get("/13/sql").send().await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(response.text(), "20231213");
+}
+
+#[tokio::test]
+async fn aggregates_the_orders() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+
+    seed_regions(&app).await;
+    let empty = app.get("/13/orders/popular").send().await;
+    assert_eq!(empty.json::<Value>(), json!({ "popular": null }));
+
+    app.post("/13/orders")
+        .admin()
+        .json(&orders())
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+
+    let total = app.get("/13/orders/total").send().await;
+    total.assert_status(StatusCode::OK);
+    assert_eq!(total.json::<Value>(), json!({ "total": 44 }));
+    let popular = app.get("/13/orders/popular").send().await;
+    assert_eq!(
+        popular.json::<Value>(),
+        json!({ "popular": "Action Figure" })
+    );
+
+    app.post("/13/reset")
+        .admin()
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    let total = app.get("/13/orders/total").send().await;
+    assert_eq!(total.json::<Value>(), json!({ "total": 0 }));
+}
+
+#[tokio::test]
+async fn stores_nothing_when_an_order_is_rejected() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed_regions(&app).await;
+    let batch = json!([
+        { "id": 1, "region_id": 1, "gift_name": "Doll", "quantity": 1 },
+        { "id": 2, "region_id": 9, "gift_name": "Doll", "quantity": 1 },
+        { "id": 3, "region_id": 1, "gift_name": "Doll", "quantity": -4 },
+        { "id": 1, "region_id": 1, "gift_name": "Doll", "quantity": 2 }
+    ]);
+
+    let response = app.post("/13/orders").admin().json(&batch).send().await;
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        response.json::<Value>(),
+        json!({
+            "mode": "all_or_nothing",
+            "committed": false,
+            "inserted": 0,
+            "updated": 0,
+            "rejected": [
+                { "index": 1, "id": 2, "reason": "unknown_region" },
+                { "index": 2, "id": 3, "reason": "negative_quantity" },
+                { "index": 3, "id": 1, "reason": "duplicate_in_batch" }
+            ]
+        })
+    );
+    let total = app.get("/13/orders/total").send().await;
+    assert_eq!(total.json::<Value>(), json!({ "total": 0 }));
+}
+
+#[tokio::test]
+async fn rejects_orders_that_already_exist() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed_regions(&app).await;
+    let order = json!([{ "id": 1, "region_id": 1, "gift_name": "Doll", "quantity": 1 }]);
+
+    app.post("/13/orders")
+        .admin()
+        .json(&order)
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    let response = app.post("/13/orders").admin().json(&order).send().await;
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    let report = response.json::<Value>();
+    assert_eq!(
+        report["rejected"],
+        json!([{ "index": 0, "id": 1, "reason": "duplicate_id" }])
+    );
+}
+
+#[tokio::test]
+async fn skips_invalid_orders() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed_regions(&app).await;
+    let batch = json!([
+        { "id": 1, "region_id": 1, "gift_name": "Doll", "quantity": 3 },
+        { "id": 2, "region_id": 9, "gift_name": "Doll", "quantity": 1 },
+        { "id": 3, "region_id": 2, "gift_name": "Toy Train", "quantity": 4 }
+    ]);
+
+    let response = app
+        .post("/13/orders?mode=skip_invalid")
+        .admin()
+        .json(&batch)
+        .send()
+        .await;
+    response.assert_status(StatusCode::OK);
+    let report = response.json::<Value>();
+    assert_eq!(report["committed"], true);
+    assert_eq!(report["inserted"], 2);
+    assert_eq!(
+        report["rejected"],
+        json!([{ "index": 1, "id": 2, "reason": "unknown_region" }])
+    );
+    let total = app.get("/13/orders/total").send().await;
+    assert_eq!(total.json::<Value>(), json!({ "total": 7 }));
+}
+
+#[tokio::test]
+async fn upserts_existing_orders() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed_regions(&app).await;
+
+    app.post("/13/orders")
+        .admin()
+        .json(&orders())
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    let batch = json!([
+        { "id": 3, "region_id": 3, "gift_name": "Action Figure", "quantity": 1 },
+        { "id": 7, "region_id": 2, "gift_name": "Doll", "quantity": 20 }
+    ]);
+    let response = app
+        .post("/13/orders?mode=upsert")
+        .admin()
+        .json(&batch)
+        .send()
+        .await;
+    response.assert_status(StatusCode::OK);
+    let report = response.json::<Value>();
+    assert_eq!(
+        (&report["inserted"], &report["updated"]),
+        (&json!(1), &json!(1))
+    );
+
+    let popular = app.get("/13/orders/popular").send().await;
+    assert_eq!(popular.json::<Value>(), json!({ "popular": "Doll" }));
+}
+
+#[tokio::test]
+async fn requires_a_key_to_write() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed_regions(&app).await;
+
+    app.post("/13/orders")
+        .json(&orders())
+        .send()
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+    app.post("/13/reset")
+        .bearer("not-a-key")
+        .send()
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    let writer = app
+        .post("/admin/api-keys")
+        .admin()
+        .json(&json!({ "name": "elves", "role": "writer" }))
+        .send()
+        .await
+        .json::<Value>();
+    let writer = writer["key"].as_str().unwrap();
+    app.post("/13/orders")
+        .bearer(writer)
+        .json(&orders())
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    app.post("/13/reset")
+        .bearer(writer)
+        .send()
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+}
+
+// Reads server-sent events off the response until the next one carrying data.
+async fn next_event(response: &mut reqwest::Response, buffer: &mut String) -> Value {
+    loop {
+        if let Some(end) = buffer.find("\n\n") {
+            let frame = buffer[..end].to_string();
+            buffer.drain(..end + 2);
+            if let Some(data) = frame.lines().find_map(|line| line.strip_prefix("data: ")) {
+                assert!(frame.lines().any(|line| line == "event: orders"));
+                return serde_json::from_str(data).expect("Event should be JSON");
+            }
+            continue;
+        }
+        let chunk = timeout(RECEIVE_TIMEOUT, response.chunk())
+            .await
+            .expect("Event should arrive in time")
+            .expect("Stream should be readable")
+            .expect("Stream should stay open");
+        buffer.push_str(std::str::from_utf8(&chunk).expect("Events should be UTF-8"));
+    }
+}
+
+#[tokio::test]
+async fn streams_stored_orders_as_server_sent_events() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed_regions(&app).await;
+    let addr = app.serve().await;
+
+    let mut response = reqwest::get(format!("http://{}/13/orders/feed", addr))
+        .await
+        .expect("Feed should be reachable");
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/event-stream");
+    let mut buffer = String::new();
+    assert_eq!(
+        next_event(&mut response, &mut buffer).await,
+        json!({ "orders": [], "total": 0, "popular": null })
+    );
+
+    // A rolled back batch is never announced.
+    app.post("/13/orders")
+        .admin()
+        .json(&json!([
+            { "id": 1, "region_id": 2, "gift_name": "Toy Train", "quantity": 5 },
+            { "id": 2, "region_id": 9, "gift_name": "Doll", "quantity": 8 }
+        ]))
+        .send()
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    app.post("/13/orders")
+        .admin()
+        .json(&json!([
+            { "id": 3, "region_id": 2, "gift_name": "Doll", "quantity": 8 },
+            { "id": 4, "region_id": 3, "gift_name": "Toy Train", "quantity": 5 }
+        ]))
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    assert_eq!(
+        next_event(&mut response, &mut buffer).await,
+        json!({
+            "orders": [
+                { "id": 3, "region_id": 2, "gift_name": "Doll", "quantity": 8 },
+                { "id": 4, "region_id": 3, "gift_name": "Toy Train", "quantity": 5 }
+            ],
+            "total": 13,
+            "popular": "Doll"
+        })
+    );
+
+    app.post("/18/orders")
+        .admin()
+        .json(&json!([{ "id": 5, "region_id": 4, "gift_name": "Toy Train", "quantity": 6 }]))
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    let event = next_event(&mut response, &mut buffer).await;
+    assert_eq!(event["total"], 19);
+    assert_eq!(event["popular"], "Toy Train");
+
+    // On a tie the feed names the same gift as /13/orders/popular, the first by name.
+    app.post("/13/orders")
+        .admin()
+        .json(&json!([{ "id": 6, "region_id": 2, "gift_name": "Doll", "quantity": 3 }]))
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    let event = next_event(&mut response, &mut buffer).await;
+    assert_eq!(event["total"], 22);
+    assert_eq!(event["popular"], "Doll");
+    assert_eq!(
+        app.get("/13/orders/popular").send().await.json::<Value>(),
+        json!({ "popular": "Doll" })
+    );
+}
+
+async fn next_message(socket: &mut Socket) -> Value {
+    loop {
+        match timeout(RECEIVE_TIMEOUT, socket.next()).await {
+            Ok(Some(Ok(Message::Text(text)))) => {
+                return serde_json::from_str(&text).expect("Event should be JSON")
+            }
+            Ok(Some(Ok(Message::Ping(_) | Message::Pong(_)))) => continue,
+            other => panic!("Event should arrive, got {:?}", other),
+        }
+    }
+}
+
+#[tokio::test]
+async fn streams_stored_orders_over_a_websocket() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed_regions(&app).await;
+    app.post("/13/orders")
+        .admin()
+        .json(&orders())
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    let addr = app.serve().await;
+
+    let (mut socket, _) = connect_async(format!("ws://{}/13/orders/feed/ws", addr))
+        .await
+        .expect("WebSocket should connect");
+    assert_eq!(
+        next_message(&mut socket).await,
+        json!({ "orders": [], "total": 44, "popular": "Action Figure" })
+    );
+
+    app.post("/13/orders?mode=upsert")
+        .admin()
+        .json(&json!([{ "id": 2, "region_id": 2, "gift_name": "Doll", "quantity": 20 }]))
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    assert_eq!(
+        next_message(&mut socket).await,
+        json!({
+            "orders": [{ "id": 2, "region_id": 2, "gift_name": "Doll", "quantity": 20 }],
+            "total": 56,
+            "popular": "Doll"
+        })
+    );
+}
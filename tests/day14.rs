@@ -0,0 +1,43 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::json;
+
+const CONTENT: &str = "<h1>Welcome to the North Pole!</h1>";
+
+fn page(content: &str) -> String {
+    format!(
+        "<html>\n  <head>\n    <title>CCH23 Day 14</title>\n  </head>\n  <body>\n    {}\n  </body>\n</html>",
+        content
+    )
+}
+
+#[tokio::test]
+async fn renders_the_content_as_is() {
+    let app = TestApp::new();
+
+    let response = app
+        .post("/14/unsafe")
+        .json(&json!({ "content": CONTENT }))
+        .send()
+        .await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(response.text(), page(CONTENT));
+}
+
+#[tokio::test]
+async fn escapes_the_content() {
+    let app = TestApp::new();
+
+    let response = app
+        .post("/14/safe")
+        .json(&json!({ "content": "<script>alert(\"XSS Attack!\")</script>" }))
+        .send()
+        .await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(
+        response.text(),
+        page("&lt;script&gt;alert(&quot;XSS Attack!&quot;)&lt;/script&gt;")
+    );
+}
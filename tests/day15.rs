@@ -0,0 +1,61 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::{json, Value};
+
+async fn check(app: &TestApp, route: &str, input: &str) -> (StatusCode, Value) {
+    let response = app
+        .post(route)
+        .json(&json!({ "input": input }))
+        .send()
+        .await;
+    (response.status, response.json())
+}
+
+#[tokio::test]
+async fn tells_nice_from_naughty_strings() {
+    let app = TestApp::new();
+
+    assert_eq!(
+        check(&app, "/15/nice", "hello there").await,
+        (StatusCode::OK, json!({ "result": "nice" }))
+    );
+    for naughty in ["abcd", "hello xylophone", "abcdefghijklmnopqrstuvwxyz"] {
+        assert_eq!(
+            check(&app, "/15/nice", naughty).await,
+            (StatusCode::BAD_REQUEST, json!({ "result": "naughty" })),
+            "{} should be naughty",
+            naughty
+        );
+    }
+}
+
+#[tokio::test]
+async fn explains_which_rule_a_password_breaks() {
+    let app = TestApp::new();
+    let cases = [
+        ("pass", StatusCode::BAD_REQUEST, "8 chars"),
+        (
+            "passwordpassword",
+            StatusCode::BAD_REQUEST,
+            "more types of chars",
+        ),
+        ("Password12", StatusCode::BAD_REQUEST, "55555"),
+        ("Password12345", StatusCode::BAD_REQUEST, "math is hard"),
+        (
+            "Password2000and23x",
+            StatusCode::NOT_ACCEPTABLE,
+            "not joyful enough",
+        ),
+    ];
+
+    for (input, status, reason) in cases {
+        assert_eq!(
+            check(&app, "/15/game", input).await,
+            (status, json!({ "result": "naughty", "reason": reason })),
+            "unexpected result for {}",
+            input
+        );
+    }
+}
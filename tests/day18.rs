@@ -0,0 +1,355 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::{json, Value};
+
+async fn seed(app: &TestApp) {
+    let regions = json!([
+        { "id": 1, "name": "North Pole" },
+        { "id": 2, "name": "Europe" },
+        { "id": 3, "name": "North America" },
+        { "id": 4, "name": "South America" },
+        { "id": 5, "name": "Africa" },
+        { "id": 6, "name": "Asia" },
+        { "id": 7, "name": "Oceania" }
+    ]);
+    let orders = json!([
+        { "id": 1, "region_id": 2, "gift_name": "Board Game", "quantity": 5 },
+        { "id": 2, "region_id": 2, "gift_name": "Origami Set", "quantity": 8 },
+        { "id": 3, "region_id": 3, "gift_name": "Action Figure", "quantity": 12 },
+        { "id": 4, "region_id": 4, "gift_name": "Teddy Bear", "quantity": 10 },
+        { "id": 5, "region_id": 2, "gift_name": "Yarn Ball", "quantity": 6 },
+        { "id": 6, "region_id": 3, "gift_name": "Art Set", "quantity": 3 },
+        { "id": 7, "region_id": 5, "gift_name": "Robot Lego Kit", "quantity": 5 },
+        { "id": 8, "region_id": 6, "gift_name": "Drone", "quantity": 9 }
+    ]);
+
+    app.post("/18/reset")
+        .admin()
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    app.post("/18/regions")
+        .admin()
+        .json(&regions)
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    app.post("/18/orders")
+        .admin()
+        .json(&orders)
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+}
+
+#[tokio::test]
+async fn totals_the_orders_by_region() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed(&app).await;
+
+    let response = app.get("/18/regions/total").send().await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(
+        response.json::<Value>(),
+        json!([
+            { "region": "Africa", "total": 5 },
+            { "region": "Asia", "total": 9 },
+            { "region": "Europe", "total": 19 },
+            { "region": "North America", "total": 15 },
+            { "region": "South America", "total": 10 }
+        ])
+    );
+}
+
+#[tokio::test]
+async fn lists_the_top_gifts_of_each_region() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed(&app).await;
+
+    let response = app.get("/18/regions/top_list/2").send().await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(
+        response.json::<Value>(),
+        json!([
+            { "region": "Africa", "top_gifts": ["Robot Lego Kit"] },
+            { "region": "Asia", "top_gifts": ["Drone"] },
+            { "region": "Europe", "top_gifts": ["Origami Set", "Yarn Ball"] },
+            { "region": "North America", "top_gifts": ["Action Figure", "Art Set"] },
+            { "region": "North Pole", "top_gifts": [] },
+            { "region": "Oceania", "top_gifts": [] },
+            { "region": "South America", "top_gifts": ["Teddy Bear"] }
+        ])
+    );
+}
+
+#[tokio::test]
+async fn reports_the_figures_of_each_region() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed(&app).await;
+
+    let response = app.get("/18/regions/analytics?top=2").send().await;
+    response.assert_status(StatusCode::OK);
+    let analytics = response.json::<Value>();
+    let regions: Vec<&str> = analytics
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|region| region["region"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        regions,
+        ["Africa", "Asia", "Europe", "North America", "South America"]
+    );
+    assert_eq!(
+        analytics[2],
+        json!({
+            "region": "Europe",
+            "bucket": null,
+            "orders": 3,
+            "total": 19,
+            "share": 19.0 / 58.0,
+            "distinct_gifts": 3,
+            "median_quantity": 6.0,
+            "p90_quantity": 7.6,
+            "top_gifts": [
+                { "gift_name": "Origami Set", "total": 8, "rank": 1 },
+                { "gift_name": "Yarn Ball", "total": 6, "rank": 2 }
+            ]
+        })
+    );
+}
+
+#[tokio::test]
+async fn buckets_the_figures_by_week() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed(&app).await;
+    sqlx::query(
+        "UPDATE orders SET created_at = CASE WHEN id <= 2
+            THEN '2023-12-05T10:00:00Z'::TIMESTAMPTZ
+            ELSE '2023-12-13T10:00:00Z'::TIMESTAMPTZ END",
+    )
+    .execute(&mut app.tenant_connection("default").await)
+    .await
+    .unwrap();
+
+    let response = app
+        .get("/18/regions/analytics?bucket=week&top=1")
+        .send()
+        .await;
+    response.assert_status(StatusCode::OK);
+    let analytics = response.json::<Value>();
+    let buckets: Vec<(&str, &str, i64, f64)> = analytics
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|region| {
+            (
+                region["bucket"].as_str().unwrap(),
+                region["region"].as_str().unwrap(),
+                region["total"].as_i64().unwrap(),
+                region["share"].as_f64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        buckets,
+        [
+            ("2023-12-04", "Europe", 13, 1.0),
+            ("2023-12-11", "Africa", 5, 5.0 / 45.0),
+            ("2023-12-11", "Asia", 9, 9.0 / 45.0),
+            ("2023-12-11", "Europe", 6, 6.0 / 45.0),
+            ("2023-12-11", "North America", 15, 15.0 / 45.0),
+            ("2023-12-11", "South America", 10, 10.0 / 45.0),
+        ]
+    );
+    assert_eq!(
+        analytics[0]["top_gifts"],
+        json!([{ "gift_name": "Origami Set", "total": 8, "rank": 1 }])
+    );
+
+    let response = app
+        .get("/18/regions/analytics?from=2023-12-10T00:00:00Z&to=2023-12-20T00:00:00Z")
+        .send()
+        .await;
+    let europe = &response.json::<Value>()[2];
+    assert_eq!(europe["region"], "Europe");
+    assert_eq!(europe["total"], 6);
+}
+
+#[tokio::test]
+async fn rejects_an_invalid_number_of_gifts() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+
+    app.get("/18/regions/analytics?top=-1")
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    app.get("/18/regions/analytics?bucket=month")
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+async fn seed_tree(app: &TestApp) {
+    let regions = json!([
+        { "id": 1, "name": "Europe" },
+        { "id": 2, "name": "Italy", "parent_id": 1 },
+        { "id": 3, "name": "Lombardy", "parent_id": 2 },
+        { "id": 4, "name": "Milan", "parent_id": 3 },
+        { "id": 5, "name": "France", "parent_id": 1 },
+        { "id": 6, "name": "Asia" }
+    ]);
+    let orders = json!([
+        { "id": 1, "region_id": 4, "gift_name": "Drone", "quantity": 5 },
+        { "id": 2, "region_id": 4, "gift_name": "Doll", "quantity": 2 },
+        { "id": 3, "region_id": 2, "gift_name": "Doll", "quantity": 4 },
+        { "id": 4, "region_id": 5, "gift_name": "Yarn Ball", "quantity": 3 },
+        { "id": 5, "region_id": 1, "gift_name": "Board Game", "quantity": 1 }
+    ]);
+    app.post("/18/regions")
+        .admin()
+        .json(&regions)
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    app.post("/18/orders")
+        .admin()
+        .json(&orders)
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+}
+
+#[tokio::test]
+async fn rolls_the_totals_up_the_region_tree() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed_tree(&app).await;
+
+    let response = app.get("/18/regions/tree?top=2").send().await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(
+        response.json::<Value>(),
+        json!([
+            { "id": 6, "name": "Asia", "own_total": 0, "total": 0, "top_gifts": [], "children": [] },
+            {
+                "id": 1, "name": "Europe", "own_total": 1, "total": 15,
+                "top_gifts": ["Doll", "Drone"],
+                "children": [
+                    {
+                        "id": 5, "name": "France", "own_total": 3, "total": 3,
+                        "top_gifts": ["Yarn Ball"], "children": []
+                    },
+                    {
+                        "id": 2, "name": "Italy", "own_total": 4, "total": 11,
+                        "top_gifts": ["Doll", "Drone"],
+                        "children": [{
+                            "id": 3, "name": "Lombardy", "own_total": 0, "total": 7,
+                            "top_gifts": ["Drone", "Doll"],
+                            "children": [{
+                                "id": 4, "name": "Milan", "own_total": 7, "total": 7,
+                                "top_gifts": ["Drone", "Doll"], "children": []
+                            }]
+                        }]
+                    }
+                ]
+            }
+        ])
+    );
+}
+
+#[tokio::test]
+async fn moves_a_subtree() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed_tree(&app).await;
+
+    let response = app
+        .put("/18/regions/3/parent")
+        .admin()
+        .json(&json!({ "parent_id": 5 }))
+        .send()
+        .await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(
+        response.json::<Value>(),
+        json!({ "id": 3, "name": "Lombardy", "parent_id": 5 })
+    );
+    let tree = app.get("/18/regions/tree").send().await.json::<Value>();
+    let europe = &tree[1]["children"];
+    assert_eq!(europe[0]["name"], "France");
+    assert_eq!(europe[0]["total"], 10);
+    assert_eq!(europe[0]["children"][0]["children"][0]["name"], "Milan");
+    assert_eq!(europe[1]["name"], "Italy");
+    assert_eq!(europe[1]["total"], 4);
+
+    let response = app
+        .put("/18/regions/1/parent")
+        .admin()
+        .json(&json!({}))
+        .send()
+        .await;
+    assert_eq!(
+        response.json::<Value>(),
+        json!({ "id": 1, "name": "Europe" })
+    );
+}
+
+#[tokio::test]
+async fn rejects_invalid_hierarchies() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed_tree(&app).await;
+
+    app.put("/18/regions/1/parent")
+        .admin()
+        .json(&json!({ "parent_id": 4 }))
+        .send()
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    app.put("/18/regions/1/parent")
+        .admin()
+        .json(&json!({ "parent_id": 1 }))
+        .send()
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    app.put("/18/regions/1/parent")
+        .admin()
+        .json(&json!({ "parent_id": 99 }))
+        .send()
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    app.put("/18/regions/99/parent")
+        .admin()
+        .json(&json!({ "parent_id": 1 }))
+        .send()
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    app.post("/18/regions")
+        .admin()
+        .json(&json!([
+            { "id": 7, "name": "Oceania" },
+            { "id": 8, "name": "Sydney", "parent_id": 99 }
+        ]))
+        .send()
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    let tree = app.get("/18/regions/tree").send().await.json::<Value>();
+    assert_eq!(tree.as_array().unwrap().len(), 2);
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use std::{net::SocketAddr, time::Duration};
+
+use axum::http::StatusCode;
+use common::TestApp;
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::{net::TcpStream, time::timeout};
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(2);
+
+async fn connect(addr: SocketAddr, path: &str) -> Socket {
+    let (socket, _) = connect_async(format!("ws://{}{}", addr, path))
+        .await
+        .expect("WebSocket should connect");
+    socket
+}
+
+async fn send_text(socket: &mut Socket, text: impl Into<String>) {
+    socket
+        .send(Message::Text(text.into()))
+        .await
+        .expect("Message should be sent");
+}
+
+async fn next_text(socket: &mut Socket) -> Option<String> {
+    loop {
+        match timeout(RECEIVE_TIMEOUT, socket.next()).await {
+            Ok(Some(Ok(Message::Text(text)))) => return Some(text),
+            Ok(Some(Ok(Message::Ping(_) | Message::Pong(_)))) => continue,
+            _ => return None,
+        }
+    }
+}
+
+#[tokio::test]
+async fn answers_pings_once_the_game_is_served() {
+    let app = TestApp::new();
+    let addr = app.serve().await;
+    let mut socket = connect(addr, "/19/ws/ping").await;
+
+    send_text(&mut socket, "ping").await;
+    send_text(&mut socket, "serve").await;
+    send_text(&mut socket, "ping").await;
+
+    assert_eq!(next_text(&mut socket).await.as_deref(), Some("pong"));
+}
+
+#[tokio::test]
+async fn broadcasts_messages_to_the_room_and_counts_views() {
+    let app = TestApp::new();
+    let addr = app.serve().await;
+
+    app.post("/19/reset")
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    let mut elf = connect(addr, "/19/ws/room/1/user/elf").await;
+    let mut santa = connect(addr, "/19/ws/room/1/user/santa").await;
+    let mut grinch = connect(addr, "/19/ws/room/2/user/grinch").await;
+    // Give the server time to subscribe every socket before broadcasting.
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    send_text(
+        &mut elf,
+        json!({ "message": "Merry Christmas!" }).to_string(),
+    )
+    .await;
+
+    let expected = json!({ "user": "elf", "message": "Merry Christmas!" });
+    for socket in [&mut elf, &mut santa] {
+        let text = next_text(socket)
+            .await
+            .expect("Message should be broadcast");
+        assert_eq!(serde_json::from_str::<Value>(&text).unwrap(), expected);
+    }
+    assert_eq!(app.get("/19/views").send().await.text(), "2");
+
+    send_text(
+        &mut grinch,
+        json!({ "message": "x".repeat(129) }).to_string(),
+    )
+    .await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(app.get("/19/views").send().await.text(), "2");
+
+    app.post("/19/reset").send().await;
+    assert_eq!(app.get("/19/views").send().await.text(), "0");
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use std::{fs, path::Path};
+
+use axum::http::StatusCode;
+use common::TestApp;
+use git2::{Repository, Signature};
+
+fn archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, content) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, path, *content)
+            .expect("File should be archived");
+    }
+    builder.into_inner().expect("Archive should be complete")
+}
+
+fn commit(repo: &Repository, author: &str, file: &str, content: &str) -> git2::Oid {
+    let workdir = repo.workdir().expect("Repository should have a workdir");
+    fs::write(workdir.join(file), content).expect("File should be written");
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new(file)).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = Signature::now(author, &format!("{}@northpole.com", author)).unwrap();
+    let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+    let parents = parent.iter().collect::<Vec<_>>();
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        &format!("Update {}", file),
+        &tree,
+        &parents,
+    )
+    .unwrap()
+}
+
+// Builds a repository whose christmas branch hid the cookie in its second commit.
+fn christmas_repository() -> (Vec<u8>, git2::Oid) {
+    let dir = tempfile::tempdir().unwrap();
+    let repo = Repository::init(dir.path()).unwrap();
+    repo.set_head("refs/heads/christmas").unwrap();
+    commit(&repo, "Santa", "README.md", "Presents list");
+    let cookie = commit(&repo, "Grinch", "santa.txt", "A COOKIE for Santa");
+    commit(&repo, "Elf", "santa.txt", "Nothing to see here");
+
+    let mut builder = tar::Builder::new(Vec::new());
+    builder.append_dir_all(".", dir.path()).unwrap();
+    (builder.into_inner().unwrap(), cookie)
+}
+
+#[tokio::test]
+async fn counts_the_files_in_an_archive() {
+    let app = TestApp::new();
+    let tar = archive(&[("a.txt", b"hello"), ("dir/b.txt", b"world!!")]);
+
+    let response = app.post("/20/archive_files").body(tar.clone()).send().await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(response.text(), "2");
+
+    let response = app.post("/20/archive_files_size").body(tar).send().await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(response.text(), "12");
+}
+
+#[tokio::test]
+async fn finds_who_hid_the_cookie() {
+    let app = TestApp::new();
+    let (tar, cookie) = christmas_repository();
+
+    let response = app.post("/20/cookie").body(tar).send().await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(response.text(), format!("Grinch {}", cookie));
+}
+
+#[tokio::test]
+async fn rejects_an_archive_without_a_repository() {
+    let app = TestApp::new();
+    let tar = archive(&[("santa.txt", b"COOKIE")]);
+
+    app.post("/20/cookie")
+        .body(tar)
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
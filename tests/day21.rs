@@ -0,0 +1,38 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+
+#[tokio::test]
+async fn converts_an_s2_cell_to_coordinates() {
+    let app = TestApp::new();
+
+    let response = app
+        .get("/21/coords/0100111110010011000110011001010101011111000010100011110001011011")
+        .send()
+        .await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(response.text(), "83°39'54.324''N 30°37'40.584''W");
+}
+
+#[tokio::test]
+async fn finds_the_country_of_an_s2_cell() {
+    let app = TestApp::new();
+
+    let response = app
+        .get("/21/country/0010000111110000011111100000111010111100000100111101111011000101")
+        .send()
+        .await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(response.text(), "Madagascar");
+}
+
+#[tokio::test]
+async fn rejects_a_cell_that_is_not_binary() {
+    let app = TestApp::new();
+
+    app.get("/21/coords/0123")
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+
+#[tokio::test]
+async fn finds_the_unpaired_integer() {
+    let app = TestApp::new();
+
+    let response = app
+        .post("/22/integers")
+        .body("888\n77\n888\n22\n77\n")
+        .send()
+        .await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(response.text(), "🎁".repeat(22));
+}
+
+#[tokio::test]
+async fn finds_the_shortest_path_to_the_last_star() {
+    let app = TestApp::new();
+    let star_map = "5\n0 1 0\n-2 2 3\n3 -3 -5\n1 1 5\n4 3 5\n4\n0 1\n2 4\n3 4\n1 2\n";
+
+    let response = app.post("/22/rocket").body(star_map).send().await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(response.text(), "3 26.123");
+}
+
+#[tokio::test]
+async fn rejects_a_malformed_star_map() {
+    let app = TestApp::new();
+
+    app.post("/22/rocket")
+        .body("2\n0 0\n")
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn refuses_to_wrap_too_many_presents() {
+    let app = TestApp::new();
+
+    app.post("/22/integers")
+        .body("99999999999\n")
+        .send()
+        .await
+        .assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+}
@@ -0,0 +1,346 @@
+mod common;
+
+use axum::http::StatusCode;
+use cch23::config::{Config, ContestConfig};
+use common::TestApp;
+use serde_json::{json, Value};
+
+#[tokio::test]
+async fn sums_the_strength_of_the_reindeers() {
+    let app = TestApp::new();
+    let reindeers = json!([
+        { "name": "Dasher", "strength": 5 },
+        { "name": "Dancer", "strength": 6 },
+        { "name": "Prancer", "strength": 4 },
+        { "name": "Vixen", "strength": 7 }
+    ]);
+
+    let response = app.post("/4/strength").json(&reindeers).send().await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(response.text(), "22");
+}
+
+#[tokio::test]
+async fn announces_the_contest_winners() {
+    let app = TestApp::new();
+    let reindeers = json!([
+        {
+            "name": "Dasher",
+            "strength": 5,
+            "speed": 50.4,
+            "height": 80,
+            "antler_width": 36,
+            "snow_magic_power": 9001,
+            "favorite_food": "hay",
+            "cAnD13s_3ATeN-yesT3rdAy": 2
+        },
+        {
+            "name": "Dancer",
+            "strength": 6,
+            "speed": 48.2,
+            "height": 65,
+            "antler_width": 37,
+            "snow_magic_power": 4004,
+            "favorite_food": "grass",
+            "cAnD13s_3ATeN-yesT3rdAy": 5
+        }
+    ]);
+
+    let response = app.post("/4/contest").json(&reindeers).send().await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(
+        response.json::<Value>(),
+        json!({
+            "fastest": "Speeding past the finish line with a strength of 5 is Dasher",
+            "tallest": "Dasher is standing tall with his 36 cm wide antlers",
+            "magician": "Dasher could blast you away with a snow magic power of 9001",
+            "consumer": "Dancer ate lots of candies, but also some grass"
+        })
+    );
+}
+
+#[tokio::test]
+async fn rejects_a_contest_without_reindeers() {
+    let app = TestApp::new();
+
+    let response = app.post("/4/contest").json(&json!([])).send().await;
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(response.headers["content-type"], "application/problem+json");
+    assert!(response.text().contains("At least one reindeer"));
+}
+
+fn incomplete_reindeers() -> Value {
+    json!([
+        {
+            "name": "Dasher",
+            "strength": 5,
+            "speed": 50.4,
+            "height": 80,
+            "snow_magic_power": 9001,
+            "cAnD13s_3ATeN-yesT3rdAy": 2
+        },
+        {
+            "name": "Dancer",
+            "strength": 6,
+            "height": 65,
+            "antler_width": 37,
+            "favorite_food": "grass"
+        },
+        {
+            "name": "Comet",
+            "strength": 4,
+            "speed": 40.0,
+            "height": 70,
+            "antler_width": 30,
+            "snow_magic_power": 100,
+            "favorite_food": "moss",
+            "cAnD13s_3ATeN-yesT3rdAy": 1
+        }
+    ])
+}
+
+#[tokio::test]
+async fn skips_reindeer_missing_stats_by_default() {
+    let app = TestApp::new();
+
+    let response = app
+        .post("/4/contest")
+        .json(&incomplete_reindeers())
+        .send()
+        .await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(
+        response.json::<Value>(),
+        json!({
+            "fastest": "Speeding past the finish line with a strength of 5 is Dasher",
+            "tallest": "Comet is standing tall with his 30 cm wide antlers",
+            "magician": "Dasher could blast you away with a snow magic power of 9001",
+            "consumer": "Comet ate lots of candies, but also some moss"
+        })
+    );
+
+    let nobody = json!([{ "name": "Dasher", "strength": 5, "speed": 50.4 }]);
+    let response = app.post("/4/contest").json(&nobody).send().await;
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(response.text().contains("the tallest category needs"));
+}
+
+#[tokio::test]
+async fn counts_missing_stats_as_zero() {
+    let app = TestApp::new();
+
+    let response = app
+        .post("/4/contest?missing=zero")
+        .json(&incomplete_reindeers())
+        .send()
+        .await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(
+        response.json::<Value>(),
+        json!({
+            "fastest": "Speeding past the finish line with a strength of 5 is Dasher",
+            "tallest": "Dasher is standing tall with his 0 cm wide antlers",
+            "magician": "Dasher could blast you away with a snow magic power of 9001",
+            "consumer": "Dasher ate lots of candies, but also some "
+        })
+    );
+}
+
+#[tokio::test]
+async fn rejects_every_reindeer_missing_stats() {
+    let app = TestApp::new();
+
+    let response = app
+        .post("/4/contest?missing=reject")
+        .json(&incomplete_reindeers())
+        .send()
+        .await;
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(response.headers["content-type"], "application/problem+json");
+    assert_eq!(
+        response.json::<Value>()["errors"],
+        json!([
+            {
+                "index": 0,
+                "name": "Dasher",
+                "reason": "Missing antler_width, favorite_food"
+            },
+            {
+                "index": 1,
+                "name": "Dancer",
+                "reason": "Missing speed, snow_magic_power, candies_eaten_yesterday"
+            }
+        ])
+    );
+}
+
+#[tokio::test]
+async fn reports_every_tied_winner() {
+    let app = TestApp::new();
+    let reindeers = json!([
+        {
+            "name": "Vixen",
+            "strength": 5,
+            "speed": 50.0,
+            "height": 80,
+            "antler_width": 36,
+            "snow_magic_power": 10,
+            "favorite_food": "hay",
+            "cAnD13s_3ATeN-yesT3rdAy": 2
+        },
+        {
+            "name": "Cupid",
+            "strength": 6,
+            "speed": 50.0,
+            "height": 70,
+            "antler_width": 30,
+            "snow_magic_power": 10,
+            "favorite_food": "grass",
+            "cAnD13s_3ATeN-yesT3rdAy": 1
+        }
+    ]);
+
+    let response = app.post("/4/contest").json(&reindeers).send().await;
+    response.assert_status(StatusCode::OK);
+    let result = response.json::<Value>();
+    assert_eq!(
+        result["fastest"],
+        "Speeding past the finish line with a strength of 5 is Vixen"
+    );
+    assert_eq!(
+        result["ties"],
+        json!({
+            "fastest": ["Vixen", "Cupid"],
+            "magician": ["Vixen", "Cupid"]
+        })
+    );
+}
+
+fn contest_config(categories: &str) -> ContestConfig {
+    toml::from_str::<Config>(categories)
+        .expect("Categories should parse")
+        .contest
+}
+
+#[tokio::test]
+async fn announces_the_configured_categories_too() {
+    let contest = contest_config(
+        r#"
+        [[contest.categories]]
+        name = "strongest"
+        score = "strength"
+        message = "{name} pulls the sleigh with a strength of {strength}"
+
+        [[contest.categories]]
+        name = "strongest_by_name"
+        score = "strength"
+        ties = "name"
+        message = "{name}"
+
+        [[contest.categories]]
+        name = "smallest"
+        score = "height"
+        direction = "min"
+        message = "{name} is only {height} cm tall"
+
+        [[contest.categories]]
+        name = "widest"
+        score = { strength = 2.0, antler_width = 1.0 }
+        message = "{name} scores {score}"
+        "#,
+    );
+    contest.check().unwrap();
+    let app = TestApp::configured(|config| config.contest = contest);
+    let reindeers = json!([
+        {
+            "name": "Prancer",
+            "strength": 6,
+            "speed": 45.0,
+            "height": 70,
+            "antler_width": 30,
+            "snow_magic_power": 100,
+            "favorite_food": "carrots",
+            "cAnD13s_3ATeN-yesT3rdAy": 1
+        },
+        {
+            "name": "Dasher",
+            "strength": 5,
+            "speed": 50.4,
+            "height": 80,
+            "antler_width": 36,
+            "snow_magic_power": 9001,
+            "favorite_food": "hay",
+            "cAnD13s_3ATeN-yesT3rdAy": 2
+        },
+        {
+            "name": "Dancer",
+            "strength": 6,
+            "speed": 48.2,
+            "height": 65,
+            "antler_width": 37,
+            "snow_magic_power": 4004,
+            "favorite_food": "grass",
+            "cAnD13s_3ATeN-yesT3rdAy": 5
+        }
+    ]);
+
+    let response = app.post("/4/contest").json(&reindeers).send().await;
+    response.assert_status(StatusCode::OK);
+    let result = response.json::<Value>();
+    assert_eq!(
+        result["fastest"],
+        "Speeding past the finish line with a strength of 5 is Dasher"
+    );
+    assert_eq!(
+        result["strongest"],
+        "Prancer pulls the sleigh with a strength of 6"
+    );
+    assert_eq!(result["strongest_by_name"], "Dancer");
+    assert_eq!(result["smallest"], "Dancer is only 65 cm tall");
+    assert_eq!(result["widest"], "Dancer scores 49");
+    assert_eq!(
+        result["ties"],
+        json!({
+            "strongest": ["Prancer", "Dancer"],
+            "strongest_by_name": ["Dancer", "Prancer"]
+        })
+    );
+    assert_eq!(result.as_object().unwrap().len(), 9);
+}
+
+#[test]
+fn rejects_invalid_categories() {
+    for (categories, error) in [
+        (
+            r#"[[contest.categories]]
+            name = "fastest"
+            score = "speed"
+            message = "{name}""#,
+            "already is a fastest",
+        ),
+        (
+            r#"[[contest.categories]]
+            name = "strongest"
+            score = "strength"
+            message = "{name} lifts {weight}""#,
+            "unknown field {weight}",
+        ),
+        (
+            r#"[[contest.categories]]
+            name = "nothing"
+            score = {}
+            message = "{name}""#,
+            "at least one",
+        ),
+        (
+            r#"[[contest.categories]]
+            name = "ties"
+            score = "strength"
+            message = "{name}""#,
+            "already is a ties",
+        ),
+    ] {
+        let err = contest_config(categories).check().unwrap_err();
+        assert!(err.to_string().contains(error), "{}", err);
+    }
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::json;
+
+fn names() -> serde_json::Value {
+    json!([
+        "Ava", "Caleb", "Mia", "Owen", "Lily", "Ethan", "Zoe", "Nolan", "Harper", "Lucas",
+        "Stella", "Mason", "Olivia"
+    ])
+}
+
+#[tokio::test]
+async fn returns_a_page_of_names() {
+    let app = TestApp::new();
+
+    let response = app.post("/5?offset=3&limit=5").json(&names()).send().await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(
+        response.text(),
+        r#"["Owen", "Lily", "Ethan", "Zoe", "Nolan"]"#
+    );
+}
+
+#[tokio::test]
+async fn defaults_to_every_name() {
+    let app = TestApp::new();
+
+    let response = app.post("/5?offset=9").json(&names()).send().await;
+    assert_eq!(response.text(), r#"["Lucas", "Stella", "Mason", "Olivia"]"#);
+}
+
+#[tokio::test]
+async fn splits_the_page_in_chunks() {
+    let app = TestApp::new();
+
+    let response = app.post("/5?offset=0&split=5").json(&names()).send().await;
+    assert_eq!(
+        response.text(),
+        r#"[["Ava", "Caleb", "Mia", "Owen", "Lily"], ["Ethan", "Zoe", "Nolan", "Harper", "Lucas"], ["Stella", "Mason", "Olivia"]]"#
+    );
+}
+
+#[tokio::test]
+async fn rejects_an_empty_split() {
+    let app = TestApp::new();
+
+    app.post("/5?split=0")
+        .json(&names())
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,42 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::{json, Value};
+
+#[tokio::test]
+async fn counts_elves_and_shelves() {
+    let app = TestApp::new();
+    let text = "there is an elf on a shelf on an elf. there is also another shelf in Belfast.";
+
+    let response = app.post("/6").body(text).send().await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(
+        response.json::<Value>(),
+        json!({
+            "elf": 5,
+            "elf on a shelf": 1,
+            "shelf with no elf on it": 1
+        })
+    );
+}
+
+#[tokio::test]
+async fn counts_around_non_ascii_text() {
+    let app = TestApp::new();
+
+    let response = app
+        .post("/6")
+        .body("élf, elf on a shelf ☃ shelf")
+        .send()
+        .await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(
+        response.json::<Value>(),
+        json!({
+            "elf": 3,
+            "elf on a shelf": 1,
+            "shelf with no elf on it": 1
+        })
+    );
+}
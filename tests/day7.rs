@@ -0,0 +1,104 @@
+mod common;
+
+use axum::http::StatusCode;
+use base64::{engine::general_purpose, Engine};
+use common::TestApp;
+use serde_json::{json, Value};
+
+fn recipe_cookie(recipe: &Value) -> String {
+    format!(
+        "recipe={}",
+        general_purpose::STANDARD.encode(recipe.to_string())
+    )
+}
+
+#[tokio::test]
+async fn decodes_the_recipe_cookie() {
+    let app = TestApp::new();
+
+    let response = app
+        .get("/7/decode")
+        .header(
+            "cookie",
+            "recipe=eyJmbG91ciI6MTAwLCJjaG9jb2xhdGUgY2hpcHMiOjIwfQ==",
+        )
+        .send()
+        .await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(response.text(), r#"{"flour":100,"chocolate chips":20}"#);
+}
+
+#[tokio::test]
+async fn rejects_a_missing_or_invalid_cookie() {
+    let app = TestApp::new();
+
+    app.get("/7/decode")
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    app.get("/7/decode")
+        .header("cookie", "recipe=not base64")
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn bakes_as_many_cookies_as_the_pantry_allows() {
+    let app = TestApp::new();
+    let recipe = json!({
+        "recipe": {
+            "flour": 95,
+            "sugar": 50,
+            "butter": 30,
+            "baking powder": 10,
+            "chocolate chips": 50
+        },
+        "pantry": {
+            "flour": 385,
+            "sugar": 507,
+            "butter": 2122,
+            "baking powder": 865,
+            "chocolate chips": 457
+        }
+    });
+
+    let response = app
+        .get("/7/bake")
+        .header("cookie", &recipe_cookie(&recipe))
+        .send()
+        .await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(
+        response.json::<Value>(),
+        json!({
+            "cookies": 4,
+            "pantry": {
+                "flour": 5,
+                "sugar": 307,
+                "butter": 2002,
+                "baking powder": 825,
+                "chocolate chips": 257
+            }
+        })
+    );
+}
+
+#[tokio::test]
+async fn ignores_ingredients_that_are_not_needed() {
+    let app = TestApp::new();
+    let recipe = json!({
+        "recipe": { "slime": 9, "sand": 0 },
+        "pantry": { "cobblestone": 64, "slime": 20 }
+    });
+
+    let response = app
+        .get("/7/bake")
+        .header("cookie", &recipe_cookie(&recipe))
+        .send()
+        .await;
+    assert_eq!(
+        response.json::<Value>(),
+        json!({ "cookies": 2, "pantry": { "cobblestone": 64, "slime": 2 } })
+    );
+}
@@ -0,0 +1,19 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+
+// The weights come from the PokéAPI, only the validation done before calling it is covered here.
+#[tokio::test]
+async fn rejects_an_invalid_pokedex_number() {
+    let app = TestApp::new();
+
+    app.get("/8/weight/pikachu")
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    app.get("/8/drop/-1")
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
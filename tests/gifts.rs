@@ -0,0 +1,326 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::{json, Value};
+
+// The catalog behaves the same on both backends, Postgres being skipped without a database.
+async fn apps() -> Vec<TestApp> {
+    let mut apps = vec![TestApp::in_memory()];
+    apps.extend(TestApp::with_database().await);
+    apps
+}
+
+async fn seed(app: &TestApp) {
+    app.post("/18/reset")
+        .admin()
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    app.post("/18/regions")
+        .admin()
+        .json(&json!([
+            { "id": 1, "name": "Europe" },
+            { "id": 2, "name": "Asia" }
+        ]))
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+}
+
+async fn add_orders(app: &TestApp, orders: Value) {
+    app.post("/13/orders")
+        .admin()
+        .json(&orders)
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+}
+
+async fn get(app: &TestApp, uri: &str) -> Value {
+    let response = app.get(uri).send().await;
+    response.assert_status(StatusCode::OK);
+    response.json()
+}
+
+async fn create_gift(app: &TestApp, gift: Value) -> Value {
+    let response = app.post("/gifts").admin().json(&gift).send().await;
+    response.assert_status(StatusCode::CREATED);
+    response.json()
+}
+
+#[tokio::test]
+async fn counts_spellings_of_a_name_as_one_gift() {
+    for app in apps().await {
+        seed(&app).await;
+        add_orders(
+            &app,
+            json!([
+                { "id": 1, "region_id": 1, "gift_name": "Toy Train", "quantity": 2 },
+                { "id": 2, "region_id": 1, "gift_name": "  toy   TRAIN ", "quantity": 2 },
+                { "id": 3, "region_id": 1, "gift_name": "Doll", "quantity": 3 }
+            ]),
+        )
+        .await;
+
+        assert_eq!(
+            get(&app, "/13/orders/popular").await,
+            json!({ "popular": "Toy Train" })
+        );
+        assert_eq!(
+            get(&app, "/18/regions/top_list/3").await,
+            json!([
+                { "region": "Asia", "top_gifts": [] },
+                { "region": "Europe", "top_gifts": ["Toy Train", "Doll"] }
+            ])
+        );
+    }
+}
+
+#[tokio::test]
+async fn resolves_names_and_aliases_to_the_catalog() {
+    for app in apps().await {
+        seed(&app).await;
+        add_orders(
+            &app,
+            json!([
+                { "id": 1, "region_id": 1, "gift_name": "choo choo", "quantity": 4 },
+                { "id": 2, "region_id": 2, "gift_name": "Doll", "quantity": 5 }
+            ]),
+        )
+        .await;
+
+        let gift = create_gift(
+            &app,
+            json!({
+                "name": " Toy  Train",
+                "aliases": ["Choo Choo", "toy train", "Train Set"],
+                "unit_price_cents": 1250,
+                "category": "Toys"
+            }),
+        )
+        .await;
+        assert_eq!(
+            gift,
+            json!({
+                "id": gift["id"],
+                "name": "Toy Train",
+                "aliases": ["Choo Choo", "Train Set"],
+                "unit_price_cents": 1250,
+                "category": "Toys"
+            })
+        );
+        add_orders(
+            &app,
+            json!([{ "id": 3, "region_id": 2, "gift_name": "TRAIN SET", "quantity": 2 }]),
+        )
+        .await;
+
+        assert_eq!(
+            get(&app, "/18/regions/top_list/2").await,
+            json!([
+                { "region": "Asia", "top_gifts": ["Doll", "Toy Train"] },
+                { "region": "Europe", "top_gifts": ["Toy Train"] }
+            ])
+        );
+        assert_eq!(
+            get(&app, "/13/orders/popular").await,
+            json!({ "popular": "Toy Train" })
+        );
+        assert_eq!(get(&app, "/gifts").await, json!([gift]));
+    }
+}
+
+#[tokio::test]
+async fn reports_revenue_and_categories() {
+    for app in apps().await {
+        seed(&app).await;
+        create_gift(
+            &app,
+            json!({ "name": "Toy Train", "unit_price_cents": 1250, "category": "Toys" }),
+        )
+        .await;
+        create_gift(
+            &app,
+            json!({ "name": "Sled", "unit_price_cents": 9900, "category": "Outdoor" }),
+        )
+        .await;
+        create_gift(&app, json!({ "name": "Doll", "unit_price_cents": 500 })).await;
+        add_orders(
+            &app,
+            json!([
+                { "id": 1, "region_id": 1, "gift_name": "Toy Train", "quantity": 10 },
+                { "id": 2, "region_id": 1, "gift_name": "Sled", "quantity": 1 },
+                { "id": 3, "region_id": 2, "gift_name": "Doll", "quantity": 4 },
+                { "id": 4, "region_id": 2, "gift_name": "Kite", "quantity": 20 }
+            ]),
+        )
+        .await;
+
+        assert_eq!(get(&app, "/13/orders/total").await, json!({ "total": 35 }));
+        assert_eq!(
+            get(&app, "/13/orders/total?by=revenue").await,
+            json!({ "total": 24400 })
+        );
+        assert_eq!(
+            get(&app, "/13/orders/popular").await,
+            json!({ "popular": "Kite" })
+        );
+        assert_eq!(
+            get(&app, "/13/orders/popular?by=revenue").await,
+            json!({ "popular": "Toy Train" })
+        );
+        assert_eq!(
+            get(&app, "/18/regions/total?by=revenue").await,
+            json!([
+                { "region": "Asia", "total": 2000 },
+                { "region": "Europe", "total": 22400 }
+            ])
+        );
+        assert_eq!(
+            get(&app, "/18/regions/top_list/1?by=revenue").await,
+            json!([
+                { "region": "Asia", "top_gifts": ["Doll"] },
+                { "region": "Europe", "top_gifts": ["Toy Train"] }
+            ])
+        );
+        assert_eq!(
+            get(&app, "/gifts/categories").await,
+            json!([
+                { "category": "Outdoor", "orders": 1, "quantity": 1, "revenue_cents": 9900 },
+                { "category": "Toys", "orders": 1, "quantity": 10, "revenue_cents": 12500 },
+                { "category": null, "orders": 2, "quantity": 24, "revenue_cents": 2000 }
+            ])
+        );
+    }
+}
+
+#[tokio::test]
+async fn orders_follow_their_gift() {
+    for app in apps().await {
+        seed(&app).await;
+        let gift = create_gift(
+            &app,
+            json!({ "name": "Toy Train", "aliases": ["Choo Choo"] }),
+        )
+        .await;
+        add_orders(
+            &app,
+            json!([{ "id": 1, "region_id": 1, "gift_name": "choo choo", "quantity": 4 }]),
+        )
+        .await;
+        let uri = format!("/gifts/{}", gift["id"]);
+
+        let response = app
+            .put(&uri)
+            .admin()
+            .json(&json!({ "name": "Train Set", "unit_price_cents": 300 }))
+            .send()
+            .await;
+        response.assert_status(StatusCode::OK);
+        assert_eq!(
+            get(&app, "/13/orders/popular").await,
+            json!({ "popular": "Train Set" })
+        );
+        assert_eq!(
+            get(&app, "/13/orders/total?by=revenue").await,
+            json!({ "total": 1200 })
+        );
+
+        app.delete(&uri)
+            .admin()
+            .send()
+            .await
+            .assert_status(StatusCode::NO_CONTENT);
+        app.get(&uri)
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+        assert_eq!(
+            get(&app, "/13/orders/popular").await,
+            json!({ "popular": "Train Set" })
+        );
+        assert_eq!(
+            get(&app, "/13/orders/total?by=revenue").await,
+            json!({ "total": 0 })
+        );
+    }
+}
+
+#[tokio::test]
+async fn rejects_invalid_and_conflicting_gifts() {
+    for app in apps().await {
+        seed(&app).await;
+        create_gift(
+            &app,
+            json!({ "name": "Toy Train", "aliases": ["Choo Choo"] }),
+        )
+        .await;
+
+        let response = app
+            .post("/gifts")
+            .admin()
+            .json(&json!({ "name": "Train", "aliases": ["CHOO choo"] }))
+            .send()
+            .await;
+        response.assert_status(StatusCode::CONFLICT);
+        assert_eq!(
+            response.json::<Value>()["detail"],
+            "Choo Choo already names the gift Toy Train"
+        );
+        for gift in [
+            json!({ "name": "   " }),
+            json!({ "name": "x".repeat(51) }),
+            json!({ "name": "Sled", "aliases": [""] }),
+            json!({ "name": "Sled", "unit_price_cents": -1 }),
+            json!({ "name": "Sled", "category": "" }),
+        ] {
+            app.post("/gifts")
+                .admin()
+                .json(&gift)
+                .send()
+                .await
+                .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+        }
+        app.put("/gifts/999")
+            .admin()
+            .json(&json!({ "name": "Sled" }))
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+        app.post("/gifts")
+            .json(&json!({ "name": "Sled" }))
+            .send()
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+        assert_eq!(get(&app, "/gifts").await.as_array().unwrap().len(), 1);
+    }
+}
+
+#[tokio::test]
+async fn finds_orders_by_any_name_of_their_gift() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed(&app).await;
+    create_gift(
+        &app,
+        json!({ "name": "Toy Train", "aliases": ["Choo Choo"] }),
+    )
+    .await;
+    add_orders(
+        &app,
+        json!([
+            { "id": 1, "region_id": 1, "gift_name": "toy train", "quantity": 4 },
+            { "id": 2, "region_id": 1, "gift_name": "Doll", "quantity": 1 }
+        ]),
+    )
+    .await;
+
+    let order = get(&app, "/orders/1").await;
+    assert_eq!(order["gift_name"], "Toy Train");
+    for name in ["choo%20choo", "TOY%20TRAIN"] {
+        let page = get(&app, &format!("/orders?gift_name={}", name)).await;
+        assert_eq!(page["orders"], json!([order]));
+    }
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::{json, Value};
+
+#[tokio::test]
+async fn is_alive_without_a_database() {
+    let app = TestApp::new();
+
+    let response = app.get("/healthz").send().await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(response.json::<Value>(), json!({ "status": "ok" }));
+
+    let response = app.get("/readyz").send().await;
+    response.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+    let report = response.json::<Value>();
+    assert_eq!(report["status"], "unavailable");
+    assert_eq!(report["database"]["status"], "unavailable");
+    assert_eq!(report["assets"]["status"], "ok");
+}
+
+#[tokio::test]
+async fn is_ready_once_migrated() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+
+    let response = app.get("/readyz").send().await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(
+        response.json::<Value>(),
+        json!({
+            "status": "ok",
+            "database": { "status": "ok" },
+            "migrations": { "status": "ok" },
+            "assets": { "status": "ok" }
+        })
+    );
+}
+
+#[tokio::test]
+async fn echoes_only_safe_request_ids() {
+    let app = TestApp::new();
+
+    let response = app
+        .get("/healthz")
+        .header("x-request-id", "client-42")
+        .send()
+        .await;
+    assert_eq!(response.headers["x-request-id"], "client-42");
+
+    for unsafe_id in ["has spaces", "quote\"d", &"a".repeat(65)] {
+        let response = app
+            .get("/healthz")
+            .header("x-request-id", unsafe_id)
+            .send()
+            .await;
+        let echoed = response.headers["x-request-id"].to_str().unwrap();
+        assert_ne!(echoed, unsafe_id);
+        assert_eq!(
+            echoed.len(),
+            26,
+            "expected a generated ulid, got {}",
+            echoed
+        );
+    }
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+
+fn limited_app() -> TestApp {
+    TestApp::configured_in_memory(|config| {
+        config.limits.rate_limit.enabled = true;
+        config.limits.rate_limit.requests_per_minute = 1;
+        config.limits.rate_limit.burst = 2;
+    })
+}
+
+#[tokio::test]
+async fn unknown_keys_share_the_client_bucket() {
+    let app = limited_app();
+
+    for attempt in 0..2 {
+        app.get("/")
+            .bearer(&format!("made-up-{}", attempt))
+            .send()
+            .await
+            .assert_status(StatusCode::OK);
+    }
+    let response = app.get("/").bearer("made-up-2").send().await;
+    response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+    assert!(response.headers.contains_key("retry-after"));
+}
+
+#[tokio::test]
+async fn known_keys_get_their_own_bucket() {
+    let app = limited_app();
+
+    app.get("/")
+        .admin()
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    app.get("/").send().await.assert_status(StatusCode::OK);
+    app.get("/")
+        .send()
+        .await
+        .assert_status(StatusCode::TOO_MANY_REQUESTS);
+    app.get("/")
+        .admin()
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+}
+
+#[tokio::test]
+async fn keys_are_not_looked_up_once_the_client_is_limited() {
+    let app = limited_app();
+
+    for _ in 0..2 {
+        app.get("/").send().await.assert_status(StatusCode::OK);
+    }
+    app.get("/")
+        .admin()
+        .send()
+        .await
+        .assert_status(StatusCode::TOO_MANY_REQUESTS);
+}
@@ -0,0 +1,180 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::json;
+
+// Last migration before orders and regions were constrained.
+const BEFORE_CONSTRAINTS: i64 = 20241020090000;
+// Last migration before the gift catalog.
+const BEFORE_CATALOG: i64 = 20241110090000;
+
+#[tokio::test]
+async fn constraining_orders_quarantines_invalid_rows() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    cch23::MIGRATOR
+        .undo(&app.pool, BEFORE_CONSTRAINTS)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO regions (id, name) VALUES (1, 'North Pole'), (2, NULL)")
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    sqlx::query(
+        "INSERT INTO orders (id, region_id, gift_name, quantity) VALUES
+            (1, 1, 'Toy Train', 5),
+            (2, NULL, 'Doll', 8),
+            (3, 1, NULL, 2),
+            (4, 2, 'Board Game', NULL),
+            (5, 1, 'Teddy Bear', -1),
+            (6, 9, 'Sled', 3)",
+    )
+    .execute(&app.pool)
+    .await
+    .unwrap();
+
+    cch23::migrate(&app.pool).await.unwrap();
+
+    // Rows stored before tenants belong to the default one.
+    let mut connection = app.tenant_connection("default").await;
+    let kept: Vec<i32> = sqlx::query_scalar("SELECT id FROM orders ORDER BY id")
+        .fetch_all(&mut connection)
+        .await
+        .unwrap();
+    assert_eq!(kept, [1]);
+    let quarantined: Vec<(i32, String)> =
+        sqlx::query_as("SELECT id, reason FROM orders_quarantine ORDER BY id")
+            .fetch_all(&app.pool)
+            .await
+            .unwrap();
+    assert_eq!(
+        quarantined,
+        [
+            (2, "missing_region".to_string()),
+            (3, "missing_gift_name".to_string()),
+            (4, "missing_quantity".to_string()),
+            (5, "negative_quantity".to_string()),
+            (6, "unknown_region".to_string()),
+        ]
+    );
+    let name: String = sqlx::query_scalar("SELECT name FROM regions WHERE id = 2")
+        .fetch_one(&mut connection)
+        .await
+        .unwrap();
+    assert_eq!(name, "Region 2");
+}
+
+#[tokio::test]
+async fn reverting_the_constraints_restores_quarantined_rows() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    sqlx::query(
+        "INSERT INTO orders_quarantine (id, region_id, gift_name, quantity, reason)
+            VALUES (7, 9, 'Sled', 3, 'unknown_region')",
+    )
+    .execute(&app.pool)
+    .await
+    .unwrap();
+
+    cch23::MIGRATOR
+        .undo(&app.pool, BEFORE_CONSTRAINTS)
+        .await
+        .unwrap();
+
+    let restored: Vec<i32> = sqlx::query_scalar("SELECT id FROM orders")
+        .fetch_all(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(restored, [7]);
+}
+
+#[tokio::test]
+async fn constraint_violations_are_unprocessable() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    let mut connection = app.tenant_connection("default").await;
+    let err = sqlx::query(
+        "INSERT INTO orders (id, region_id, gift_name, quantity) VALUES (1, 9, 'Sled', 3)",
+    )
+    .execute(&mut connection)
+    .await
+    .unwrap_err();
+    let err = cch23::error::AppError::from(err);
+    assert_eq!(err.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    // The constraint Postgres reports isn't shown to clients.
+    assert_eq!(
+        cch23::error::ProblemDetails::from(&err).detail,
+        "The request refers to a missing record or holds an invalid value"
+    );
+}
+
+#[tokio::test]
+async fn updates_touch_updated_at() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    app.post("/18/regions")
+        .admin()
+        .json(&json!([{ "id": 1, "name": "North Pole" }]))
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    app.put("/orders/1")
+        .admin()
+        .json(&json!({ "region_id": 1, "gift_name": "Sled", "quantity": 3 }))
+        .send()
+        .await
+        .assert_status(StatusCode::CREATED);
+
+    app.patch("/orders/1")
+        .admin()
+        .json(&json!({ "quantity": 4 }))
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+
+    let (created_at, updated_at): (String, String) =
+        sqlx::query_as("SELECT created_at::TEXT, updated_at::TEXT FROM orders WHERE id = 1")
+            .fetch_one(&mut app.tenant_connection("default").await)
+            .await
+            .unwrap();
+    assert!(updated_at > created_at);
+}
+
+#[tokio::test]
+async fn adding_the_catalog_merges_stored_spellings() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    cch23::MIGRATOR
+        .undo(&app.pool, BEFORE_CATALOG)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO regions (id, name) VALUES (1, 'North Pole')")
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    sqlx::query(
+        "INSERT INTO orders (id, region_id, gift_name, quantity) VALUES
+            (1, 1, 'Toy Train', 5),
+            (2, 1, 'toy  train', 8),
+            (3, 1, ' Sled ', 2)",
+    )
+    .execute(&app.pool)
+    .await
+    .unwrap();
+
+    cch23::migrate(&app.pool).await.unwrap();
+
+    let names: Vec<String> = sqlx::query_scalar("SELECT DISTINCT gift_name FROM orders ORDER BY 1")
+        .fetch_all(&mut app.tenant_connection("default").await)
+        .await
+        .unwrap();
+    assert_eq!(names.len(), 2);
+    assert_eq!(names[0], "Sled");
+    assert_eq!(names[1].to_lowercase(), "toy train");
+}
@@ -0,0 +1,205 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::{json, Value};
+
+async fn seed(app: &TestApp) {
+    let regions = json!([
+        { "id": 1, "name": "North Pole" },
+        { "id": 2, "name": "Europe" }
+    ]);
+    let orders = json!([
+        { "id": 1, "region_id": 1, "gift_name": "Toy Train", "quantity": 5 },
+        { "id": 2, "region_id": 2, "gift_name": "Doll", "quantity": 8 },
+        { "id": 3, "region_id": 1, "gift_name": "Doll", "quantity": 12 },
+        { "id": 4, "region_id": 2, "gift_name": "Board Game", "quantity": 5 },
+        { "id": 5, "region_id": 1, "gift_name": "Teddy Bear", "quantity": 1 }
+    ]);
+    app.post("/18/regions")
+        .admin()
+        .json(&regions)
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    app.post("/13/orders")
+        .admin()
+        .json(&orders)
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+}
+
+fn ids(page: &Value) -> Vec<i64> {
+    page["orders"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|order| order["id"].as_i64().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn filters_the_orders() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed(&app).await;
+
+    let page = app.get("/orders?region_id=1").send().await;
+    page.assert_status(StatusCode::OK);
+    assert_eq!(ids(&page.json()), [1, 3, 5]);
+
+    let page = app
+        .get("/orders?gift_name=Doll&min_quantity=10")
+        .send()
+        .await;
+    assert_eq!(ids(&page.json()), [3]);
+
+    let page = app
+        .get("/orders?min_quantity=2&max_quantity=8")
+        .send()
+        .await;
+    assert_eq!(ids(&page.json()), [1, 2, 4]);
+}
+
+#[tokio::test]
+async fn pages_through_sorted_orders() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed(&app).await;
+
+    let mut seen = Vec::new();
+    let mut uri = "/orders?sort=quantity&direction=desc&limit=2".to_string();
+    loop {
+        let page = app.get(&uri).send().await;
+        page.assert_status(StatusCode::OK);
+        let page = page.json::<Value>();
+        seen.extend(ids(&page));
+        match page["next_cursor"].as_str() {
+            Some(cursor) => {
+                uri = format!(
+                    "/orders?sort=quantity&direction=desc&limit=2&cursor={}",
+                    cursor
+                )
+            }
+            None => break,
+        }
+    }
+    // Orders 1 and 4 have the same quantity, the id decides.
+    assert_eq!(seen, [3, 2, 4, 1, 5]);
+}
+
+#[tokio::test]
+async fn rejects_a_cursor_from_another_sort() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed(&app).await;
+
+    let page = app.get("/orders?limit=1").send().await.json::<Value>();
+    let cursor = page["next_cursor"].as_str().unwrap();
+    app.get(&format!("/orders?sort=gift_name&cursor={}", cursor))
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    app.get("/orders?cursor=garbage")
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    app.get("/orders?limit=0")
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn reads_and_edits_a_single_order() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed(&app).await;
+
+    let order = app.get("/orders/2").send().await;
+    order.assert_status(StatusCode::OK);
+    assert_eq!(
+        order.json::<Value>(),
+        json!({ "id": 2, "region_id": 2, "gift_name": "Doll", "quantity": 8 })
+    );
+    app.get("/orders/42")
+        .send()
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    let replaced = app
+        .put("/orders/2")
+        .admin()
+        .json(&json!({ "region_id": 1, "gift_name": "Puzzle", "quantity": 3 }))
+        .send()
+        .await;
+    replaced.assert_status(StatusCode::OK);
+    assert_eq!(replaced.json::<Value>()["gift_name"], "Puzzle");
+
+    let created = app
+        .put("/orders/6")
+        .admin()
+        .json(&json!({ "region_id": 2, "gift_name": "Kite", "quantity": 2 }))
+        .send()
+        .await;
+    created.assert_status(StatusCode::CREATED);
+
+    let patched = app
+        .patch("/orders/6")
+        .admin()
+        .json(&json!({ "quantity": 7 }))
+        .send()
+        .await;
+    patched.assert_status(StatusCode::OK);
+    assert_eq!(
+        patched.json::<Value>(),
+        json!({ "id": 6, "region_id": 2, "gift_name": "Kite", "quantity": 7 })
+    );
+
+    app.delete("/orders/6")
+        .admin()
+        .send()
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+    app.delete("/orders/6")
+        .admin()
+        .send()
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn rejects_invalid_edits() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed(&app).await;
+
+    app.patch("/orders/1")
+        .admin()
+        .json(&json!({ "quantity": -1 }))
+        .send()
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    app.put("/orders/1")
+        .admin()
+        .json(&json!({ "region_id": 9, "gift_name": "Doll", "quantity": 1 }))
+        .send()
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    app.patch("/orders/42")
+        .admin()
+        .json(&json!({ "quantity": 1 }))
+        .send()
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    app.delete("/orders/1")
+        .send()
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,243 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::{json, Value};
+
+fn roster() -> Value {
+    json!([
+        {
+            "name": "Dasher",
+            "strength": 5,
+            "speed": 50.4,
+            "height": 80,
+            "antler_width": 36,
+            "snow_magic_power": 9001,
+            "favorite_food": "hay",
+            "cAnD13s_3ATeN-yesT3rdAy": 2
+        },
+        {
+            "name": "Dancer",
+            "strength": 6,
+            "speed": 48.2,
+            "height": 65,
+            "antler_width": 37,
+            "snow_magic_power": 4004,
+            "favorite_food": "grass",
+            "cAnD13s_3ATeN-yesT3rdAy": 5
+        }
+    ])
+}
+
+async fn register(app: &TestApp, reindeers: &Value) {
+    app.post("/reindeer")
+        .admin()
+        .json(reindeers)
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+}
+
+async fn hold(app: &TestApp, contest: Value) -> Value {
+    let response = app.post("/contests").admin().json(&contest).send().await;
+    response.assert_status(StatusCode::CREATED);
+    response.json::<Value>()
+}
+
+#[tokio::test]
+async fn registers_and_replaces_reindeer() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    register(&app, &roster()).await;
+    register(&app, &json!([{ "name": "Dasher", "strength": 7 }])).await;
+
+    let names = app.get("/reindeer").send().await.json::<Value>();
+    assert_eq!(names[0]["name"], "Dancer");
+    assert_eq!(names[1]["name"], "Dasher");
+    let dasher = app.get("/reindeer/Dasher").send().await.json::<Value>();
+    assert_eq!(dasher["strength"], 7);
+    assert_eq!(dasher["speed"], Value::Null);
+    assert_eq!(
+        app.get("/reindeer/Dancer").send().await.json::<Value>(),
+        roster()[1]
+    );
+
+    app.delete("/reindeer/Dasher")
+        .admin()
+        .send()
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+    app.get("/reindeer/Dasher")
+        .send()
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    app.post("/reindeer")
+        .admin()
+        .json(&json!([{ "name": "", "strength": 1 }]))
+        .send()
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    app.post("/reindeer")
+        .json(&roster())
+        .send()
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn records_contests_with_their_roster() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    register(&app, &roster()).await;
+
+    let everyone = hold(&app, json!({})).await;
+    assert_eq!(
+        everyone["result"],
+        json!({
+            "fastest": "Speeding past the finish line with a strength of 5 is Dasher",
+            "tallest": "Dasher is standing tall with his 36 cm wide antlers",
+            "magician": "Dasher could blast you away with a snow magic power of 9001",
+            "consumer": "Dancer ate lots of candies, but also some grass"
+        })
+    );
+    assert_eq!(everyone["reindeer"].as_array().unwrap().len(), 2);
+
+    // Later changes to the roster leave the recorded contest as it was.
+    register(
+        &app,
+        &json!([{ "name": "Dancer", "strength": 6, "favorite_food": "moss" }]),
+    )
+    .await;
+    let id = everyone["id"].as_i64().unwrap();
+    let recorded = app
+        .get(&format!("/contests/{}", id))
+        .send()
+        .await
+        .json::<Value>();
+    assert_eq!(recorded["reindeer"][0], roster()[1]);
+    assert_eq!(recorded["result"], everyone["result"]);
+
+    let subset = hold(&app, json!({ "reindeer": ["Dasher", "Dasher"] })).await;
+    assert_eq!(subset["reindeer"].as_array().unwrap().len(), 1);
+    let response = app
+        .post("/contests")
+        .admin()
+        .json(&json!({ "reindeer": ["Dasher", "Rudolph"] }))
+        .send()
+        .await;
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(response.text().contains("Rudolph"));
+
+    let contests = app.get("/contests").send().await.json::<Value>();
+    assert_eq!(contests[0]["id"], subset["id"]);
+    assert_eq!(contests[1]["id"], everyone["id"]);
+    let limited = app.get("/contests?limit=1").send().await.json::<Value>();
+    assert_eq!(limited.as_array().unwrap().len(), 1);
+    app.get("/contests/999")
+        .send()
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn ranks_the_winners_of_each_category() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    register(&app, &roster()).await;
+    hold(&app, json!({})).await;
+    hold(&app, json!({})).await;
+    hold(&app, json!({ "reindeer": ["Dancer"] })).await;
+
+    let board = app
+        .get("/contests/leaderboard?category=magician")
+        .send()
+        .await
+        .json::<Value>();
+    let wins = board
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| {
+            (
+                entry["reindeer"].as_str().unwrap(),
+                entry["wins"].as_i64().unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(wins, [("Dasher", 2), ("Dancer", 1)]);
+    assert_eq!(board[0]["category"], "magician");
+
+    let everything = app
+        .get("/contests/leaderboard")
+        .send()
+        .await
+        .json::<Value>();
+    assert_eq!(everything.as_array().unwrap().len(), 7);
+    let before = app
+        .get("/contests/leaderboard?to=2000-01-01T00:00:00Z")
+        .send()
+        .await
+        .json::<Value>();
+    assert_eq!(before, json!([]));
+
+    // Other tenants have a leaderboard of their own.
+    let other = app
+        .get("/contests/leaderboard")
+        .tenant("north")
+        .send()
+        .await
+        .json::<Value>();
+    assert_eq!(other, json!([]));
+}
+
+#[tokio::test]
+async fn records_every_tied_winner_and_how_stats_were_missing() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    register(&app, &roster()).await;
+    register(
+        &app,
+        &json!([{ "name": "Comet", "strength": 5, "speed": 50.4 }]),
+    )
+    .await;
+
+    let skipped = hold(&app, json!({})).await;
+    assert_eq!(skipped["missing"], "skip");
+    assert_eq!(
+        skipped["result"]["ties"]["fastest"],
+        json!(["Comet", "Dasher"])
+    );
+    let board = app
+        .get("/contests/leaderboard?category=fastest")
+        .send()
+        .await
+        .json::<Value>();
+    let winners = board
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["reindeer"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(winners, ["Comet", "Dasher"]);
+
+    let zero = hold(&app, json!({ "missing": "zero" })).await;
+    let recorded = app
+        .get(&format!("/contests/{}", zero["id"]))
+        .send()
+        .await
+        .json::<Value>();
+    assert_eq!(recorded["missing"], "zero");
+
+    let response = app
+        .post("/contests")
+        .admin()
+        .json(&json!({ "missing": "reject" }))
+        .send()
+        .await;
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(response.json::<Value>()["errors"][0]["name"], "Comet");
+}
@@ -0,0 +1,323 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::{json, Value};
+
+// Runs the same requests against an app, collecting each status and body so that backends can be
+// compared. The detail of problems is left out, since the memory backend words the rejections that
+// Postgres reports with a fixed detail.
+async fn scenario(app: &TestApp) -> Vec<(StatusCode, Value)> {
+    let mut responses = Vec::new();
+    let mut record = |response: common::TestResponse| {
+        let mut body = if response.text().is_empty() {
+            Value::Null
+        } else {
+            response.json::<Value>()
+        };
+        if let Some(problem) = body
+            .as_object_mut()
+            .filter(|body| body.contains_key("title"))
+        {
+            problem.remove("detail");
+        }
+        responses.push((response.status, body));
+    };
+
+    record(app.post("/18/reset").admin().send().await);
+    record(
+        app.post("/18/regions")
+            .admin()
+            .json(&json!([
+                { "id": 1, "name": "Europe" },
+                { "id": 2, "name": "Italy", "parent_id": 1 },
+                { "id": 3, "name": "Lapland", "parent_id": 1 },
+                { "id": 4, "name": "Asia" },
+                { "id": 5, "name": "Oceania" }
+            ]))
+            .send()
+            .await,
+    );
+    // A duplicate id stores none of the batch.
+    record(
+        app.post("/18/regions")
+            .admin()
+            .json(&json!([
+                { "id": 6, "name": "Africa" },
+                { "id": 1, "name": "Europe" }
+            ]))
+            .send()
+            .await,
+    );
+    record(
+        app.post("/18/regions")
+            .admin()
+            .json(&json!([{ "id": 7, "name": "Atlantis", "parent_id": 99 }]))
+            .send()
+            .await,
+    );
+    // Ids that don't fit the INT column are rejected rather than truncated.
+    record(
+        app.post("/18/regions")
+            .admin()
+            .json(&json!([{ "id": 4294967297i64, "name": "Lemuria" }]))
+            .send()
+            .await,
+    );
+    record(
+        app.put("/18/regions/2/parent")
+            .admin()
+            .json(&json!({ "parent_id": 4294967297i64 }))
+            .send()
+            .await,
+    );
+
+    record(
+        app.post("/13/orders")
+            .admin()
+            .json(&json!([
+                { "id": 1, "region_id": 2, "gift_name": "Toy Train", "quantity": 5 },
+                { "id": 2, "region_id": 3, "gift_name": "Sled", "quantity": 4 },
+                { "id": 3, "region_id": 42, "gift_name": "Doll", "quantity": 1 }
+            ]))
+            .send()
+            .await,
+    );
+    record(
+        app.post("/13/orders?mode=skip_invalid")
+            .admin()
+            .json(&json!([
+                { "id": 1, "region_id": 2, "gift_name": "Toy Train", "quantity": 5 },
+                { "id": 2, "region_id": 3, "gift_name": "Sled", "quantity": 4 },
+                { "id": 2, "region_id": 3, "gift_name": "Sled", "quantity": 4 },
+                { "id": 3, "region_id": 42, "gift_name": "Doll", "quantity": 1 },
+                { "id": 4, "region_id": 4, "gift_name": "Kite", "quantity": -1 },
+                { "id": 5, "region_id": 4, "gift_name": "Kite", "quantity": 9 },
+                { "id": 6, "region_id": 1, "gift_name": "Doll", "quantity": 4 }
+            ]))
+            .send()
+            .await,
+    );
+    record(
+        app.post("/13/orders?mode=skip_invalid")
+            .admin()
+            .json(&json!([
+                { "id": 1, "region_id": 2, "gift_name": "Yo-yo", "quantity": 1 }
+            ]))
+            .send()
+            .await,
+    );
+    record(
+        app.post("/13/orders?mode=upsert")
+            .admin()
+            .json(&json!([
+                { "id": 1, "region_id": 2, "gift_name": "Toy Train", "quantity": 8 },
+                { "id": 7, "region_id": 5, "gift_name": "Sled", "quantity": 1 }
+            ]))
+            .send()
+            .await,
+    );
+
+    record(app.get("/13/orders/total").send().await);
+    record(app.get("/13/orders/popular").send().await);
+    record(app.get("/18/regions/total").send().await);
+    record(app.get("/18/regions/top_list/2").send().await);
+    record(app.get("/18/regions/tree?top=2").send().await);
+    record(app.get("/18/regions/analytics?top=2").send().await);
+
+    record(
+        app.put("/18/regions/1/parent")
+            .admin()
+            .json(&json!({ "parent_id": 3 }))
+            .send()
+            .await,
+    );
+    record(
+        app.put("/18/regions/1/parent")
+            .admin()
+            .json(&json!({ "parent_id": 99 }))
+            .send()
+            .await,
+    );
+    record(
+        app.put("/18/regions/99/parent")
+            .admin()
+            .json(&json!({ "parent_id": null }))
+            .send()
+            .await,
+    );
+    record(
+        app.put("/18/regions/3/parent")
+            .admin()
+            .json(&json!({ "parent_id": 4 }))
+            .send()
+            .await,
+    );
+    record(app.get("/18/regions/tree?top=1").send().await);
+
+    record(app.post("/13/reset").admin().send().await);
+    record(app.get("/13/orders/total").send().await);
+    record(app.get("/13/orders/popular").send().await);
+    record(app.get("/18/regions/tree").send().await);
+    responses
+}
+
+#[tokio::test]
+async fn memory_backend_answers_like_postgres() {
+    let Some(postgres) = TestApp::with_database().await else {
+        return;
+    };
+    let expected = scenario(&postgres).await;
+    let actual = scenario(&TestApp::in_memory()).await;
+
+    assert_eq!(actual.len(), expected.len());
+    for (index, (actual, expected)) in actual.iter().zip(&expected).enumerate() {
+        assert_eq!(actual, expected, "response {} differs", index);
+    }
+}
+
+#[tokio::test]
+async fn memory_backend_runs_without_a_database() {
+    let app = TestApp::in_memory();
+    let responses = scenario(&app).await;
+
+    assert!(responses
+        .iter()
+        .all(|(status, _)| !status.is_server_error()));
+    app.get("/18/regions/total")
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+}
+
+#[tokio::test]
+async fn memory_backend_only_accepts_the_bootstrap_key() {
+    let app = TestApp::in_memory();
+
+    app.post("/18/regions")
+        .bearer("not-a-key")
+        .json(&json!([{ "id": 1, "name": "Europe" }]))
+        .send()
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+    app.post("/18/regions")
+        .admin()
+        .json(&json!([{ "id": 1, "name": "Europe" }]))
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+}
+
+#[tokio::test]
+async fn memory_backend_rejects_snapshot_resets() {
+    let app = TestApp::in_memory();
+
+    app.post("/18/regions")
+        .admin()
+        .json(&json!([{ "id": 1, "name": "Europe" }]))
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    app.post("/13/reset?snapshot=before")
+        .admin()
+        .send()
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Nothing was deleted.
+    app.post("/18/regions")
+        .admin()
+        .json(&json!([{ "id": 1, "name": "Europe" }]))
+        .send()
+        .await
+        .assert_status(StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn memory_backend_rejects_out_of_range_region_ids() {
+    let app = TestApp::in_memory();
+
+    app.post("/18/regions")
+        .admin()
+        .json(&json!([{ "id": 4294967297i64, "name": "Lemuria" }]))
+        .send()
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        app.get("/18/regions/tree").send().await.json::<Value>(),
+        json!([])
+    );
+}
+
+#[tokio::test]
+async fn memory_backend_leaves_out_the_postgres_routes() {
+    let app = TestApp::in_memory();
+
+    for uri in [
+        "/orders",
+        "/orders/export",
+        "/snapshots",
+        "/reindeer",
+        "/audit",
+        "/13/orders/feed",
+    ] {
+        app.get(uri)
+            .admin()
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+    }
+}
+
+// Names that only differ by case, or that sort differently byte by byte than in a locale, so the
+// tie-breaks of both backends get compared.
+async fn tied_names(app: &TestApp) -> Vec<Value> {
+    app.post("/18/reset").admin().send().await;
+    app.post("/18/regions")
+        .admin()
+        .json(&json!([
+            { "id": 1, "name": "europe" },
+            { "id": 2, "name": "Asia" },
+            { "id": 3, "name": "africa", "parent_id": 1 },
+            { "id": 4, "name": "Africa", "parent_id": 1 }
+        ]))
+        .send()
+        .await;
+    app.post("/13/orders")
+        .admin()
+        .json(&json!([
+            { "id": 1, "region_id": 1, "gift_name": "apple", "quantity": 2 },
+            { "id": 2, "region_id": 1, "gift_name": "Sled", "quantity": 2 },
+            { "id": 3, "region_id": 1, "gift_name": "sled", "quantity": 2 },
+            { "id": 4, "region_id": 2, "gift_name": "banana", "quantity": 2 },
+            { "id": 5, "region_id": 3, "gift_name": "Sled", "quantity": 2 },
+            { "id": 6, "region_id": 4, "gift_name": "apple", "quantity": 2 }
+        ]))
+        .send()
+        .await;
+
+    let mut bodies = Vec::new();
+    for path in [
+        "/13/orders/popular",
+        "/18/regions/total",
+        "/18/regions/top_list/2",
+        "/18/regions/tree?top=2",
+        "/18/regions/analytics?top=2",
+    ] {
+        let response = app.get(path).send().await;
+        response.assert_status(StatusCode::OK);
+        bodies.push(response.json::<Value>());
+    }
+    bodies
+}
+
+#[tokio::test]
+async fn memory_backend_breaks_ties_like_postgres() {
+    let Some(postgres) = TestApp::with_database().await else {
+        return;
+    };
+    let expected = tied_names(&postgres).await;
+    let actual = tied_names(&TestApp::in_memory()).await;
+
+    assert_eq!(actual, expected);
+}
@@ -0,0 +1,223 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::{json, Value};
+
+async fn seed(app: &TestApp) {
+    let regions = json!([
+        { "id": 1, "name": "North Pole" },
+        { "id": 2, "name": "Europe" }
+    ]);
+    let orders = json!([
+        { "id": 1, "region_id": 1, "gift_name": "Toy Train", "quantity": 5 },
+        { "id": 2, "region_id": 2, "gift_name": "Doll", "quantity": 8 },
+        { "id": 3, "region_id": 1, "gift_name": "Board Game", "quantity": 2 }
+    ]);
+    app.post("/18/regions")
+        .admin()
+        .json(&regions)
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    app.post("/13/orders")
+        .admin()
+        .json(&orders)
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+}
+
+async fn total(app: &TestApp) -> Value {
+    app.get("/13/orders/total").send().await.json::<Value>()["total"].clone()
+}
+
+#[tokio::test]
+async fn saves_and_restores_a_snapshot() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed(&app).await;
+
+    let response = app
+        .post("/snapshots")
+        .admin()
+        .json(&json!({ "name": "christmas-eve" }))
+        .send()
+        .await;
+    response.assert_status(StatusCode::CREATED);
+    let snapshot = response.json::<Value>();
+    assert_eq!(snapshot["name"], "christmas-eve");
+    assert_eq!(snapshot["orders"], 3);
+    assert_eq!(snapshot["regions"], 2);
+
+    app.post("/13/reset")
+        .admin()
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    assert_eq!(total(&app).await, 0);
+
+    let response = app
+        .post("/snapshots/christmas-eve/restore")
+        .admin()
+        .send()
+        .await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(total(&app).await, 15);
+    let regions = app.get("/18/regions/total").send().await.json::<Value>();
+    assert_eq!(
+        regions,
+        json!([
+            { "region": "Europe", "total": 8 },
+            { "region": "North Pole", "total": 7 }
+        ])
+    );
+
+    let snapshots = app.get("/snapshots").send().await.json::<Value>();
+    assert_eq!(snapshots.as_array().unwrap().len(), 1);
+    app.delete("/snapshots/christmas-eve")
+        .admin()
+        .send()
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+    app.get("/snapshots/christmas-eve")
+        .send()
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn resets_into_a_snapshot() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed(&app).await;
+
+    app.post("/18/reset?snapshot=before-reset")
+        .admin()
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    assert_eq!(total(&app).await, 0);
+    let snapshot = app
+        .get("/snapshots/before-reset")
+        .send()
+        .await
+        .json::<Value>();
+    assert_eq!(snapshot["orders"], 3);
+
+    // A taken name leaves the data in place.
+    seed(&app).await;
+    app.post("/13/reset?snapshot=before-reset")
+        .admin()
+        .send()
+        .await
+        .assert_status(StatusCode::CONFLICT);
+    assert_eq!(total(&app).await, 15);
+}
+
+#[tokio::test]
+async fn diffs_snapshots() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed(&app).await;
+    app.post("/snapshots")
+        .admin()
+        .json(&json!({ "name": "first" }))
+        .send()
+        .await
+        .assert_status(StatusCode::CREATED);
+
+    app.delete("/orders/1")
+        .admin()
+        .send()
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+    app.patch("/orders/2")
+        .admin()
+        .json(&json!({ "quantity": 9 }))
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    app.put("/orders/4")
+        .admin()
+        .json(&json!({ "region_id": 2, "gift_name": "Sled", "quantity": 1 }))
+        .send()
+        .await
+        .assert_status(StatusCode::CREATED);
+
+    let expected = json!({
+        "added": [{ "id": 4, "region_id": 2, "gift_name": "Sled", "quantity": 1 }],
+        "removed": [{ "id": 1, "region_id": 1, "gift_name": "Toy Train", "quantity": 5 }],
+        "changed": [{
+            "before": { "id": 2, "region_id": 2, "gift_name": "Doll", "quantity": 8 },
+            "after": { "id": 2, "region_id": 2, "gift_name": "Doll", "quantity": 9 }
+        }]
+    });
+    let response = app.get("/snapshots/first/diff").send().await;
+    response.assert_status(StatusCode::OK);
+    let mut diff = response.json::<Value>();
+    assert_eq!(diff["to"], Value::Null);
+    diff.as_object_mut().unwrap().remove("from");
+    diff.as_object_mut().unwrap().remove("to");
+    assert_eq!(diff, expected);
+
+    app.post("/snapshots")
+        .admin()
+        .json(&json!({ "name": "second" }))
+        .send()
+        .await
+        .assert_status(StatusCode::CREATED);
+    let mut diff = app
+        .get("/snapshots/first/diff?to=second")
+        .send()
+        .await
+        .json::<Value>();
+    assert_eq!(diff["to"], "second");
+    diff.as_object_mut().unwrap().remove("from");
+    diff.as_object_mut().unwrap().remove("to");
+    assert_eq!(diff, expected);
+
+    app.get("/snapshots/first/diff?to=third")
+        .send()
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn rejects_invalid_snapshots() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+
+    app.post("/snapshots")
+        .admin()
+        .json(&json!({ "name": "no spaces" }))
+        .send()
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    app.post("/snapshots")
+        .admin()
+        .json(&json!({ "name": "empty" }))
+        .send()
+        .await
+        .assert_status(StatusCode::CREATED);
+    app.post("/snapshots")
+        .admin()
+        .json(&json!({ "name": "empty" }))
+        .send()
+        .await
+        .assert_status(StatusCode::CONFLICT);
+    app.post("/snapshots/missing/restore")
+        .admin()
+        .send()
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    app.post("/snapshots")
+        .json(&json!({ "name": "anonymous" }))
+        .send()
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,180 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::{json, Value};
+
+async fn seed(app: &TestApp, tenant: &str, region: &str, quantity: i32) {
+    app.post("/18/regions")
+        .admin()
+        .tenant(tenant)
+        .json(&json!([{ "id": 1, "name": region }]))
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    app.post("/13/orders")
+        .admin()
+        .tenant(tenant)
+        .json(&json!([
+            { "id": 1, "region_id": 1, "gift_name": "Sled", "quantity": quantity }
+        ]))
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+}
+
+async fn total(app: &TestApp, tenant: &str) -> Value {
+    let response = app.get("/13/orders/total").tenant(tenant).send().await;
+    response.assert_status(StatusCode::OK);
+    response.json::<Value>()["total"].clone()
+}
+
+// Both tenants use the same ids without seeing each other, and a reset only empties its own.
+async fn isolates_tenants(app: &TestApp) {
+    seed(app, "north", "North Pole", 5).await;
+    seed(app, "south", "South Pole", 8).await;
+
+    assert_eq!(total(app, "north").await, 5);
+    assert_eq!(total(app, "south").await, 8);
+    let regions = app
+        .get("/18/regions/total")
+        .tenant("south")
+        .send()
+        .await
+        .json::<Value>();
+    assert_eq!(regions, json!([{ "region": "South Pole", "total": 8 }]));
+    // Requests without the header use the default tenant.
+    let default = app.get("/13/orders/total").send().await.json::<Value>();
+    assert_eq!(default["total"], 0);
+
+    app.post("/13/reset")
+        .admin()
+        .tenant("north")
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    assert_eq!(total(app, "north").await, 0);
+    assert_eq!(total(app, "south").await, 8);
+}
+
+#[tokio::test]
+async fn isolates_tenants_in_the_database() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    isolates_tenants(&app).await;
+}
+
+#[tokio::test]
+async fn isolates_tenants_in_memory() {
+    isolates_tenants(&TestApp::in_memory()).await;
+}
+
+#[tokio::test]
+async fn rejects_invalid_tenants() {
+    let app = TestApp::in_memory();
+    let response = app
+        .get("/13/orders/total")
+        .tenant("North Pole")
+        .send()
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+    assert!(response.text().contains("Tenants have at most"));
+}
+
+#[tokio::test]
+async fn keys_bound_to_a_tenant_only_reach_it() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    let created = app
+        .post("/admin/api-keys")
+        .admin()
+        .json(&json!({ "name": "north-writer", "role": "writer", "tenant": "north" }))
+        .send()
+        .await;
+    created.assert_status(StatusCode::CREATED);
+    let created = created.json::<Value>();
+    assert_eq!(created["tenant"], "north");
+    let key = created["key"].as_str().unwrap();
+
+    // Without the header, the key stores into its own tenant.
+    app.post("/18/regions")
+        .bearer(key)
+        .json(&json!([{ "id": 1, "name": "North Pole" }]))
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    let response = app
+        .post("/18/regions")
+        .bearer(key)
+        .tenant("south")
+        .json(&json!([{ "id": 2, "name": "South Pole" }]))
+        .send()
+        .await;
+    response.assert_status(StatusCode::FORBIDDEN);
+
+    let regions = "SELECT name FROM regions";
+    let north: Vec<String> = sqlx::query_scalar(regions)
+        .fetch_all(&mut app.tenant_connection("north").await)
+        .await
+        .unwrap();
+    assert_eq!(north, ["North Pole"]);
+    let south: Vec<String> = sqlx::query_scalar(regions)
+        .fetch_all(&mut app.tenant_connection("south").await)
+        .await
+        .unwrap();
+    assert!(south.is_empty());
+}
+
+#[tokio::test]
+async fn policies_hide_other_tenants_from_plain_queries() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed(&app, "north", "North Pole", 5).await;
+
+    let count = "SELECT COUNT(*) FROM orders";
+    let north: i64 = sqlx::query_scalar(count)
+        .fetch_one(&mut app.tenant_connection("north").await)
+        .await
+        .unwrap();
+    assert_eq!(north, 1);
+    let south: i64 = sqlx::query_scalar(count)
+        .fetch_one(&mut app.tenant_connection("south").await)
+        .await
+        .unwrap();
+    assert_eq!(south, 0);
+    // Without a tenant, no rows are visible and none can be stored.
+    let unset: i64 = sqlx::query_scalar(count)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(unset, 0);
+    sqlx::query("INSERT INTO regions (id, name) VALUES (2, 'Nowhere')")
+        .execute(&app.pool)
+        .await
+        .unwrap_err();
+}
+
+#[tokio::test]
+async fn refuses_roles_that_bypass_the_policies() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    assert!(cch23::tenant::ensure_isolated(&app.pool).await.is_ok());
+
+    // The user of the test database URL only needs to create roles, it may not be a superuser.
+    let url = std::env::var("TEST_DATABASE_URL").unwrap();
+    let admin = sqlx::PgPool::connect(&url).await.unwrap();
+    let bypasses = sqlx::query_scalar::<_, bool>(
+        "SELECT rolsuper OR rolbypassrls FROM pg_roles WHERE rolname = current_user",
+    )
+    .fetch_one(&admin)
+    .await
+    .unwrap();
+    assert_eq!(
+        cch23::tenant::ensure_isolated(&admin).await.is_err(),
+        bypasses
+    );
+}
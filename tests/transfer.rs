@@ -0,0 +1,262 @@
+mod common;
+
+use axum::{body::Body, http::StatusCode};
+use common::TestApp;
+use serde_json::{json, Value};
+
+const REGIONS_CSV: &str = "id,name,parent_id\n1,Europe,\n2,Italy,1\n3,Asia,\n";
+
+async fn seed(app: &TestApp) {
+    app.post("/regions/import")
+        .admin()
+        .header("content-type", "text/csv")
+        .body(REGIONS_CSV)
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    app.post("/orders/import")
+        .admin()
+        .header("content-type", "application/x-ndjson")
+        .body(concat!(
+            r#"{"id":1,"region_id":2,"gift_name":"Toy Train","quantity":5}"#,
+            "\n",
+            r#"{"id":2,"region_id":3,"gift_name":"Doll","quantity":8}"#,
+            "\n",
+        ))
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+}
+
+#[tokio::test]
+async fn imports_regions_and_orders() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+
+    let response = app
+        .post("/regions/import")
+        .admin()
+        .header("content-type", "text/csv")
+        .body(REGIONS_CSV)
+        .send()
+        .await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(response.json::<Value>(), json!({ "inserted": 3 }));
+
+    let response = app
+        .post("/orders/import?mode=skip_invalid")
+        .admin()
+        .header("content-type", "text/csv")
+        .body("id,region_id,gift_name,quantity\n1,2,Toy Train,5\n2,9,Doll,8\n3,1,\"Board Game, deluxe\",2\n")
+        .send()
+        .await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(
+        response.json::<Value>(),
+        json!({
+            "mode": "skip_invalid",
+            "committed": true,
+            "inserted": 2,
+            "updated": 0,
+            "rejected": [{ "index": 1, "id": 2, "reason": "unknown_region" }]
+        })
+    );
+    let order = app.get("/orders/3").send().await.json::<Value>();
+    assert_eq!(order["gift_name"], "Board Game, deluxe");
+}
+
+#[tokio::test]
+async fn imports_large_bodies_in_one_transaction() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed(&app).await;
+
+    // Well beyond one parsing chunk and the default body limit, with a duplicate at the very end.
+    let mut body = String::new();
+    for id in 10..50_010 {
+        body.push_str(&format!(
+            "{{\"id\":{},\"region_id\":1,\"gift_name\":\"Gift number {}\",\"quantity\":1}}\n",
+            id, id
+        ));
+    }
+    let with_duplicate = format!(
+        "{}{}\n",
+        body, r#"{"id":1,"region_id":1,"gift_name":"Sled","quantity":1}"#
+    );
+    assert!(with_duplicate.len() > 2 * 1024 * 1024);
+
+    let response = app
+        .post("/orders/import")
+        .admin()
+        .header("content-type", "application/x-ndjson")
+        .body(with_duplicate)
+        .send()
+        .await;
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    let report = response.json::<Value>();
+    assert_eq!(report["committed"], false);
+    assert_eq!(
+        report["rejected"],
+        json!([{ "index": 50_000, "id": 1, "reason": "duplicate_id" }])
+    );
+    let total = app.get("/13/orders/total").send().await.json::<Value>();
+    assert_eq!(total["total"], 13);
+
+    let response = app
+        .post("/orders/import")
+        .admin()
+        .header("content-type", "application/x-ndjson")
+        .body(body)
+        .send()
+        .await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(response.json::<Value>()["inserted"], 50_000);
+}
+
+#[tokio::test]
+async fn rejects_malformed_imports() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed(&app).await;
+
+    let response = app
+        .post("/orders/import")
+        .admin()
+        .header("content-type", "application/x-ndjson")
+        .body(concat!(
+            r#"{"id":5,"region_id":1,"gift_name":"Sled","quantity":1}"#,
+            "\n",
+            r#"{"id":6,"region_id":1,"#,
+            "\n",
+        ))
+        .send()
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+    let response = app.get("/orders/5").send().await;
+    response.assert_status(StatusCode::NOT_FOUND);
+
+    app.post("/orders/import")
+        .admin()
+        .json(&json!([]))
+        .send()
+        .await
+        .assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    app.post("/regions/import")
+        .admin()
+        .header("content-type", "text/csv")
+        .body("id,name,parent_id\n4,Oceania,\n5,Sydney,99\n")
+        .send()
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    app.post("/regions/import")
+        .admin()
+        .header("content-type", "text/csv")
+        .body("id,name\n1,Europe\n")
+        .send()
+        .await
+        .assert_status(StatusCode::CONFLICT);
+    app.post("/regions/import")
+        .header("content-type", "text/csv")
+        .body(Body::from("id,name\n7,Africa\n"))
+        .send()
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn exports_in_the_accepted_format() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed(&app).await;
+
+    let response = app.get("/orders/export").send().await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(response.headers["content-type"], "application/json");
+    assert_eq!(
+        response.json::<Value>(),
+        json!([
+            { "id": 1, "region_id": 2, "gift_name": "Toy Train", "quantity": 5 },
+            { "id": 2, "region_id": 3, "gift_name": "Doll", "quantity": 8 }
+        ])
+    );
+
+    let response = app
+        .get("/orders/export")
+        .header("accept", "text/csv;q=0.5, application/x-ndjson")
+        .send()
+        .await;
+    assert_eq!(response.headers["content-type"], "application/x-ndjson");
+    assert_eq!(
+        response.text(),
+        concat!(
+            r#"{"id":1,"region_id":2,"gift_name":"Toy Train","quantity":5}"#,
+            "\n",
+            r#"{"id":2,"region_id":3,"gift_name":"Doll","quantity":8}"#,
+            "\n",
+        )
+    );
+
+    let response = app
+        .get("/regions/export")
+        .header("accept", "text/csv")
+        .send()
+        .await;
+    assert_eq!(response.headers["content-type"], "text/csv");
+    assert_eq!(
+        response.text(),
+        "id,name,parent_id\n1,Europe,\n3,Asia,\n2,Italy,1\n"
+    );
+
+    app.get("/orders/export")
+        .header("accept", "application/xml")
+        .send()
+        .await
+        .assert_status(StatusCode::NOT_ACCEPTABLE);
+}
+
+#[tokio::test]
+async fn exports_the_region_reports() {
+    let Some(app) = TestApp::with_database().await else {
+        return;
+    };
+    seed(&app).await;
+
+    let response = app
+        .get("/18/regions/total")
+        .header("accept", "text/csv")
+        .send()
+        .await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(response.text(), "region,total\nAsia,8\nItaly,5\n");
+
+    let response = app
+        .get("/18/regions/top_list/2")
+        .header("accept", "text/csv")
+        .send()
+        .await;
+    assert_eq!(
+        response.text(),
+        "region,position,gift_name\nAsia,1,Doll\nItaly,1,Toy Train\n"
+    );
+
+    let response = app
+        .get("/18/regions/top_list/2")
+        .header("accept", "application/x-ndjson")
+        .send()
+        .await;
+    assert_eq!(
+        response.text(),
+        concat!(
+            r#"{"region":"Asia","top_gifts":["Doll"]}"#,
+            "\n",
+            r#"{"region":"Europe","top_gifts":[]}"#,
+            "\n",
+            r#"{"region":"Italy","top_gifts":["Toy Train"]}"#,
+            "\n",
+        )
+    );
+}